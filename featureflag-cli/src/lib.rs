@@ -0,0 +1,392 @@
+//! Command-line tool for inspecting feature flag registries.
+//!
+//! The registry of a build is exported with `featureflag::registry::export_json`,
+//! and can then be listed with the `cargo featureflag` command:
+//!
+//! ```text
+//! cargo featureflag list registry.json [--owner OWNER] [--expired] [--today YYYY-MM-DD] [--fail-on-expired]
+//! ```
+//!
+//! Flags are listed with their default value, `owner` metadata, and expiration
+//! status based on the `expires` metadata, formatted as `YYYY-MM-DD`.
+
+use std::{
+    fmt,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value;
+
+const USAGE: &str = "usage: cargo featureflag list <registry.json> [--owner OWNER] [--expired] [--today YYYY-MM-DD] [--fail-on-expired]";
+
+/// A feature flag in an exported registry.
+///
+/// A flag that is defined in multiple places has one definition per place,
+/// see [`group_by_name`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flag {
+    name: String,
+    default: Option<bool>,
+    default_expr: String,
+    module_path: String,
+    file: String,
+    line: u32,
+    metadata: Vec<(String, String)>,
+}
+
+impl Flag {
+    /// Get the name of the flag.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the default value of the flag, if it is a literal.
+    pub fn default(&self) -> Option<bool> {
+        self.default
+    }
+
+    /// Get the source code of the default value expression.
+    pub fn default_expr(&self) -> &str {
+        &self.default_expr
+    }
+
+    /// Get the module path where the flag is defined.
+    pub fn module_path(&self) -> &str {
+        &self.module_path
+    }
+
+    /// Get the file and line where the flag is defined.
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+
+    /// Get a metadata value of the flag by key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the owner of the flag, from the `owner` metadata.
+    pub fn owner(&self) -> Option<&str> {
+        self.get("owner")
+    }
+
+    /// Get the expiration date of the flag, from the `expires` metadata.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `expires` metadata is not a valid date.
+    pub fn expires(&self) -> Result<Option<Date>, String> {
+        self.get("expires").map(Date::parse).transpose()
+    }
+}
+
+/// Parse a registry exported with `featureflag::registry::export_json`.
+///
+/// # Errors
+///
+/// Returns an error if the input is not valid JSON, or doesn't have the
+/// structure of a registry export.
+pub fn parse_registry(input: &str) -> Result<Vec<Flag>, String> {
+    let Value::Array(items) = serde_json::from_str(input).map_err(|err| err.to_string())? else {
+        return Err("expected an array of flags".to_string());
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let field = |key: &str| {
+                item.get(key)
+                    .ok_or_else(|| format!("flag {i}: missing field {key:?}"))
+            };
+            let string = |key: &str| {
+                field(key)?
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("flag {i}: expected string for {key:?}"))
+            };
+
+            let metadata = match field("metadata")? {
+                Value::Object(fields) => fields
+                    .iter()
+                    .map(|(key, value)| {
+                        value
+                            .as_str()
+                            .map(|value| (key.clone(), value.to_string()))
+                            .ok_or_else(|| {
+                                format!("flag {i}: expected string for metadata {key:?}")
+                            })
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(format!("flag {i}: expected object for \"metadata\"")),
+            };
+
+            Ok(Flag {
+                name: string("name")?,
+                default: match field("default")? {
+                    Value::Null => None,
+                    value => Some(
+                        value
+                            .as_bool()
+                            .ok_or_else(|| format!("flag {i}: expected boolean for \"default\""))?,
+                    ),
+                },
+                default_expr: string("default_expr")?,
+                module_path: string("module_path")?,
+                file: string("file")?,
+                line: field("line")?
+                    .as_u64()
+                    .and_then(|line| u32::try_from(line).ok())
+                    .ok_or_else(|| format!("flag {i}: expected integer for \"line\""))?,
+                metadata,
+            })
+        })
+        .collect()
+}
+
+/// Group flag definitions by name, keeping the order of first appearance.
+pub fn group_by_name(flags: &[Flag]) -> Vec<(&str, Vec<&Flag>)> {
+    let mut groups = Vec::<(&str, Vec<&Flag>)>::new();
+    for flag in flags {
+        match groups.iter_mut().find(|(name, _)| *name == flag.name) {
+            Some((_, definitions)) => definitions.push(flag),
+            None => groups.push((&flag.name, vec![flag])),
+        }
+    }
+    groups
+}
+
+/// A calendar date, used for flag expiration.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    /// Parse a date formatted as `YYYY-MM-DD`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the date is not formatted correctly, or is out of range.
+    pub fn parse(input: &str) -> Result<Date, String> {
+        let error = || format!("invalid date {input:?}, expected YYYY-MM-DD");
+
+        let mut parts = input.splitn(3, '-');
+        let mut part = |len: usize| {
+            parts
+                .next()
+                .filter(|part| part.len() == len && part.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|part| part.parse::<u32>().ok())
+                .ok_or_else(error)
+        };
+        let (year, month, day) = (part(4)?, part(2)?, part(2)?);
+
+        if !(1..=12).contains(&month) || !(1..=days_in_month(year as i32, month)).contains(&day) {
+            return Err(error());
+        }
+
+        Ok(Date {
+            year: year as i32,
+            month,
+            day,
+        })
+    }
+
+    /// Get the current date in UTC.
+    pub fn today() -> Date {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        Date::from_days((secs / 86400) as i64)
+    }
+
+    /// Convert days since the Unix epoch to a date.
+    fn from_days(days: i64) -> Date {
+        // civil date algorithm from https://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Date { year, month, day }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+struct Options {
+    path: String,
+    owner: Option<String>,
+    expired_only: bool,
+    fail_on_expired: bool,
+    today: Option<Date>,
+}
+
+/// Parse the command-line arguments, or return `None` if `--help` is passed.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut args = args.into_iter().peekable();
+
+    // `cargo featureflag` passes the subcommand name as the first argument
+    args.next_if(|arg| arg == "featureflag");
+    args.next_if(|arg| arg == "list");
+
+    let mut path = None;
+    let mut options = Options {
+        path: String::new(),
+        owner: None,
+        expired_only: false,
+        fail_on_expired: false,
+        today: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--owner" => options.owner = Some(args.next().ok_or(USAGE)?),
+            "--expired" => options.expired_only = true,
+            "--fail-on-expired" => options.fail_on_expired = true,
+            "--today" => options.today = Some(Date::parse(&args.next().ok_or(USAGE)?)?),
+            "-h" | "--help" => return Ok(None),
+            _ if arg.starts_with('-') || path.is_some() => {
+                return Err(format!("unexpected argument {arg:?}\n{USAGE}"));
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    options.path = path.ok_or(USAGE)?;
+    Ok(Some(options))
+}
+
+/// Run the command-line tool with the given arguments, excluding the program
+/// name, writing the output to `out`.
+///
+/// # Errors
+///
+/// Returns an error message if the arguments are invalid, the registry could
+/// not be loaded, or if `--fail-on-expired` is passed and any of the listed
+/// flags have expired.
+pub fn run<W: Write>(args: impl IntoIterator<Item = String>, out: &mut W) -> Result<(), String> {
+    let Some(options) = parse_args(args)? else {
+        return writeln!(out, "{USAGE}").map_err(|err| err.to_string());
+    };
+
+    let input = std::fs::read_to_string(&options.path)
+        .map_err(|err| format!("failed to read {}: {err}", options.path))?;
+    let flags = parse_registry(&input).map_err(|err| format!("{}: {err}", options.path))?;
+    let today = options.today.unwrap_or_else(Date::today);
+
+    let mut rows = vec![[
+        "NAME".to_string(),
+        "DEFAULT".to_string(),
+        "OWNER".to_string(),
+        "EXPIRES".to_string(),
+        "STATUS".to_string(),
+        "LOCATION".to_string(),
+    ]];
+    let mut expired_count = 0;
+
+    for (name, definitions) in group_by_name(&flags) {
+        // metadata is usually only given where the flag is declared
+        let primary = definitions
+            .iter()
+            .find(|flag| !flag.metadata.is_empty())
+            .unwrap_or(&definitions[0]);
+
+        let defaults = definitions
+            .iter()
+            .map(|flag| flag.default_expr())
+            .collect::<Vec<_>>();
+        let default = if defaults.iter().all(|default| *default == defaults[0]) {
+            defaults[0].to_string()
+        } else {
+            "<conflict>".to_string()
+        };
+
+        let expires = primary
+            .expires()
+            .map_err(|err| format!("flag {name:?}: {err}"))?;
+        let expired = expires.is_some_and(|expires| expires < today);
+
+        if options.expired_only && !expired {
+            continue;
+        }
+        if let Some(owner) = &options.owner {
+            if primary.owner() != Some(owner.as_str()) {
+                continue;
+            }
+        }
+        if expired {
+            expired_count += 1;
+        }
+
+        let mut location = primary.location();
+        if definitions.len() > 1 {
+            location += &format!(" (+{})", definitions.len() - 1);
+        }
+
+        rows.push([
+            name.to_string(),
+            default,
+            primary.owner().unwrap_or("-").to_string(),
+            expires.map_or("-".to_string(), |expires| expires.to_string()),
+            match expires {
+                Some(_) if expired => "expired".to_string(),
+                Some(_) => "active".to_string(),
+                None => "-".to_string(),
+            },
+            location,
+        ]);
+    }
+
+    write_table(out, &rows).map_err(|err| err.to_string())?;
+
+    if options.fail_on_expired && expired_count > 0 {
+        return Err(format!("{expired_count} expired flags"));
+    }
+    Ok(())
+}
+
+fn write_table<W: Write, const N: usize>(out: &mut W, rows: &[[String; N]]) -> std::io::Result<()> {
+    let mut widths = [0; N];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in rows {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+            if i + 1 == N {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{cell:width$}  "));
+            }
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,14 @@
+//! `cargo featureflag` command, see the [`featureflag_cli`] crate.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut stdout = std::io::stdout().lock();
+    match featureflag_cli::run(std::env::args().skip(1), &mut stdout) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
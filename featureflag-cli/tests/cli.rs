@@ -0,0 +1,131 @@
+#![allow(missing_docs)]
+
+use featureflag_cli::{Date, parse_registry, run};
+
+const REGISTRY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/registry.json");
+
+fn run_with(args: &[&str]) -> (Result<(), String>, String) {
+    let mut out = Vec::new();
+    let result = run(args.iter().map(|arg| arg.to_string()), &mut out);
+    (result, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn test_list() {
+    let (result, out) = run_with(&["featureflag", "list", REGISTRY, "--today", "2024-06-01"]);
+    result.unwrap();
+
+    assert_eq!(
+        out,
+        concat!(
+            "NAME               DEFAULT                 OWNER      EXPIRES     STATUS   LOCATION\n",
+            "checkout.new_flow  false                   payments   2024-03-31  expired  src/checkout.rs:12 (+1)\n",
+            "search.\"fuzzy\"     cfg!(debug_assertions)  discovery  2024-12-31  active   src/search.rs:7\n",
+            "ui.dark_mode       true                    -          -           -        src/ui.rs:3\n",
+        )
+    );
+}
+
+#[test]
+fn test_filters() {
+    let (result, out) = run_with(&[REGISTRY, "--today", "2024-06-01", "--owner", "discovery"]);
+    result.unwrap();
+    assert_eq!(out.lines().skip(1).collect::<Vec<_>>().len(), 1);
+    assert!(out.contains("search.\"fuzzy\""));
+
+    let (result, out) = run_with(&[REGISTRY, "--today", "2024-06-01", "--expired"]);
+    result.unwrap();
+    let names = out
+        .lines()
+        .skip(1)
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["checkout.new_flow"]);
+
+    let (result, _) = run_with(&[REGISTRY, "--today", "2024-06-01", "--fail-on-expired"]);
+    assert_eq!(result, Err("1 expired flags".to_string()));
+
+    let (result, _) = run_with(&[REGISTRY, "--today", "2024-01-01", "--fail-on-expired"]);
+    result.unwrap();
+
+    // only the listed flags are counted
+    let (result, _) = run_with(&[
+        REGISTRY,
+        "--today",
+        "2024-06-01",
+        "--owner",
+        "discovery",
+        "--fail-on-expired",
+    ]);
+    result.unwrap();
+}
+
+#[test]
+fn test_help() {
+    let (result, out) = run_with(&["featureflag", "list", "--help"]);
+    result.unwrap();
+    assert!(out.starts_with("usage:"));
+}
+
+#[test]
+fn test_errors() {
+    assert!(run_with(&[]).0.unwrap_err().starts_with("usage:"));
+    assert!(run_with(&["a.json", "b.json"]).0.is_err());
+    assert!(run_with(&[REGISTRY, "--today", "2024-02-30"]).0.is_err());
+    assert!(
+        run_with(&["does-not-exist.json"])
+            .0
+            .unwrap_err()
+            .starts_with("failed to read")
+    );
+
+    assert!(parse_registry("{}").is_err());
+    assert!(parse_registry("[{\"name\": \"a\"}]").is_err());
+    assert_eq!(parse_registry("[]"), Ok(Vec::new()));
+    assert!(parse_registry("[\"\\ud83d\"]").is_err());
+}
+
+#[test]
+fn test_parse_escapes() {
+    let flags = parse_registry(
+        r#"[{
+            "name": "\b\f\u00e9\ud83d\ude00",
+            "default": true,
+            "default_expr": "true",
+            "module_path": "m",
+            "file": "src/lib.rs",
+            "line": 1,
+            "metadata": {}
+        }]"#,
+    )
+    .unwrap();
+    assert_eq!(flags[0].name(), "\u{8}\u{c}é😀");
+}
+
+#[test]
+fn test_date() {
+    assert_eq!(Date::parse("2024-02-29").unwrap().to_string(), "2024-02-29");
+    assert!(Date::parse("2023-02-29").is_err());
+    assert!(Date::parse("2024-1-01").is_err());
+    assert!(Date::parse("2024-01-01-x").is_err());
+    assert!(Date::today() > Date::parse("2024-01-01").unwrap());
+}
+
+#[allow(dead_code)]
+fn func() {
+    featureflag::feature!("exported", true, owner = "tools", expires = "2030-01-01");
+    featureflag::is_enabled!("exported", true);
+}
+
+#[test]
+fn test_parse_export() {
+    let flags = parse_registry(&featureflag::registry::export_json()).unwrap();
+    assert_eq!(flags.len(), 2);
+
+    let flag = flags.iter().find(|flag| flag.owner().is_some()).unwrap();
+    assert_eq!(flag.name(), "exported");
+    assert_eq!(flag.default(), Some(true));
+    assert_eq!(flag.module_path(), "cli");
+    assert_eq!(flag.expires(), Ok(Some(Date::parse("2030-01-01").unwrap())));
+    assert!(flag.location().starts_with(file!()));
+}
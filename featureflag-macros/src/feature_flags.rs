@@ -0,0 +1,229 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use syn::{
+    Data, DeriveInput, Expr, Fields, LitStr, Token,
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+};
+
+use crate::utils::crate_name;
+
+pub fn derive_feature_flags(input: DeriveInput) -> syn::Result<impl ToTokens> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`FeatureFlags` can only be derived for enums",
+        ));
+    };
+
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`FeatureFlags` requires at least one variant",
+        ));
+    }
+
+    let featureflag = crate_name("featureflag");
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut variants = Vec::new();
+    let mut names = Vec::new();
+    let mut features = Vec::new();
+
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "`FeatureFlags` variants can't have fields",
+            ));
+        }
+
+        let mut name = None;
+        let mut default = None;
+        for attr in &variant.attrs {
+            if !attr.path().is_ident("featureflag") {
+                continue;
+            }
+
+            for arg in
+                attr.parse_args_with(Punctuated::<VariantArg, Token![,]>::parse_terminated)?
+            {
+                match arg {
+                    VariantArg::Name(value) if name.is_none() => name = Some(value),
+                    VariantArg::Default(value) if default.is_none() => default = Some(value),
+                    VariantArg::Name(value) => {
+                        return Err(syn::Error::new_spanned(value, "duplicate `name` argument"));
+                    }
+                    VariantArg::Default(value) => {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            "duplicate `default` argument",
+                        ));
+                    }
+                }
+            }
+        }
+
+        let name = name.unwrap_or_else(|| {
+            LitStr::new(
+                &snake_case(&variant.ident.to_string()),
+                variant.ident.span(),
+            )
+        });
+        let default = default.unwrap_or_else(|| parse_quote! { false });
+
+        let variant = &variant.ident;
+        features.push(quote! {
+            Self::#variant => #featureflag::feature!(#name, #default)
+        });
+        names.push(quote! { Self::#variant => #name });
+        variants.push(quote! { Self::#variant });
+    }
+
+    let body: TokenStream = quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// All feature flags in this enum.
+            pub const ALL: &'static [Self] = &[#( #variants ),*];
+
+            /// Iterate over all feature flags in this enum.
+            pub fn iter() -> impl ::core::iter::Iterator<Item = &'static Self> {
+                Self::ALL.iter()
+            }
+
+            /// Get the name of the feature flag.
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    #( #names, )*
+                }
+            }
+
+            /// Get the feature flag definition.
+            pub fn feature(&self) -> #featureflag::Feature<'static> {
+                match self {
+                    #( #features, )*
+                }
+            }
+
+            /// Check if the feature flag is enabled in the current context.
+            pub fn is_enabled(&self) -> bool {
+                self.feature().is_enabled()
+            }
+
+            /// Check if the feature flag is enabled in the given context.
+            pub fn is_enabled_in(&self, context: ::core::option::Option<&#featureflag::context::Context>) -> bool {
+                self.feature().is_enabled_in(context)
+            }
+        }
+    };
+
+    Ok(body)
+}
+
+enum VariantArg {
+    Name(LitStr),
+    Default(Expr),
+}
+
+impl Parse for VariantArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse::<syn::Ident>()?;
+        input.parse::<Token![=]>()?;
+
+        if key == "name" {
+            Ok(Self::Name(input.parse()?))
+        } else if key == "default" {
+            Ok(Self::Default(input.parse()?))
+        } else {
+            Err(syn::Error::new(key.span(), "expected `name` or `default`"))
+        }
+    }
+}
+
+/// Convert a `CamelCase` variant name to a `snake_case` flag name.
+fn snake_case(ident: &str) -> String {
+    let chars = ident.chars().collect::<Vec<_>>();
+    let mut name = String::with_capacity(ident.len() + 4);
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                name.push('_');
+            }
+        }
+        name.extend(c.to_lowercase());
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::{ToTokens, quote};
+
+    use super::{derive_feature_flags, snake_case};
+
+    #[test]
+    fn test_derive_feature_flags() {
+        let input = syn::parse_quote! {
+            enum Flags {
+                NewUi,
+                #[featureflag(name = "fast-path", default = true)]
+                FastPath,
+            }
+        };
+
+        let expanded = derive_feature_flags(input).unwrap().into_token_stream();
+
+        let expected = quote! {
+            impl Flags {
+                /// All feature flags in this enum.
+                pub const ALL: &'static [Self] = &[Self::NewUi, Self::FastPath];
+
+                /// Iterate over all feature flags in this enum.
+                pub fn iter() -> impl ::core::iter::Iterator<Item = &'static Self> {
+                    Self::ALL.iter()
+                }
+
+                /// Get the name of the feature flag.
+                pub const fn name(&self) -> &'static str {
+                    match self {
+                        Self::NewUi => "new_ui",
+                        Self::FastPath => "fast-path",
+                    }
+                }
+
+                /// Get the feature flag definition.
+                pub fn feature(&self) -> ::featureflag::Feature<'static> {
+                    match self {
+                        Self::NewUi => ::featureflag::feature!("new_ui", false),
+                        Self::FastPath => ::featureflag::feature!("fast-path", true),
+                    }
+                }
+
+                /// Check if the feature flag is enabled in the current context.
+                pub fn is_enabled(&self) -> bool {
+                    self.feature().is_enabled()
+                }
+
+                /// Check if the feature flag is enabled in the given context.
+                pub fn is_enabled_in(&self, context: ::core::option::Option<&::featureflag::context::Context>) -> bool {
+                    self.feature().is_enabled_in(context)
+                }
+            }
+        };
+
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(snake_case("NewUi"), "new_ui");
+        assert_eq!(snake_case("HTTPClient"), "http_client");
+        assert_eq!(snake_case("Http2Push"), "http2_push");
+        assert_eq!(snake_case("V2"), "v2");
+    }
+}
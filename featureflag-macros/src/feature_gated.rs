@@ -0,0 +1,165 @@
+use quote::{ToTokens, quote};
+use syn::{
+    Expr, FnArg, Ident, Item, LitStr, Pat, Token,
+    parse::{Parse, ParseStream},
+    parse_quote,
+    spanned::Spanned,
+};
+
+use crate::utils::crate_name;
+
+pub fn feature_gated(args: FeatureGatedArgs, input: Item) -> syn::Result<impl ToTokens> {
+    let Item::Fn(mut input) = input else {
+        return Err(syn::Error::new_spanned(
+            &input,
+            "expected function or method",
+        ));
+    };
+
+    let featureflag = crate_name("featureflag");
+
+    let FeatureGatedArgs {
+        name,
+        default,
+        fallback,
+    } = args;
+    let default = default.unwrap_or_else(|| parse_quote! { false });
+
+    let disabled = match fallback {
+        Some(fallback) => {
+            let args = input
+                .sig
+                .inputs
+                .iter()
+                .map(|arg| match arg {
+                    FnArg::Receiver(receiver) => Ok(receiver.self_token.to_token_stream()),
+                    FnArg::Typed(arg) => match &*arg.pat {
+                        Pat::Ident(pat) if pat.subpat.is_none() => Ok(pat.ident.to_token_stream()),
+                        pat => Err(syn::Error::new(
+                            pat.span(),
+                            "arguments must be identifiers when using a fallback",
+                        )),
+                    },
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+
+            let call = quote! { (#fallback)(#(#args),*) };
+            if input.sig.asyncness.is_some() {
+                quote! { #call.await }
+            } else {
+                call
+            }
+        }
+        None => quote! { ::core::default::Default::default() },
+    };
+
+    input.block.stmts.insert(
+        0,
+        parse_quote! {
+            if !#featureflag::is_enabled!(#name, #default) {
+                return #disabled;
+            }
+        },
+    );
+
+    Ok(input)
+}
+
+pub struct FeatureGatedArgs {
+    name: LitStr,
+    default: Option<Expr>,
+    fallback: Option<Expr>,
+}
+
+impl Parse for FeatureGatedArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let mut default = None;
+        let mut fallback = None;
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let key = input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+            let slot = if key == "default" {
+                &mut default
+            } else if key == "fallback" {
+                &mut fallback
+            } else {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "expected `default` or `fallback`",
+                ));
+            };
+
+            if slot.is_some() {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("duplicate `{key}` argument"),
+                ));
+            }
+            *slot = Some(input.parse()?);
+        }
+
+        Ok(Self {
+            name,
+            default,
+            fallback,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::{ToTokens, quote};
+
+    use crate::utils::expand_macro;
+
+    use super::feature_gated;
+
+    #[test]
+    fn test_feature_gated() {
+        let expanded = expand_macro! {
+            #[feature_gated("new_algo", default = true, fallback = Self::old_algo)]
+            fn new_algo(&self, n: u64) -> u64 {
+                n << 1
+            }
+        };
+
+        let expected = quote! {
+            fn new_algo(&self, n: u64) -> u64 {
+                if !::featureflag::is_enabled!("new_algo", true) {
+                    return (Self::old_algo)(self, n);
+                }
+                n << 1
+            }
+        };
+
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_feature_gated_default() {
+        let expanded = expand_macro! {
+            #[feature_gated("new_algo")]
+            async fn new_algo(Foo(x): Foo) -> u64 {
+                x
+            }
+        };
+
+        let expected = quote! {
+            async fn new_algo(Foo(x): Foo) -> u64 {
+                if !::featureflag::is_enabled!("new_algo", false) {
+                    return ::core::default::Default::default();
+                }
+                x
+            }
+        };
+
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+}
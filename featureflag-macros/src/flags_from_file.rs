@@ -0,0 +1,194 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{ToTokens, quote};
+use syn::{Ident, LitStr};
+
+use crate::utils::crate_name;
+
+pub fn flags_from_file(path: LitStr) -> syn::Result<impl ToTokens> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| syn::Error::new(path.span(), "CARGO_MANIFEST_DIR is not set"))?;
+    let full_path = std::path::Path::new(&manifest_dir).join(path.value());
+
+    let contents = std::fs::read_to_string(&full_path).map_err(|err| {
+        syn::Error::new(
+            path.span(),
+            format!("failed to read {}: {err}", full_path.display()),
+        )
+    })?;
+    let flags = parse_manifest(&contents).map_err(|err| {
+        syn::Error::new(
+            path.span(),
+            format!("invalid manifest {}: {err}", path.value()),
+        )
+    })?;
+
+    let constants = flags_to_tokens(&flags, path.span())?;
+    let full_path = full_path.to_string_lossy();
+
+    Ok(quote! {
+        // Make sure the manifest is tracked, so changes trigger a rebuild.
+        const _: &str = ::core::include_str!(#full_path);
+
+        #constants
+    })
+}
+
+struct FlagDefinition {
+    name: String,
+    default: bool,
+    description: Option<String>,
+}
+
+fn parse_manifest(contents: &str) -> Result<Vec<FlagDefinition>, String> {
+    let document = contents
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|err| err.to_string())?;
+
+    document
+        .iter()
+        .map(|(name, item)| {
+            if let Some(default) = item.as_bool() {
+                return Ok(FlagDefinition {
+                    name: name.to_string(),
+                    default,
+                    description: None,
+                });
+            }
+
+            let table = item
+                .as_table_like()
+                .ok_or_else(|| format!("expected boolean or table for flag {name:?}"))?;
+
+            let mut default = None;
+            let mut description = None;
+            for (key, item) in table.iter() {
+                match key {
+                    "default" => {
+                        default = Some(item.as_bool().ok_or_else(|| {
+                            format!("expected boolean `default` for flag {name:?}")
+                        })?);
+                    }
+                    "description" => {
+                        description = Some(item.as_str().map(str::to_string).ok_or_else(|| {
+                            format!("expected string `description` for flag {name:?}")
+                        })?);
+                    }
+                    _ => return Err(format!("unknown key {key:?} for flag {name:?}")),
+                }
+            }
+
+            Ok(FlagDefinition {
+                name: name.to_string(),
+                default: default.ok_or_else(|| format!("missing `default` for flag {name:?}"))?,
+                description,
+            })
+        })
+        .collect()
+}
+
+fn flags_to_tokens(flags: &[FlagDefinition], span: Span) -> syn::Result<TokenStream> {
+    let featureflag = crate_name("featureflag");
+
+    let mut idents = Vec::<(Ident, &str)>::new();
+    let mut tokens = TokenStream::new();
+
+    for flag in flags {
+        let ident = Ident::new(&const_name(&flag.name), span);
+        if let Some((_, other)) = idents.iter().find(|(other, _)| *other == ident) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "flags {other:?} and {:?} both map to the constant `{ident}`",
+                    flag.name
+                ),
+            ));
+        }
+
+        let name = LitStr::new(&flag.name, span);
+        let default = flag.default;
+        let doc = flag.description.iter();
+        let description = flag.description.iter();
+
+        tokens.extend(quote! {
+            #( #[doc = #doc] )*
+            pub const #ident: #featureflag::Feature<'static> =
+                #featureflag::feature!(#name, #default #(, description = #description)*);
+        });
+
+        idents.push((ident, &flag.name));
+    }
+
+    let idents = idents.iter().map(|(ident, _)| ident);
+    tokens.extend(quote! {
+        /// All flags defined in the manifest.
+        pub const ALL: &[#featureflag::Feature<'static>] = &[#( #idents ),*];
+    });
+
+    Ok(tokens)
+}
+
+/// Convert a flag name to an `UPPER_SNAKE_CASE` constant name.
+fn const_name(name: &str) -> String {
+    let mut ident = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::Span;
+    use quote::quote;
+
+    use super::{const_name, flags_to_tokens, parse_manifest};
+
+    #[test]
+    fn test_flags_from_file() {
+        let flags = parse_manifest(
+            r#"
+            fast_path = false
+
+            ["checkout.new-flow"]
+            default = true
+            description = "Use the new checkout flow."
+            "#,
+        )
+        .unwrap();
+
+        let expanded = flags_to_tokens(&flags, Span::call_site()).unwrap();
+
+        let expected = quote! {
+            pub const FAST_PATH: ::featureflag::Feature<'static> = ::featureflag::feature!("fast_path", false);
+            #[doc = "Use the new checkout flow."]
+            pub const CHECKOUT_NEW_FLOW: ::featureflag::Feature<'static> =
+                ::featureflag::feature!("checkout.new-flow", true, description = "Use the new checkout flow.");
+            /// All flags defined in the manifest.
+            pub const ALL: &[::featureflag::Feature<'static>] = &[FAST_PATH, CHECKOUT_NEW_FLOW];
+        };
+
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_flags_from_file_errors() {
+        assert!(parse_manifest("a = 1").is_err());
+        assert!(parse_manifest("[a]\ndescription = \"x\"").is_err());
+        assert!(parse_manifest("[a]\ndefault = true\nowner = \"x\"").is_err());
+
+        let flags = parse_manifest("a-b = true\na_b = false").unwrap();
+        assert!(flags_to_tokens(&flags, Span::call_site()).is_err());
+
+        assert_eq!(const_name("3d"), "_3D");
+    }
+}
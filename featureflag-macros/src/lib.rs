@@ -0,0 +1,133 @@
+//! Macros for the `featureflag` crate.
+//!
+//! This crate shouldn't be used directly, but should be used
+//! through its reexports in the `featureflag` crate.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use quote::ToTokens;
+
+mod feature_flags;
+mod feature_gated;
+mod flags_from_file;
+mod utils;
+
+/// Gate a function behind a feature flag.
+///
+/// When the feature is disabled, the function delegates to the `fallback`
+/// function with the same arguments, or returns `Default::default()` if no
+/// fallback is given. The `default` argument is the default value of the
+/// feature, and defaults to `false`.
+///
+/// With a fallback, all arguments must be simple identifiers. For methods, the
+/// receiver is passed as the first argument, so the fallback can be given as
+/// `Self::method`. For `async` functions, the fallback is awaited.
+///
+/// # Examples
+///
+/// ```ignore
+/// use featureflag::feature_gated;
+///
+/// fn old_algo(n: u64) -> u64 {
+///     n * 2
+/// }
+///
+/// #[feature_gated("new_algo", default = false, fallback = old_algo)]
+/// fn new_algo(n: u64) -> u64 {
+///     n << 1
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn feature_gated(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = syn::parse_macro_input!(args);
+    let input = syn::parse_macro_input!(input);
+
+    feature_gated::feature_gated(args, input)
+        .map(|output| output.into_token_stream())
+        .unwrap_or_else(|err| err.into_compile_error())
+        .into()
+}
+
+/// Define feature flag constants from a TOML manifest.
+///
+/// The path is relative to the directory containing the crate's `Cargo.toml`.
+/// Each top-level key in the manifest defines a flag, either as a boolean
+/// default value, or as a table with a required `default` and an optional
+/// `description`:
+///
+/// ```toml
+/// fast_path = false
+///
+/// [new_ui]
+/// default = true
+/// description = "Use the redesigned UI."
+/// ```
+///
+/// For each flag, a `pub const` [`Feature`] is generated, named after the flag
+/// in `UPPER_SNAKE_CASE`, with the description as its documentation and
+/// registered `description` metadata. An `ALL`
+/// constant lists all of the flags. The constants are usually generated inside
+/// a dedicated module.
+///
+/// [`Feature`]: https://docs.rs/featureflag/latest/featureflag/feature/struct.Feature.html
+///
+/// # Examples
+///
+/// ```ignore
+/// mod flags {
+///     featureflag::flags_from_file!("flags.toml");
+/// }
+///
+/// if flags::NEW_UI.is_enabled() {
+///     // ...
+/// }
+/// ```
+#[proc_macro]
+pub fn flags_from_file(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path = syn::parse_macro_input!(input);
+
+    flags_from_file::flags_from_file(path)
+        .map(|output| output.into_token_stream())
+        .unwrap_or_else(|err| err.into_compile_error())
+        .into()
+}
+
+/// Derive feature flag definitions for an enum.
+///
+/// Each unit variant of the enum is a feature flag. The flag name defaults to
+/// the variant name in `snake_case`, and the default value to `false`. Both can
+/// be changed with `#[featureflag(name = "...", default = ...)]` on the
+/// variant.
+///
+/// The derive generates inherent `name`, `feature`, `is_enabled` and
+/// `is_enabled_in` methods, as well as an `ALL` constant and an `iter` function
+/// for iterating over all variants. The flags are registered like flags
+/// defined with the `feature!` macro.
+///
+/// # Examples
+///
+/// ```ignore
+/// use featureflag::FeatureFlags;
+///
+/// #[derive(FeatureFlags)]
+/// enum Flags {
+///     NewUi,
+///     #[featureflag(default = true)]
+///     FastPath,
+/// }
+///
+/// if Flags::NewUi.is_enabled() {
+///     // ...
+/// }
+/// ```
+#[proc_macro_derive(FeatureFlags, attributes(featureflag))]
+pub fn derive_feature_flags(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input);
+
+    feature_flags::derive_feature_flags(input)
+        .map(|output| output.into_token_stream())
+        .unwrap_or_else(|err| err.into_compile_error())
+        .into()
+}
@@ -11,12 +11,31 @@ mod with_features;
 
 /// Enable the specified features for use in tests.
 ///
-/// This macro calls `featureflag::evaluator::set_thread_evaluator`, so it
-/// should only be used for single-threaded tests.
+/// For regular functions, this macro calls `featureflag::evaluator::set_default`,
+/// so the features are enabled on the current thread until the function returns.
+///
+/// For `async` functions, the body is instead wrapped with
+/// `featureflag::utils::AnyExt::wrap_evaluator`, so the features stay enabled
+/// when the future moves between threads. When combined with an async test
+/// attribute like `#[tokio::test]`, this attribute must be placed above it.
+///
+/// The setup is injected at the start of the function body, without changing
+/// the function signature, so it works with functions returning `Result` and
+/// can be combined with other test attributes like `#[rstest]` or `#[test_case]`
+/// in any order.
 ///
 /// Feature values can be any value that implements the `featureflag_test::TestFeature`
 /// trait.
 ///
+/// Features can also be loaded from JSON or TOML fixture files with
+/// `file = "path"`, where the path is relative to the crate root. See
+/// `TestEvaluator::load_json` and `TestEvaluator::load_toml` for the formats.
+/// Features set inline override features from fixture files.
+///
+/// By default, the features are set on a new `featureflag_test::TestEvaluator`.
+/// With `evaluator = factory`, the evaluator returned by calling `factory()` is
+/// used instead, with the listed features taking precedence over it.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -27,6 +46,14 @@ mod with_features;
 ///   assert!(!featureflag::is_enabled("disabled"));
 /// }
 /// ```
+///
+/// ```ignore
+/// #[with_features("enabled")]
+/// #[tokio::test]
+/// async fn my_async_test() {
+///   assert!(featureflag::is_enabled("enabled"));
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn with_features(
     args: proc_macro::TokenStream,
@@ -1,5 +1,5 @@
-use proc_macro2::TokenStream;
-use quote::{ToTokens, format_ident, quote_spanned};
+use proc_macro2::{Span, TokenStream};
+use quote::{ToTokens, format_ident, quote, quote_spanned};
 use syn::{
     Expr, ExprLit, Ident, Item, Lit, LitBool, LitStr, Token,
     parse::{Parse, ParseStream},
@@ -18,11 +18,41 @@ pub fn with_features(args: TestFeaturesArgs, input: Item) -> syn::Result<impl To
         ));
     };
 
-    let evaluator = format_ident!("__evaluator");
+    // Generated locals use mixed-site hygiene, so they can't conflict with
+    // locals in the function body or in code generated by other attributes.
+    let evaluator = Ident::new("__evaluator", Span::mixed_site());
 
     let featureflag = crate_name("featureflag");
     let featureflag_test = crate_name("featureflag-test");
 
+    let fixtures = args
+        .fixtures
+        .iter()
+        .map(|file| {
+            let span = file.span();
+            let load = match file.value().rsplit_once('.') {
+                Some((_, "json")) => format_ident!("load_json"),
+                Some((_, "toml")) => format_ident!("load_toml"),
+                _ => {
+                    return Err(syn::Error::new(
+                        span,
+                        "expected a `.json` or `.toml` fixture file",
+                    ));
+                }
+            };
+
+            Ok(quote_spanned! {span=>
+                #evaluator
+                    .#load(::core::include_str!(::core::concat!(
+                        ::core::env!("CARGO_MANIFEST_DIR"),
+                        "/",
+                        #file
+                    )))
+                    .unwrap_or_else(|err| ::core::panic!("invalid fixture file {}: {}", #file, err));
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
     let features = args
         .test_features
         .into_iter()
@@ -39,29 +69,116 @@ pub fn with_features(args: TestFeaturesArgs, input: Item) -> syn::Result<impl To
         })
         .collect::<Vec<_>>();
 
-    input.block.stmts.insert(
-        0,
-        parse_quote! {
+    let base = args.evaluator.map(|factory| {
+        quote_spanned! {factory.span()=>
+            let #evaluator = #featureflag::evaluator::EvaluatorExt::chain(#evaluator, (#factory)());
+        }
+    });
+
+    let setup = quote! {
+        let mut #evaluator = #featureflag_test::TestEvaluator::new();
+        #( #fixtures )*
+        #( #features )*
+        #base
+    };
+
+    if input.sig.asyncness.is_some() {
+        // Async tests may move between threads, so the evaluator is attached to
+        // the future instead of the current thread.
+        let block = &input.block;
+        input.block = parse_quote! {
             {
-                let mut #evaluator = #featureflag_test::TestEvaluator::new();
-                #( #features )*
-                #featureflag::evaluator::set_thread_default(#evaluator);
-            };
-        },
-    );
+                let #evaluator = {
+                    #setup
+                    #featureflag::evaluator::Evaluator::into_ref(#evaluator)
+                };
+                #featureflag::utils::AnyExt::wrap_evaluator(async move #block, #evaluator).await
+            }
+        };
+    } else {
+        let guard = Ident::new("__guard", Span::mixed_site());
+        input.block.stmts.insert(
+            0,
+            parse_quote! {
+                let #guard = {
+                    #setup
+                    #featureflag::evaluator::set_default(#evaluator)
+                };
+            },
+        );
+    }
 
     Ok(input)
 }
 
 pub struct TestFeaturesArgs {
-    test_features: Punctuated<TestFeatureArg, Token![,]>,
+    evaluator: Option<Expr>,
+    fixtures: Vec<LitStr>,
+    test_features: Vec<TestFeatureArg>,
 }
 
 impl Parse for TestFeaturesArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let test_features = Punctuated::parse_terminated(input)?;
+        let mut evaluator = None;
+        let mut fixtures = Vec::new();
+        let mut test_features = Vec::new();
+
+        for arg in Punctuated::<TestFeaturesArg, Token![,]>::parse_terminated(input)? {
+            match arg {
+                TestFeaturesArg::Evaluator(factory) => {
+                    if evaluator.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            factory,
+                            "duplicate `evaluator` argument",
+                        ));
+                    }
+                    evaluator = Some(factory);
+                }
+                TestFeaturesArg::File(file) => fixtures.push(file),
+                TestFeaturesArg::Feature(feature) => test_features.push(feature),
+            }
+        }
+
+        Ok(Self {
+            evaluator,
+            fixtures,
+            test_features,
+        })
+    }
+}
+
+/// A single argument to `#[with_features]`.
+///
+/// `evaluator = factory` sets the evaluator factory and `file = "path"` loads
+/// a fixture file, while anything else sets a feature. Features named
+/// `evaluator` or `file` can still be set with `"evaluator" = value`.
+enum TestFeaturesArg {
+    Evaluator(Expr),
+    File(LitStr),
+    Feature(TestFeatureArg),
+}
+
+impl Parse for TestFeaturesArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let fork = input.fork();
+        let keyword = fork
+            .parse::<Ident>()
+            .ok()
+            .filter(|_| fork.parse::<Token![=]>().is_ok());
 
-        Ok(Self { test_features })
+        match keyword {
+            Some(ident) if ident == "evaluator" => {
+                input.parse::<Ident>()?;
+                input.parse::<Token![=]>()?;
+                Ok(Self::Evaluator(input.parse()?))
+            }
+            Some(ident) if ident == "file" && fork.peek(LitStr) => {
+                input.parse::<Ident>()?;
+                input.parse::<Token![=]>()?;
+                Ok(Self::File(input.parse()?))
+            }
+            _ => Ok(Self::Feature(input.parse()?)),
+        }
     }
 }
 
@@ -137,13 +254,13 @@ mod tests {
         let expected = quote! {
             #[foo]
             fn test<'a, T: Foo, U, const V: usize>(&mut self, n: i32, Foo(x): Foo) {
-                {
+                let __guard = {
                     let mut __evaluator = ::featureflag_test::TestEvaluator::new();
                     __evaluator.set_feature("enabled", true);
                     __evaluator.set_feature("disabled", false);
                     __evaluator.set_feature("implicit", true);
                     __evaluator.set_feature("custom", custom);
-                    ::featureflag::evaluator::set_thread_default(__evaluator);
+                    ::featureflag::evaluator::set_default(__evaluator)
                 };
 
                 self.beep_boop(n, x)
@@ -152,4 +269,81 @@ mod tests {
 
         assert_eq!(expanded.to_string(), expected.to_string());
     }
+
+    #[test]
+    fn test_with_features_async() {
+        let expanded = expand_macro! {
+            #[with_features(enabled)]
+            async fn test(n: i32) -> i32 {
+                foo(n).await
+            }
+        };
+
+        let expected = quote! {
+            async fn test(n: i32) -> i32 {
+                let __evaluator = {
+                    let mut __evaluator = ::featureflag_test::TestEvaluator::new();
+                    __evaluator.set_feature("enabled", true);
+                    ::featureflag::evaluator::Evaluator::into_ref(__evaluator)
+                };
+                ::featureflag::utils::AnyExt::wrap_evaluator(
+                    async move {
+                        foo(n).await
+                    },
+                    __evaluator
+                )
+                .await
+            }
+        };
+
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_with_features_file() {
+        let expanded = expand_macro! {
+            #[with_features(file = "tests/flags.toml", enabled)]
+            fn test() {}
+        };
+
+        let expected = quote! {
+            fn test() {
+                let __guard = {
+                    let mut __evaluator = ::featureflag_test::TestEvaluator::new();
+                    __evaluator
+                        .load_toml(::core::include_str!(::core::concat!(
+                            ::core::env!("CARGO_MANIFEST_DIR"),
+                            "/",
+                            "tests/flags.toml"
+                        )))
+                        .unwrap_or_else(|err| ::core::panic!("invalid fixture file {}: {}", "tests/flags.toml", err));
+                    __evaluator.set_feature("enabled", true);
+                    ::featureflag::evaluator::set_default(__evaluator)
+                };
+            }
+        };
+
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_with_features_evaluator() {
+        let expanded = expand_macro! {
+            #[with_features(evaluator = base_evaluator, enabled)]
+            fn test() {}
+        };
+
+        let expected = quote! {
+            fn test() {
+                let __guard = {
+                    let mut __evaluator = ::featureflag_test::TestEvaluator::new();
+                    __evaluator.set_feature("enabled", true);
+                    let __evaluator = ::featureflag::evaluator::EvaluatorExt::chain(__evaluator, (base_evaluator)());
+                    ::featureflag::evaluator::set_default(__evaluator)
+                };
+            }
+        };
+
+        assert_eq!(expanded.to_string(), expected.to_string());
+    }
 }
@@ -0,0 +1,173 @@
+//! A small benchmark harness for evaluators.
+//!
+//! [`evaluator_suite`] measures the common evaluation paths with a given
+//! evaluator, so evaluator crates can compare their numbers against the
+//! built-in evaluators with the same harness:
+//!
+//! ```no_run
+//! use featureflag::evaluator::{EvaluatorExt, NoEvaluator};
+//! use featureflag_test::bench::evaluator_suite;
+//!
+//! for result in evaluator_suite(NoEvaluator.fallback([("feature", true)])) {
+//!     println!("{result}");
+//! }
+//! ```
+//!
+//! Benchmarks are usually run from a `[[bench]]` target with `harness = false`.
+//! Note that [`TestEvaluator`](crate::TestEvaluator) records every evaluation,
+//! so it is not suited for benchmarks.
+
+use std::{
+    fmt,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use featureflag::{
+    Evaluator, Feature, context,
+    evaluator::{EvaluatorExt, NoEvaluator, with_default},
+};
+
+/// Time spent measuring each benchmark.
+const MEASUREMENT_TIME: Duration = Duration::from_millis(200);
+
+/// Result of a single benchmark, see [`bench()`].
+#[derive(Clone, Debug)]
+pub struct BenchResult {
+    name: String,
+    iterations: u64,
+    elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Get the name of the benchmark.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the number of measured iterations.
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Get the total time of the measured iterations.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Get the average time per iteration, in nanoseconds.
+    pub fn nanos_per_iter(&self) -> f64 {
+        self.elapsed.as_nanos() as f64 / self.iterations.max(1) as f64
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<32} {:>10.1} ns/iter ({} iterations)",
+            self.name,
+            self.nanos_per_iter(),
+            self.iterations
+        )
+    }
+}
+
+/// Measure the average time of calling `f`.
+///
+/// `f` is run repeatedly for a short warm-up, and then for a fixed amount of
+/// time. Use [`black_box`] on inputs and results to keep the compiler from
+/// optimizing the measured code away.
+pub fn bench<F: FnMut()>(name: &str, mut f: F) -> BenchResult {
+    // warm up, and find a batch size that takes at least a millisecond
+    let mut batch = 1u64;
+    loop {
+        let start = Instant::now();
+        for _ in 0..batch {
+            f();
+        }
+        if start.elapsed() >= Duration::from_millis(1) || batch >= 1 << 30 {
+            break;
+        }
+        batch *= 2;
+    }
+
+    let mut iterations = 0;
+    let start = Instant::now();
+    while start.elapsed() < MEASUREMENT_TIME {
+        for _ in 0..batch {
+            f();
+        }
+        iterations += batch;
+    }
+
+    BenchResult {
+        name: name.to_string(),
+        iterations,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Benchmark the common evaluation paths with `evaluator`.
+///
+/// The evaluator is installed with [`with_default`], and should enable the
+/// feature named `feature`. The suite measures:
+///
+/// - `is_enabled!` in the root context and in a child context,
+/// - evaluating a feature directly with [`Feature::is_enabled_in`],
+/// - creating contexts with and without fields,
+/// - entering a context with `in_scope`,
+/// - evaluating through [`Chain`](featureflag::evaluator::Chain) and
+///   [`Filter`](featureflag::evaluator::Filter) combinators.
+pub fn evaluator_suite<E>(evaluator: E) -> Vec<BenchResult>
+where
+    E: Evaluator + Send + Sync + 'static,
+{
+    let evaluator = evaluator.into_ref();
+    let mut results = Vec::new();
+
+    with_default(evaluator.clone(), || {
+        results.push(bench("is_enabled!", || {
+            black_box(featureflag::is_enabled!("feature", false));
+        }));
+
+        let feature = Feature::new("feature", false);
+        results.push(bench("Feature::is_enabled_in(root)", || {
+            black_box(black_box(&feature).is_enabled_in(None));
+        }));
+
+        let parent = context!(user_id = 42u64, tenant = "acme");
+        results.push(bench("is_enabled! in context", || {
+            black_box(featureflag::is_enabled!(context: parent, "feature", false));
+        }));
+
+        results.push(bench("context!(2 fields)", || {
+            black_box(context!(parent: parent, user_id = 42u64, tenant = "acme"));
+        }));
+
+        results.push(bench("context!(empty child)", || {
+            black_box(context!(parent: parent));
+        }));
+
+        results.push(bench("in_scope", || {
+            parent.in_scope(|| black_box(()));
+        }));
+    });
+
+    with_default(NoEvaluator.chain(evaluator.clone()), || {
+        results.push(bench("Chain", || {
+            black_box(featureflag::is_enabled!("feature", false));
+        }));
+    });
+
+    with_default(
+        evaluator.filter(|feature| feature.starts_with("feat")),
+        || {
+            results.push(bench("Filter", || {
+                black_box(featureflag::is_enabled!("feature", false));
+            }));
+        },
+    );
+
+    results
+}
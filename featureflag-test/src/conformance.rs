@@ -0,0 +1,231 @@
+//! Conformance tests for [`Evaluator`] implementations.
+//!
+//! The [`run`] function exercises an evaluator the way this crate and the
+//! [`featureflag`] crate use evaluators: registration, context creation and
+//! destruction, tracing, chaining and concurrent evaluation. Implementors of
+//! third-party evaluators can call it from a test to check that they honor the
+//! contract of the [`Evaluator`] trait.
+//!
+//! # Examples
+//!
+//! ```
+//! use featureflag_test::{TestEvaluator, conformance};
+//!
+//! conformance::run(
+//!     || TestEvaluator::with_features([("a", true), ("b", false)]),
+//!     &["a", "b", "c"],
+//! );
+//! ```
+
+use std::{sync::Arc, thread};
+
+use featureflag::{
+    Context, Evaluator, Feature, context,
+    evaluator::{
+        EvaluationTrace, EvaluatorExt, NoEvaluator, RegistrationInfo, RegistrationKind,
+        with_default,
+    },
+};
+
+use crate::{TestContextExt, TestEvaluator};
+
+/// Run the conformance tests against evaluators created by `factory`.
+///
+/// The evaluators are checked by evaluating `features`, which should include
+/// features that the evaluator has values for, as well as some it doesn't.
+/// The factory is called multiple times, and should return evaluators that
+/// behave the same way.
+///
+/// # Panics
+///
+/// Panics with a message starting with `conformance:` if the evaluator does
+/// not behave as expected.
+pub fn run<E, F>(factory: F, features: &[&str])
+where
+    F: Fn() -> E,
+    E: Evaluator + 'static,
+{
+    check_registration(&factory, features);
+    check_traced(&factory, features);
+    check_contexts(&factory, features);
+    check_chain(&factory, features);
+    check_threads(&factory, features);
+    check_describe(&factory);
+}
+
+fn results(evaluator: &dyn Evaluator, context: &Context, features: &[&str]) -> Vec<Option<bool>> {
+    features
+        .iter()
+        .map(|feature| evaluator.is_enabled(feature, context))
+        .collect()
+}
+
+fn feature_results(context: &Context, features: &[&str]) -> Vec<Option<bool>> {
+    features
+        .iter()
+        .map(|feature| Feature::new(feature, false).get_state_in(Some(context)))
+        .collect()
+}
+
+fn check_registration<E: Evaluator, F: Fn() -> E>(factory: &F, features: &[&str]) {
+    let evaluator = factory();
+    evaluator.on_registration(&RegistrationInfo::new(RegistrationKind::Scoped, 1));
+    let first = results(&evaluator, &Context::root(), features);
+
+    evaluator.on_registration(&RegistrationInfo::new(RegistrationKind::Scoped, 2));
+    let second = results(&evaluator, &Context::root(), features);
+
+    assert_eq!(
+        first, second,
+        "conformance: results changed after registering the evaluator again"
+    );
+}
+
+fn check_traced<E: Evaluator + 'static, F: Fn() -> E>(factory: &F, features: &[&str]) {
+    let evaluator = Arc::new(factory());
+    with_default(evaluator.clone(), || {
+        let context = context!(user = "conformance", id = 1u64);
+        for context in [&Context::root(), &context] {
+            for feature in features {
+                let result = evaluator.is_enabled(feature, context);
+
+                let mut trace = EvaluationTrace::new();
+                let traced = evaluator.is_enabled_traced(feature, context, &mut trace);
+                assert_eq!(
+                    result, traced,
+                    "conformance: is_enabled and is_enabled_traced differ for {feature:?}"
+                );
+
+                if result.is_some() {
+                    let decided = trace.steps().iter().rev().find_map(|step| step.result());
+                    assert_eq!(
+                        decided, result,
+                        "conformance: trace for {feature:?} does not record the result\n{trace}"
+                    );
+                }
+            }
+        }
+    });
+}
+
+fn check_contexts<E: Evaluator + 'static, F: Fn() -> E>(factory: &F, features: &[&str]) {
+    let evaluator = Arc::new(factory());
+    with_default(evaluator.clone(), || {
+        let parent = context!(user = "conformance", id = 1u64);
+        let child = context!(parent: parent, request = "first");
+        let sibling = context!(parent: parent, request = "second");
+
+        for context in [&Context::root(), &parent, &child, &sibling] {
+            let first = results(&*evaluator, context, features);
+            assert_eq!(
+                first,
+                results(&*evaluator, context, features),
+                "conformance: results changed between evaluations in the same context"
+            );
+            assert_eq!(
+                first,
+                feature_results(context, features),
+                "conformance: results differ when evaluated through `Feature`"
+            );
+        }
+
+        let child_results = results(&*evaluator, &child, features);
+        drop(parent);
+        assert_eq!(
+            child_results,
+            results(&*evaluator, &child, features),
+            "conformance: results in a child context changed after its parent was dropped"
+        );
+
+        drop(sibling);
+        assert_eq!(
+            child_results,
+            results(&*evaluator, &child, features),
+            "conformance: results in a context changed after a sibling was closed"
+        );
+    });
+}
+
+fn check_chain<E: Evaluator + 'static, F: Fn() -> E>(factory: &F, features: &[&str]) {
+    fn evaluate<U: Evaluator + 'static>(evaluator: U, features: &[&str]) -> Vec<Vec<Option<bool>>> {
+        with_default(evaluator, || {
+            let context = context!(user = "conformance", id = 1u64);
+            vec![
+                feature_results(&Context::root(), features),
+                feature_results(&context, features),
+            ]
+        })
+    }
+
+    let expected = evaluate(factory(), features);
+    assert_eq!(
+        expected,
+        evaluate(factory().into_ref(), features),
+        "conformance: results changed after converting the evaluator with `into_ref`"
+    );
+    assert_eq!(
+        expected,
+        evaluate(factory().chain(NoEvaluator), features),
+        "conformance: results changed when chained before another evaluator"
+    );
+    assert_eq!(
+        expected,
+        evaluate(NoEvaluator.chain(factory()), features),
+        "conformance: results changed when chained after another evaluator"
+    );
+
+    // The evaluator must not interfere with the context data of other evaluators.
+    let probe = || {
+        let probe = TestEvaluator::new();
+        probe.set_feature("conformance.probe", |context: &Context| {
+            context.test_fields().and_then(|fields| {
+                fields
+                    .get("user")?
+                    .as_str()
+                    .map(|user| user == "conformance")
+            })
+        });
+        probe
+    };
+    for (order, evaluator) in [
+        ("before", probe().chain(factory()).into_ref()),
+        ("after", factory().chain(probe()).into_ref()),
+    ] {
+        with_default(probe().chain(evaluator), || {
+            let context = context!(user = "conformance");
+            assert!(
+                Feature::new("conformance.probe", false).is_enabled_in(Some(&context)),
+                "conformance: evaluator interferes with context data of an evaluator chained {order} it"
+            );
+        });
+    }
+}
+
+fn check_threads<E: Evaluator + 'static, F: Fn() -> E>(factory: &F, features: &[&str]) {
+    let evaluator = Arc::new(factory());
+    with_default(evaluator.clone(), || {
+        let context = context!(user = "conformance", id = 1u64);
+        let expected = results(&*evaluator, &context, features);
+
+        thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|_| scope.spawn(|| results(&*evaluator, &context, features)))
+                .collect::<Vec<_>>();
+            for handle in handles {
+                assert_eq!(
+                    expected,
+                    handle.join().unwrap(),
+                    "conformance: results differ when evaluated from another thread"
+                );
+            }
+        });
+    });
+}
+
+fn check_describe<E: Evaluator, F: Fn() -> E>(factory: &F) {
+    let description = factory().describe();
+    assert!(
+        !description.name().is_empty(),
+        "conformance: evaluator description has an empty name"
+    );
+}
@@ -1,25 +1,93 @@
 //! Test utilities for the [`featureflag`] crate.
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
-use std::{collections::HashMap, ops::Deref, sync::RwLock};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use featureflag::{Context, Evaluator, context::ContextRef, fields::Fields};
+use featureflag::{
+    Context, Evaluator,
+    context::{ContextId, ContextRef},
+    diagnostics::{FeatureUsage, capture_usage},
+    evaluator::BootstrapEvaluator,
+    fields::{Fields, FieldsBuf},
+    value::ToValue,
+};
+
+use crate::sharded::ShardedMap;
+
+pub mod bench;
+pub mod conformance;
+mod sharded;
 
 pub use featureflag_test_macros::*;
 
 /// A test evaluator that allows setting features for testing purposes.
+///
+/// Cloning a `TestEvaluator` is cheap, and returns a handle to the same
+/// evaluator. This allows changing features after the evaluator has been
+/// installed, e.g. to test code that reacts to features changing at runtime.
+#[derive(Clone)]
 pub struct TestEvaluator {
-    features: RwLock<HashMap<String, Box<dyn TestFeature>>>,
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    features: ShardedMap<Box<dyn TestFeature>>,
+    overrides: ShardedMap<Vec<ContextOverride>>,
+    record_evaluations: AtomicBool,
+    evaluations: Mutex<VecDeque<EvaluationRecord>>,
+}
+
+/// Maximum number of evaluations kept by a [`TestEvaluator`].
+///
+/// When more evaluations are recorded, the oldest ones are forgotten.
+pub const MAX_EVALUATIONS: usize = 10_000;
+
+/// A feature value that only applies in matching contexts, see [`TestEvaluator::set_feature_in`].
+struct ContextOverride {
+    matcher: Box<dyn ContextMatcher>,
+    enabled: Box<dyn TestFeature>,
 }
 
 impl TestEvaluator {
     /// Create a new `TestEvaluator`.
     pub fn new() -> TestEvaluator {
         TestEvaluator {
-            features: RwLock::new(HashMap::new()),
+            inner: Arc::new(Inner {
+                features: ShardedMap::new(),
+                overrides: ShardedMap::new(),
+                record_evaluations: AtomicBool::new(false),
+                evaluations: Mutex::new(VecDeque::new()),
+            }),
         }
     }
 
+    /// Create a new `TestEvaluator` with the given features set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use featureflag_test::TestEvaluator;
+    ///
+    /// let evaluator = TestEvaluator::with_features([("a", true), ("b", false)]);
+    /// ```
+    pub fn with_features<I, K, T>(features: I) -> TestEvaluator
+    where
+        I: IntoIterator<Item = (K, T)>,
+        K: AsRef<str>,
+        T: TestFeature,
+    {
+        let evaluator = TestEvaluator::new();
+        evaluator.set_all(features);
+        evaluator
+    }
+
     /// Set the state of a feature.
     ///
     /// The feature can be set to any value that implements `TestFeature`, which
@@ -27,15 +95,229 @@ impl TestEvaluator {
     /// is automatically implemented for `bool`, `Option<bool>` and
     /// `Fn(&Context) -> impl TestFeature`.
     pub fn set_feature<T: TestFeature>(&self, feature: &str, enabled: T) {
-        self.features
-            .write()
-            .unwrap()
+        self.inner
+            .features
             .insert(feature.to_string(), Box::new(enabled));
     }
 
-    /// Unset a feature.
+    /// Set the state of multiple features.
+    ///
+    /// See [`set_feature`](Self::set_feature) for details.
+    pub fn set_all<I, K, T>(&self, features: I)
+    where
+        I: IntoIterator<Item = (K, T)>,
+        K: AsRef<str>,
+        T: TestFeature,
+    {
+        for (feature, enabled) in features {
+            self.set_feature(feature.as_ref(), enabled);
+        }
+    }
+
+    /// Set the state of a feature in contexts that match `matcher`.
+    ///
+    /// In matching contexts, this overrides the state set by [`set_feature`](Self::set_feature).
+    /// If multiple overrides match a context, the one set last is used.
+    ///
+    /// `ContextMatcher` is implemented for arrays of `(key, value)` pairs, which
+    /// match contexts where all the fields are set to the given values, and for
+    /// `Fn(&Context) -> bool`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use featureflag_test::TestEvaluator;
+    ///
+    /// let evaluator = TestEvaluator::new();
+    /// evaluator.set_feature_in([("user", "alice")], "new_ui", true);
+    /// ```
+    pub fn set_feature_in<M, T>(&self, matcher: M, feature: &str, enabled: T)
+    where
+        M: ContextMatcher,
+        T: TestFeature,
+    {
+        self.inner
+            .overrides
+            .update(feature.to_string(), |overrides| {
+                overrides.push(ContextOverride {
+                    matcher: Box::new(matcher),
+                    enabled: Box::new(enabled),
+                })
+            });
+    }
+
+    /// Set features from a JSON fixture.
+    ///
+    /// The fixture must be an object mapping feature names to `true`, `false`
+    /// or `null`, where `null` means the feature is not set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fixture could not be parsed. No features are
+    /// set in that case.
+    pub fn load_json(&self, json: &str) -> Result<(), FixtureError> {
+        let fixture = BootstrapEvaluator::from_json(json).map_err(|err| FixtureError {
+            message: err.to_string(),
+        })?;
+        for (feature, enabled) in fixture.features() {
+            self.set_feature(feature, enabled);
+        }
+        Ok(())
+    }
+
+    /// Set features from a TOML fixture.
+    ///
+    /// The fixture maps feature names to booleans. Features in tables are
+    /// prefixed by the table name, so `new_flow` in a `[checkout]` table sets
+    /// the `checkout.new_flow` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fixture could not be parsed, or contains
+    /// non-boolean values. No features are set in that case.
+    pub fn load_toml(&self, toml: &str) -> Result<(), FixtureError> {
+        fn collect(
+            prefix: &str,
+            table: &dyn toml_edit::TableLike,
+            features: &mut Vec<(String, bool)>,
+        ) -> Result<(), FixtureError> {
+            for (key, item) in table.iter() {
+                let name = format!("{prefix}{key}");
+                if let Some(enabled) = item.as_bool() {
+                    features.push((name, enabled));
+                } else if let Some(table) = item.as_table_like() {
+                    collect(&format!("{name}."), table, features)?;
+                } else {
+                    return Err(FixtureError {
+                        message: format!("expected boolean for feature {name:?}"),
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        let document = toml
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|err| FixtureError {
+                message: err.to_string(),
+            })?;
+
+        let mut features = Vec::new();
+        collect("", document.as_table(), &mut features)?;
+        for (feature, enabled) in features {
+            self.set_feature(&feature, enabled);
+        }
+        Ok(())
+    }
+
+    /// Enable or disable recording evaluations.
+    ///
+    /// Evaluations are not recorded by default, so that concurrent evaluations
+    /// don't contend on a shared lock. Recording must be enabled to use
+    /// [`evaluations`](Self::evaluations) and the evaluation assertions. At
+    /// most [`MAX_EVALUATIONS`] are kept, so tests that evaluate features in a
+    /// loop don't grow without bound.
+    pub fn record_evaluations(&self, enabled: bool) {
+        self.inner
+            .record_evaluations
+            .store(enabled, Ordering::Relaxed);
+    }
+
+    /// Get the evaluations made by this evaluator, in order.
+    ///
+    /// Only the last [`MAX_EVALUATIONS`] evaluations are returned.
+    pub fn evaluations(&self) -> Vec<EvaluationRecord> {
+        self.inner
+            .evaluations
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Forget all evaluations made by this evaluator so far.
+    pub fn clear_evaluations(&self) {
+        self.inner.evaluations.lock().unwrap().clear();
+    }
+
+    /// Check if a feature has been evaluated by this evaluator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if evaluations are not recorded, see
+    /// [`record_evaluations`](Self::record_evaluations).
+    #[track_caller]
+    pub fn was_evaluated(&self, feature: &str) -> bool {
+        assert!(
+            self.inner.record_evaluations.load(Ordering::Relaxed),
+            "evaluations are not recorded, call `record_evaluations(true)` first"
+        );
+        self.inner
+            .evaluations
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|record| record.feature == feature)
+    }
+
+    /// Assert that a feature has been evaluated by this evaluator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the feature has not been evaluated, or if evaluations are not
+    /// recorded.
+    #[track_caller]
+    pub fn assert_evaluated(&self, feature: &str) {
+        assert!(
+            self.was_evaluated(feature),
+            "expected feature {feature:?} to be evaluated"
+        );
+    }
+
+    /// Assert that a feature has not been evaluated by this evaluator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the feature has been evaluated, or if evaluations are not
+    /// recorded.
+    #[track_caller]
+    pub fn assert_not_evaluated(&self, feature: &str) {
+        assert!(
+            !self.was_evaluated(feature),
+            "expected feature {feature:?} not to be evaluated"
+        );
+    }
+
+    /// Unset a feature, including any context overrides.
     pub fn clear_feature(&self, feature: &str) {
-        self.features.write().unwrap().remove(feature);
+        self.inner.features.remove(feature);
+        self.inner.overrides.remove(feature);
+    }
+
+    /// Unset all features and context overrides, and forget all evaluations.
+    pub fn reset(&self) {
+        self.inner.features.clear();
+        self.inner.overrides.clear();
+        self.inner.evaluations.lock().unwrap().clear();
+    }
+
+    fn evaluate(&self, feature: &str, context: &Context) -> Option<bool> {
+        let overridden = self.inner.overrides.get(feature, |overrides| {
+            overrides
+                .iter()
+                .rev()
+                .find(|o| o.matcher.matches(context))
+                .map(|o| o.enabled.is_enabled(context))
+        });
+        if let Some(Some(enabled)) = overridden {
+            return enabled;
+        }
+
+        self.inner
+            .features
+            .get(feature, |f| f.is_enabled(context))
+            .flatten()
     }
 }
 
@@ -46,17 +328,76 @@ impl Default for TestEvaluator {
 }
 
 impl Evaluator for TestEvaluator {
-    fn is_enabled(&self, feature: &str, _context: &crate::Context) -> Option<bool> {
-        self.features
-            .read()
-            .unwrap()
-            .get(feature)
-            .and_then(|f| f.is_enabled(_context))
+    fn is_enabled(&self, feature: &str, context: &crate::Context) -> Option<bool> {
+        let result = self.evaluate(feature, context);
+        if self.inner.record_evaluations.load(Ordering::Relaxed) {
+            let mut evaluations = self.inner.evaluations.lock().unwrap();
+            if evaluations.len() >= MAX_EVALUATIONS {
+                evaluations.pop_front();
+            }
+            evaluations.push_back(EvaluationRecord {
+                feature: feature.to_string(),
+                context_id: context.id(),
+                result,
+            });
+        }
+        result
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        let mut flags = self.inner.features.keys();
+        flags.extend(self.inner.overrides.keys());
+        flags.sort_unstable();
+        flags.dedup();
+        Some(flags)
     }
 
     fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
-        let fields = TestFields::new(fields);
-        context.extensions_mut().insert(fields);
+        context
+            .extensions_mut()
+            .insert(TestFields(FieldsBuf::from(fields)));
+    }
+}
+
+/// Error loading a feature fixture, see [`TestEvaluator::load_json`] and
+/// [`TestEvaluator::load_toml`].
+#[derive(Debug)]
+pub struct FixtureError {
+    message: String,
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FixtureError {}
+
+/// A record of a feature evaluation, see [`TestEvaluator::evaluations`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvaluationRecord {
+    feature: String,
+    context_id: ContextId,
+    result: Option<bool>,
+}
+
+impl EvaluationRecord {
+    /// Get the name of the evaluated feature.
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    /// Get the identifier of the context the feature was evaluated in.
+    pub fn context_id(&self) -> ContextId {
+        self.context_id
+    }
+
+    /// Get the result of the evaluation.
+    ///
+    /// `None` means that the feature's default value was used.
+    pub fn result(&self) -> Option<bool> {
+        self.result
     }
 }
 
@@ -88,39 +429,44 @@ where
     }
 }
 
-/// Extension type for [`Context`] that allows access to fields set on a context
-/// when using the [`TestEvaluator`].
-///
-/// This type is not intended to be used directly. Instead, use [`TestContextExt::test_fields`]
-/// to access the fields.
-struct TestFields {
-    fields: Fields<'static>,
+/// A trait for types that match contexts, see [`TestEvaluator::set_feature_in`].
+pub trait ContextMatcher: Send + Sync + 'static {
+    /// Check if the context matches.
+    fn matches(&self, context: &Context) -> bool;
 }
 
-impl TestFields {
-    fn new(fields: Fields<'_>) -> TestFields {
-        // very leaky!
-
-        let fields = fields
-            .pairs()
-            .map(|(k, v)| (&*k.to_string().leak(), v.to_static()))
-            .collect::<Vec<_>>()
-            .leak();
-
-        TestFields {
-            fields: Fields::new(fields),
-        }
+impl<F> ContextMatcher for F
+where
+    F: Fn(&Context) -> bool + Send + Sync + 'static,
+{
+    fn matches(&self, context: &Context) -> bool {
+        self(context)
     }
 }
 
-impl Deref for TestFields {
-    type Target = Fields<'static>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.fields
+impl<K, V, const N: usize> ContextMatcher for [(K, V); N]
+where
+    K: AsRef<str> + Send + Sync + 'static,
+    V: ToValue + Send + Sync + 'static,
+{
+    fn matches(&self, context: &Context) -> bool {
+        self.iter().all(|(key, value)| {
+            context
+                .iter()
+                .filter_map(|c| c.test_fields())
+                .find_map(|f| f.get(key.as_ref()))
+                .is_some_and(|v| *v == value.to_value())
+        })
     }
 }
 
+/// Extension type for [`Context`] that allows access to fields set on a context
+/// when using the [`TestEvaluator`].
+///
+/// This type is not intended to be used directly. Instead, use [`TestContextExt::test_fields`]
+/// to access the fields.
+struct TestFields(FieldsBuf);
+
 /// Extension trait for [`Context`] that provides access to the fields set on
 /// the context when using the [`TestEvaluator`].
 pub trait TestContextExt {
@@ -128,13 +474,122 @@ pub trait TestContextExt {
     ///
     /// This method will only work with contexts that have been created when
     /// using [`TestEvaluator`].
-    fn test_fields(&self) -> Option<&Fields<'_>>;
+    fn test_fields(&self) -> Option<&FieldsBuf>;
 }
 
 impl TestContextExt for Context {
-    fn test_fields(&self) -> Option<&Fields<'_>> {
+    fn test_fields(&self) -> Option<&FieldsBuf> {
         self.extensions()
             .get::<TestFields>()
-            .map(|fields| fields.deref())
+            .map(|fields| &fields.0)
+    }
+}
+
+/// Call `f`, recording the features evaluated on the current thread.
+///
+/// The returned [`FlagUsage`] can be compared against a snapshot with
+/// [`FlagUsage::assert_snapshot`], to catch code paths that start depending on
+/// new features.
+///
+/// # Examples
+///
+/// ```
+/// let ((), usage) = featureflag_test::record_usage(|| {
+///     featureflag::is_enabled!("new_checkout", false);
+/// });
+///
+/// usage.assert_snapshot("new_checkout: false (default)");
+/// ```
+pub fn record_usage<F: FnOnce() -> R, R>(f: F) -> (R, FlagUsage) {
+    let (result, evaluations) = capture_usage(f);
+    (result, FlagUsage { evaluations })
+}
+
+/// Features evaluated within [`record_usage`].
+#[derive(Clone, Debug)]
+pub struct FlagUsage {
+    evaluations: Vec<FeatureUsage>,
+}
+
+impl FlagUsage {
+    /// Get all evaluations, in order.
+    pub fn evaluations(&self) -> &[FeatureUsage] {
+        &self.evaluations
+    }
+
+    /// Get the names of the evaluated features, sorted and deduplicated.
+    pub fn features(&self) -> Vec<&str> {
+        self.evaluations
+            .iter()
+            .map(|usage| usage.feature())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Check if a feature was evaluated.
+    pub fn contains(&self, feature: &str) -> bool {
+        self.evaluations
+            .iter()
+            .any(|usage| usage.feature() == feature)
+    }
+
+    /// Assert that the usage matches a snapshot.
+    ///
+    /// The snapshot has one line per distinct evaluation, in the format of
+    /// the [`Display`](fmt::Display) implementation of `FlagUsage`. Lines are
+    /// compared without regard to order or surrounding whitespace.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the usage does not match the snapshot.
+    #[track_caller]
+    pub fn assert_snapshot(&self, expected: &str) {
+        let expected = expected
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<BTreeSet<_>>();
+        let actual = self.lines();
+        let actual = actual.iter().map(String::as_str).collect::<BTreeSet<_>>();
+
+        if expected != actual {
+            let missing = expected.difference(&actual).collect::<Vec<_>>();
+            let unexpected = actual.difference(&expected).collect::<Vec<_>>();
+            panic!(
+                "feature usage does not match snapshot\n\
+                 missing: {missing:?}\n\
+                 unexpected: {unexpected:?}\n\
+                 actual:\n{self}"
+            );
+        }
+    }
+
+    fn lines(&self) -> BTreeSet<String> {
+        self.evaluations
+            .iter()
+            .map(|usage| {
+                let feature = usage.feature();
+                match (usage.enabled(), usage.state()) {
+                    (Some(enabled), None) => format!("{feature}: {enabled} (default)"),
+                    (_, Some(state)) => format!("{feature}: {state}"),
+                    (None, None) => format!("{feature}: unset"),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Formats the usage as one line per distinct evaluation, sorted.
+///
+/// Each line is `feature: value`, where the value is followed by `(default)`
+/// if the feature's default value was used, or is `unset` if only the state of
+/// the feature was evaluated and the evaluator returned `None`.
+impl fmt::Display for FlagUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in self.lines() {
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,58 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, BuildHasherDefault, DefaultHasher},
+    sync::RwLock,
+};
+
+const SHARDS: usize = 16;
+
+/// Map from feature names to values, split into independently locked shards
+/// so that concurrent lookups from many threads don't contend on one lock.
+pub(crate) struct ShardedMap<V> {
+    shards: [RwLock<HashMap<String, V>>; SHARDS],
+}
+
+impl<V> ShardedMap<V> {
+    pub(crate) fn new() -> ShardedMap<V> {
+        ShardedMap {
+            shards: std::array::from_fn(|_| RwLock::new(HashMap::new())),
+        }
+    }
+
+    fn shard(&self, key: &str) -> &RwLock<HashMap<String, V>> {
+        let hash = BuildHasherDefault::<DefaultHasher>::default().hash_one(key);
+        &self.shards[hash as usize % SHARDS]
+    }
+
+    pub(crate) fn get<R>(&self, key: &str, f: impl FnOnce(&V) -> R) -> Option<R> {
+        self.shard(key).read().unwrap().get(key).map(f)
+    }
+
+    pub(crate) fn insert(&self, key: String, value: V) {
+        self.shard(&key).write().unwrap().insert(key, value);
+    }
+
+    pub(crate) fn update(&self, key: String, f: impl FnOnce(&mut V))
+    where
+        V: Default,
+    {
+        f(self.shard(&key).write().unwrap().entry(key).or_default());
+    }
+
+    pub(crate) fn remove(&self, key: &str) {
+        self.shard(key).write().unwrap().remove(key);
+    }
+
+    pub(crate) fn clear(&self) {
+        for shard in &self.shards {
+            shard.write().unwrap().clear();
+        }
+    }
+
+    pub(crate) fn keys(&self) -> Vec<String> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.read().unwrap().keys().cloned().collect::<Vec<_>>())
+            .collect()
+    }
+}
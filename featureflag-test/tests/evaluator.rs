@@ -0,0 +1,224 @@
+#![allow(missing_docs)]
+
+use std::sync::Arc;
+
+use featureflag::{Context, Evaluator, Feature, context, evaluator::with_default};
+use featureflag_test::{MAX_EVALUATIONS, TestEvaluator};
+
+#[test]
+fn test_set_feature_in() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("feature", false);
+    evaluator.set_feature_in([("user", "alice")], "feature", true);
+    evaluator.set_feature_in([("user", "bob"), ("plan", "beta")], "feature", true);
+    evaluator.set_feature_in(
+        |context: &Context| context.parent().is_some_and(|parent| !parent.is_root()),
+        "nested",
+        true,
+    );
+
+    with_default(evaluator, || {
+        assert!(!featureflag::is_enabled!("feature", true));
+
+        let alice = context!(user = "alice");
+        assert!(featureflag::is_enabled!(context: alice, "feature", false));
+
+        let nested = context!(parent: alice, request = 1);
+        assert!(featureflag::is_enabled!(context: nested, "feature", false));
+        assert!(featureflag::is_enabled!(context: nested, "nested", false));
+        assert!(!featureflag::is_enabled!(context: alice, "nested", false));
+
+        let bob = context!(user = "bob");
+        assert!(!featureflag::is_enabled!(context: bob, "feature", true));
+
+        let bob_beta = context!(user = "bob", plan = "beta");
+        assert!(featureflag::is_enabled!(context: bob_beta, "feature", false));
+    });
+}
+
+#[test]
+fn test_evaluation_recording() {
+    let evaluator = Arc::new(TestEvaluator::new());
+    evaluator.set_feature("enabled", true);
+    evaluator.record_evaluations(true);
+
+    let context = with_default(evaluator.clone(), || {
+        assert!(featureflag::is_enabled!("enabled", false));
+
+        let context = context!(user = "alice");
+        assert!(!featureflag::is_enabled!(context: context, "unset", false));
+        context
+    });
+
+    evaluator.assert_evaluated("enabled");
+    evaluator.assert_evaluated("unset");
+    evaluator.assert_not_evaluated("other");
+
+    let evaluations = evaluator.evaluations();
+    assert_eq!(evaluations.len(), 2);
+    assert_eq!(evaluations[0].feature(), "enabled");
+    assert_eq!(evaluations[0].result(), Some(true));
+    assert_eq!(evaluations[1].feature(), "unset");
+    assert_eq!(evaluations[1].result(), None);
+    assert_eq!(evaluations[1].context_id(), context.id());
+
+    evaluator.clear_evaluations();
+    evaluator.assert_not_evaluated("enabled");
+
+    evaluator.record_evaluations(false);
+    for _ in 0..10 {
+        evaluator.is_enabled("enabled", &Context::root());
+    }
+    assert!(evaluator.evaluations().is_empty());
+
+    evaluator.record_evaluations(true);
+    for _ in 0..MAX_EVALUATIONS + 1 {
+        evaluator.is_enabled("enabled", &Context::root());
+    }
+    assert_eq!(evaluator.evaluations().len(), MAX_EVALUATIONS);
+}
+
+#[test]
+#[should_panic = "expected feature \"feature\" to be evaluated"]
+fn test_assert_evaluated_panics() {
+    let evaluator = TestEvaluator::new();
+    evaluator.record_evaluations(true);
+    evaluator.assert_evaluated("feature");
+}
+
+#[test]
+#[should_panic = "evaluations are not recorded"]
+fn test_assert_evaluated_without_recording_panics() {
+    TestEvaluator::new().assert_not_evaluated("feature");
+}
+
+#[test]
+fn test_load_fixture_errors() {
+    let evaluator = TestEvaluator::new();
+    assert!(evaluator.load_json(r#"{ "a": 1 }"#).is_err());
+    assert!(evaluator.load_toml("a = 1").is_err());
+    assert!(evaluator.load_toml("a = [").is_err());
+
+    evaluator
+        .load_toml("a = true\n[b]\nc = { d = false }")
+        .unwrap();
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("a", false));
+        assert!(!featureflag::is_enabled!("b.c.d", true));
+    });
+}
+
+#[test]
+fn test_clone_handle() {
+    let evaluator = TestEvaluator::new();
+    evaluator.record_evaluations(true);
+    let handle = evaluator.clone();
+
+    with_default(evaluator, || {
+        assert!(!featureflag::is_enabled!("feature", false));
+
+        handle.set_feature("feature", true);
+        assert!(featureflag::is_enabled!("feature", false));
+
+        handle.clear_feature("feature");
+        assert!(!featureflag::is_enabled!("feature", false));
+    });
+
+    handle.assert_evaluated("feature");
+}
+
+#[test]
+fn test_bulk_setup_and_reset() {
+    let evaluator = TestEvaluator::with_features([("a", true), ("b", false)]);
+    evaluator.set_all([("b", true), ("c", true)]);
+    evaluator.set_feature_in([("user", "alice")], "d", true);
+    evaluator.record_evaluations(true);
+
+    with_default(evaluator.clone(), || {
+        assert!(featureflag::is_enabled!("a", false));
+        assert!(featureflag::is_enabled!("b", false));
+        assert!(featureflag::is_enabled!("c", false));
+
+        evaluator.reset();
+        evaluator.assert_not_evaluated("a");
+
+        assert!(!featureflag::is_enabled!("a", false));
+        assert!(!featureflag::is_enabled!("b", false));
+        let alice = context!(user = "alice");
+        assert!(!featureflag::is_enabled!(context: alice, "d", false));
+    });
+}
+
+#[test]
+fn test_record_usage() {
+    let evaluator = TestEvaluator::with_features([("enabled", true), ("disabled", false)]);
+
+    let (result, usage) = with_default(evaluator, || {
+        featureflag_test::record_usage(|| {
+            let enabled = featureflag::is_enabled!("enabled", false);
+            featureflag::is_enabled!("disabled", true);
+            featureflag::is_enabled!("defaulted", true);
+            featureflag::feature!("state_only", false).get_state();
+            enabled
+        })
+    });
+
+    assert!(result);
+    assert_eq!(
+        usage.features(),
+        ["defaulted", "disabled", "enabled", "state_only"]
+    );
+    assert!(usage.contains("enabled"));
+    assert!(!usage.contains("other"));
+    usage.assert_snapshot(
+        "
+        enabled: true
+        disabled: false
+        defaulted: true (default)
+        state_only: unset
+        ",
+    );
+}
+
+#[test]
+#[should_panic = "feature usage does not match snapshot"]
+fn test_record_usage_mismatch() {
+    let ((), usage) = featureflag_test::record_usage(|| {
+        featureflag::is_enabled!("feature", false);
+    });
+    usage.assert_snapshot("other: false (default)");
+}
+
+#[test]
+fn test_conformance() {
+    featureflag_test::conformance::run(
+        || {
+            let evaluator = TestEvaluator::with_features([("enabled", true), ("disabled", false)]);
+            evaluator.set_feature_in([("user", "conformance")], "user", true);
+            evaluator
+        },
+        &["enabled", "disabled", "user", "unset"],
+    );
+}
+
+#[test]
+fn test_concurrent_access() {
+    let evaluator = TestEvaluator::new();
+
+    std::thread::scope(|scope| {
+        for i in 0..8 {
+            let evaluator = evaluator.clone();
+            scope.spawn(move || {
+                for j in 0..100 {
+                    let feature = format!("feature.{i}.{j}");
+                    evaluator.set_feature(&feature, j % 2 == 0);
+                    with_default(evaluator.clone(), || {
+                        assert_eq!(Feature::new(&feature, true).is_enabled(), j % 2 == 0);
+                    });
+                }
+            });
+        }
+    });
+
+    assert_eq!(evaluator.known_flags().unwrap().len(), 800);
+}
@@ -8,7 +8,7 @@
 use std::marker::PhantomData;
 
 use featureflag::{Context, context};
-use featureflag_test::{TestContextExt, with_features};
+use featureflag_test::{TestContextExt, TestEvaluator, with_features};
 
 #[test]
 #[with_features(enabled = true, disabled = false, custom = custom, implicit)]
@@ -88,3 +88,121 @@ impl<'a, T, const N: usize> Foo<'a, T, N> {
         foo::<T, U, N, M>();
     }
 }
+
+#[with_features(enabled = true, disabled = false)]
+async fn async_features() -> (bool, bool) {
+    std::future::ready(()).await;
+    (
+        featureflag::is_enabled!("enabled", false),
+        featureflag::is_enabled!("disabled", true),
+    )
+}
+
+#[test]
+fn test_macro_async() {
+    assert_eq!(block_on(async_features()), (true, false));
+
+    // The features are only enabled within the future.
+    assert!(!featureflag::is_enabled!("enabled", false));
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    use std::{
+        pin::pin,
+        sync::Arc,
+        task::{Context, Poll, Wake, Waker},
+    };
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[with_features(enabled = true)]
+fn enabled_in_scope() -> bool {
+    featureflag::is_enabled!("enabled", false)
+}
+
+#[with_features(enabled = false)]
+fn disabled_in_scope() -> bool {
+    featureflag::is_enabled!("enabled", true)
+}
+
+#[test]
+fn test_macro_scoped() {
+    assert!(enabled_in_scope());
+    assert!(!disabled_in_scope());
+    assert!(enabled_in_scope());
+
+    assert!(!featureflag::is_enabled!("enabled", false));
+}
+
+#[test]
+#[with_features(file = "tests/flags/checkout.json", "checkout.express" = true)]
+fn test_macro_json_fixture() {
+    assert!(featureflag::is_enabled!("checkout.new_flow", false));
+    assert!(featureflag::is_enabled!("checkout.express", false));
+}
+
+#[test]
+#[with_features(file = "tests/flags/checkout.toml")]
+fn test_macro_toml_fixture() {
+    assert!(featureflag::is_enabled!("checkout.new_flow", false));
+    assert!(!featureflag::is_enabled!("checkout.express", true));
+}
+
+fn base_evaluator() -> TestEvaluator {
+    TestEvaluator::with_features([("from_base", true), ("overridden", true)])
+}
+
+#[test]
+#[with_features(evaluator = base_evaluator, "overridden" = false, enabled)]
+fn test_macro_evaluator() {
+    assert!(featureflag::is_enabled!("from_base", false));
+    assert!(!featureflag::is_enabled!("overridden", true));
+    assert!(featureflag::is_enabled!("enabled", false));
+}
+
+#[test]
+#[with_features(enabled)]
+fn test_macro_result() -> Result<(), std::num::ParseIntError> {
+    let n: i32 = "1".parse()?;
+    assert_eq!(n, 1);
+    assert!(featureflag::is_enabled!("enabled", false));
+    Ok(())
+}
+
+#[with_features(enabled)]
+async fn async_result() -> Result<bool, std::num::ParseIntError> {
+    let _: i32 = "1".parse()?;
+    if !featureflag::is_enabled!("enabled", false) {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+#[test]
+fn test_macro_async_result() {
+    assert_eq!(block_on(async_result()), Ok(true));
+}
+
+#[test]
+#[with_features(enabled)]
+fn test_macro_hygiene() {
+    // Locals in the body don't conflict with the generated setup.
+    let __guard = ();
+    let __evaluator = ();
+    let _ = (__guard, __evaluator);
+    assert!(featureflag::is_enabled!("enabled", false));
+}
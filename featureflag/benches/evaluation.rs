@@ -0,0 +1,10 @@
+#![allow(missing_docs)]
+
+use featureflag::evaluator::{EvaluatorExt, NoEvaluator};
+use featureflag_test::bench::evaluator_suite;
+
+fn main() {
+    for result in evaluator_suite(NoEvaluator.fallback([("feature", true)])) {
+        println!("{result}");
+    }
+}
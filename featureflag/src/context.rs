@@ -1,11 +1,17 @@
 //! Context values for context-aware features.
 
+#[cfg(feature = "std")]
 mod stack;
 
-use std::{fmt, sync::Arc};
+use alloc::sync::Arc;
+use core::{
+    fmt,
+    sync::atomic::{AtomicU8, AtomicU64, Ordering},
+};
 
+#[cfg(feature = "std")]
+use crate::context::stack::GLOBAL_CONTEXT_STACK;
 use crate::{
-    context::stack::GLOBAL_CONTEXT_STACK,
     evaluator::{Evaluator, EvaluatorRef, WeakEvaluatorRef, get_default},
     extensions::Extensions,
     fields::Fields,
@@ -24,6 +30,7 @@ pub struct Context {
 }
 
 struct Data {
+    id: ContextId,
     evaluator: WeakEvaluatorRef,
     parent: Option<Context>,
     extensions: Extensions,
@@ -44,31 +51,107 @@ impl Context {
     ///
     /// The context is associated with the current evaluator.
     ///
+    /// If there are no fields or extensions, and the parent context is
+    /// associated with the current evaluator, a clone of the parent context is
+    /// returned instead, since it would behave the same. This avoids an
+    /// allocation for empty child contexts, and the evaluator is not notified
+    /// about them. Likewise, the parent is returned if it is
+    /// [detached](Self::is_detached) and there is no current evaluator.
+    ///
     /// In most cases, you should use the [`context!`] macro to create a context
     /// instead of using this constructor.
-    pub fn new_with_parent(mut parent: Option<&Context>, fields: Fields<'_>) -> Context {
+    pub fn new_with_parent(parent: Option<&Context>, fields: Fields<'_>) -> Context {
+        Context::new_with_parts(parent, Extensions::new(), |with_fields| with_fields(fields))
+    }
+
+    /// Creates a new context with the given parent context, initial extensions
+    /// and lazily built fields.
+    ///
+    /// The extensions are inserted before the evaluator is notified about the
+    /// new context. The `fields` closure is only called if there is a current
+    /// evaluator, and should pass the fields to the callback it receives. Only
+    /// the first call to the callback is used.
+    ///
+    /// This is used by the [`context!`] macro with `extensions:` or `lazy:`.
+    #[doc(hidden)]
+    pub fn new_with_parts<F>(
+        mut parent: Option<&Context>,
+        mut extensions: Extensions,
+        fields: F,
+    ) -> Context
+    where
+        F: FnOnce(&mut dyn FnMut(Fields<'_>)),
+    {
         if parent.is_some_and(|p| p.is_root()) {
             parent = None;
         }
+        if let Some(parent) = parent {
+            extensions.inherit_from(parent.extensions());
+        }
 
         get_default(|evaluator| {
             let data = match evaluator {
                 Some(evaluator) => {
-                    let mut data = Data {
-                        evaluator: evaluator.downgrade(),
-                        parent: parent.cloned(),
-                        extensions: Extensions::new(),
+                    let reusable = parent.filter(|parent| {
+                        parent.data.as_ref().is_some_and(|parent| {
+                            parent.evaluator.refers_to(evaluator)
+                                && extensions.is_inherited_only(&parent.extensions)
+                        })
+                    });
+
+                    let mut extensions = Some(extensions);
+                    let mut data = None;
+                    let mut with_fields = |fields: Fields<'_>| {
+                        let Some(extensions) = extensions.take() else {
+                            return;
+                        };
+                        if fields.is_empty() && reusable.is_some() {
+                            return;
+                        }
+
+                        let mut new_data = Data {
+                            id: ContextId::next(),
+                            evaluator: evaluator.downgrade(),
+                            parent: parent.cloned(),
+                            extensions,
+                        };
+                        evaluator.on_new_context(
+                            ContextRef {
+                                data: &mut new_data,
+                            },
+                            fields,
+                        );
+                        data = Some(new_data);
                     };
-
-                    evaluator.on_new_context(ContextRef { data: &mut data }, fields);
-
-                    data
+                    fields(&mut with_fields);
+                    // in case the closure didn't pass any fields
+                    with_fields(Fields::new(&[]));
+
+                    match (data, reusable) {
+                        (Some(data), _) => data,
+                        (None, Some(parent)) => return parent.clone(),
+                        (None, None) => unreachable!("empty child context without a parent"),
+                    }
+                }
+                _ => {
+                    // fields are ignored without an evaluator, so a child of a
+                    // detached context behaves like its parent
+                    if let Some(parent) = parent.filter(|parent| {
+                        parent.data.as_ref().is_some_and(|parent| {
+                            parent.evaluator.upgrade().is_none()
+                                && extensions.is_inherited_only(&parent.extensions)
+                        })
+                    }) {
+                        return parent.clone();
+                    }
+
+                    Data {
+                        id: ContextId::next(),
+                        evaluator: WeakEvaluatorRef::new(),
+                        parent: parent.cloned(),
+                        extensions,
+                    }
                 }
-                _ => Data {
-                    evaluator: WeakEvaluatorRef::new(),
-                    parent: parent.cloned(),
-                    extensions: Extensions::new(),
-                },
             };
 
             Context {
@@ -90,9 +173,27 @@ impl Context {
         self.data.is_none()
     }
 
+    /// Get the identifier of this context.
+    ///
+    /// Clones of a context have the same identifier, and so do empty child
+    /// contexts that are replaced by their parent, see
+    /// [`new_with_parent`](Self::new_with_parent).
+    pub fn id(&self) -> ContextId {
+        self.data
+            .as_ref()
+            .map(|data| data.id)
+            .unwrap_or(ContextId::ROOT)
+    }
+
     /// Get the current context.
+    ///
+    /// Without the `std` feature, there is no current context, so this always
+    /// returns `None`.
     pub fn current() -> Option<Context> {
-        GLOBAL_CONTEXT_STACK.current()
+        #[cfg(feature = "std")]
+        return GLOBAL_CONTEXT_STACK.current();
+        #[cfg(not(feature = "std"))]
+        return None;
     }
 
     /// Get the current context or the root context if no current context is set.
@@ -122,21 +223,42 @@ impl Context {
 
     /// Iterate over this context and its parents.
     pub fn iter(&self) -> impl Iterator<Item = &Context> {
-        std::iter::successors(Some(self), |context| context.parent())
+        core::iter::successors(Some(self), |context| context.parent())
     }
 
     /// Run a function with this context as the current context.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
     pub fn in_scope<F: FnOnce() -> R, R>(&self, f: F) -> R {
         GLOBAL_CONTEXT_STACK.in_scope(self, f)
     }
 
-    /// Get the evaluator associated with this context.
-    pub(crate) fn evaluator(&self) -> Option<EvaluatorRef> {
+    /// Check if the evaluator associated with this context is gone.
+    ///
+    /// This is the case if the evaluator has been dropped, for example after
+    /// the evaluator set by [`with_default`](crate::evaluator::with_default)
+    /// went out of scope, or if there was no evaluator when the context was
+    /// created. Features are then evaluated according to the
+    /// [`DetachedPolicy`]. The root context is never detached.
+    pub fn is_detached(&self) -> bool {
+        self.data
+            .as_ref()
+            .is_some_and(|data| data.evaluator.upgrade().is_none())
+    }
+
+    /// Call `f` with the evaluator associated with this context.
+    pub(crate) fn with_evaluator<F: FnOnce(Option<&EvaluatorRef>) -> R, R>(&self, f: F) -> R {
         match &self.data {
-            Some(data) => data.evaluator.upgrade(),
+            Some(data) => match data.evaluator.upgrade() {
+                Some(evaluator) => f(Some(&evaluator)),
+                None => match DetachedPolicy::get() {
+                    DetachedPolicy::UseDefaults => f(None),
+                    DetachedPolicy::UseCurrent => get_default(f),
+                },
+            },
             None => {
                 // root context always uses the current default evaluator
-                get_default(|evaluator| evaluator.cloned())
+                crate::evaluator::get_default(f)
             }
         }
     }
@@ -156,12 +278,86 @@ impl Drop for Data {
     }
 }
 
+/// How features are evaluated in a [detached](Context::is_detached) context.
+///
+/// The policy is set for the whole process with [`DetachedPolicy::set`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum DetachedPolicy {
+    /// Use the default value of each feature.
+    ///
+    /// This is the default policy.
+    #[default]
+    UseDefaults,
+
+    /// Use the current default evaluator, like the root context.
+    ///
+    /// The evaluator is not notified about the context, so extensions stored
+    /// by the original evaluator are still present, and extensions the current
+    /// evaluator would store are missing.
+    UseCurrent,
+}
+
+static DETACHED_POLICY: AtomicU8 = AtomicU8::new(DetachedPolicy::UseDefaults as u8);
+
+impl DetachedPolicy {
+    /// Set the policy for all detached contexts.
+    pub fn set(policy: DetachedPolicy) {
+        DETACHED_POLICY.store(policy as u8, Ordering::Relaxed);
+    }
+
+    /// Get the current policy.
+    pub fn get() -> DetachedPolicy {
+        match DETACHED_POLICY.load(Ordering::Relaxed) {
+            1 => DetachedPolicy::UseCurrent,
+            _ => DetachedPolicy::UseDefaults,
+        }
+    }
+}
+
+/// Identifier of a [`Context`], see [`Context::id`].
+///
+/// Identifiers are never reused within a process, so contexts that are
+/// created separately have different identifiers. Contexts that are not
+/// created, like empty child contexts replaced by their parent, share the
+/// identifier of the context they are replaced by.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ContextId(u64);
+
+impl ContextId {
+    /// The identifier of the root context.
+    pub const ROOT: ContextId = ContextId(0);
+
+    fn next() -> ContextId {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        ContextId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Get the identifier as an integer.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ContextId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
 /// A mutable reference to a context being created or destroyed.
 pub struct ContextRef<'a> {
     data: &'a mut Data,
 }
 
 impl ContextRef<'_> {
+    /// Get the identifier of this context.
+    ///
+    /// See [`Context::id`] for more details.
+    pub fn id(&self) -> ContextId {
+        self.data.id
+    }
+
     /// Get the parent context of this context.
     ///
     /// See [`Context::parent`] for more details.
@@ -199,15 +395,70 @@ impl ContextRef<'_> {
 ///
 /// A parent context can be specified with `parent: <parent>`.
 ///
+/// Field values are usually evaluated when the macro is called. With `lazy:`
+/// before the fields, they are only evaluated if there is a current evaluator,
+/// which avoids computing expensive fields when feature flags are not in use.
+///
+/// Values can be inserted into the [`Extensions`](crate::extensions::Extensions) of the new context with
+/// `extensions: [<value>, ...]`, after the parent and before the fields. They
+/// are inserted before the evaluator is notified about the new context, so
+/// evaluators can use them in [`Evaluator::on_new_context`](crate::evaluator::Evaluator::on_new_context).
+///
 /// # Examples
 ///
 /// ```
+/// use featureflag::context;
+///
+/// struct RequestId(u64);
+///
 /// let a = context!(foo = 1, bar = "baz");
 /// let b = context!(parent: a, foo = 2);
 /// let c = context!(parent: None, foo = 3);
+/// let d = context!(parent: a, lazy: foo = 4);
+/// let e = context!(extensions: [RequestId(5)], foo = 5);
+/// assert_eq!(e.extensions().get::<RequestId>().map(|id| id.0), Some(5));
 /// ```
 #[macro_export]
 macro_rules! context {
+    (@__build [$parent:expr] [$($extension:expr),*] lazy: $($fields:tt)*) => {
+        $crate::context::Context::new_with_parts(
+            $parent,
+            $crate::context!(@__extensions $($extension),*),
+            |with_fields| with_fields($crate::fields!($($fields)*)),
+        )
+    };
+    (@__build [$parent:expr] [$($extension:expr),*] $($fields:tt)*) => {
+        match $crate::fields!($($fields)*) {
+            fields => $crate::context::Context::new_with_parts(
+                $parent,
+                $crate::context!(@__extensions $($extension),*),
+                |with_fields| with_fields(fields),
+            ),
+        }
+    };
+    (@__extensions $($extension:expr),*) => {{
+        #[allow(unused_mut)]
+        let mut extensions = $crate::extensions::Extensions::new();
+        $( extensions.insert($extension); )*
+        extensions
+    }};
+
+    (parent: $parent:expr, extensions: [$($extension:expr),* $(,)?] $(, $($fields:tt)*)?) => {
+        $crate::context!(
+            @__build
+            [$crate::context::AsContextParam::as_context_param(&$parent)]
+            [$($extension),*]
+            $($($fields)*)?
+        )
+    };
+    (parent: $parent:expr, lazy: $($fields:tt)*) => {
+        $crate::context!(
+            @__build
+            [$crate::context::AsContextParam::as_context_param(&$parent)]
+            []
+            lazy: $($fields)*
+        )
+    };
     (parent: $parent:expr $(, $($fields:tt)*)?) => {
         $crate::context::Context::new_with_parent(
             $crate::context::AsContextParam::as_context_param(
@@ -216,6 +467,22 @@ macro_rules! context {
             $crate::fields!($($($fields)*)?),
         )
     };
+    (extensions: [$($extension:expr),* $(,)?] $(, $($fields:tt)*)?) => {
+        $crate::context!(
+            @__build
+            [$crate::context::Context::current().as_ref()]
+            [$($extension),*]
+            $($($fields)*)?
+        )
+    };
+    (lazy: $($fields:tt)*) => {
+        $crate::context!(
+            @__build
+            [$crate::context::Context::current().as_ref()]
+            []
+            lazy: $($fields)*
+        )
+    };
     ($($fields:tt)*) => {
         $crate::context::Context::new($crate::fields!($($fields)*))
     };
@@ -1,6 +1,5 @@
 use std::{
     cell::RefCell,
-    mem,
     panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
 };
 
@@ -29,10 +28,7 @@ impl ContextStack {
     pub fn in_scope<F: FnOnce() -> R, R>(&self, context: &Context, f: F) -> R {
         let thread_state = self.thread_state.get_or_default();
 
-        let old_context = mem::replace(
-            &mut *thread_state.current.borrow_mut(),
-            Some(context.clone()),
-        );
+        let old_context = thread_state.current.borrow_mut().replace(context.clone());
 
         let result = catch_unwind(AssertUnwindSafe(f));
 
@@ -0,0 +1,121 @@
+//! This module provides central defaults for feature flags.
+//!
+//! When the evaluator returns `None` for a feature, the default value passed
+//! to [`feature!`](crate::feature!) or [`is_enabled!`](crate::is_enabled!) is
+//! used. A [`DefaultProvider`] set with [`set_default_provider`] is consulted
+//! first, so defaults can be changed in one place instead of at every call
+//! site. The call site default is only used when the provider has no default
+//! for the feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! use featureflag::defaults::{clear_default_provider, set_default_provider};
+//!
+//! set_default_provider(HashMap::from([("new_ui".to_string(), true)]));
+//! assert!(featureflag::is_enabled!("new_ui", false));
+//!
+//! clear_default_provider();
+//! assert!(!featureflag::is_enabled!("new_ui", false));
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+static PROVIDER: RwLock<Option<Arc<dyn DefaultProvider>>> = RwLock::new(None);
+
+// Checked before locking `PROVIDER`, so defaults stay cheap without a provider.
+static HAS_PROVIDER: AtomicBool = AtomicBool::new(false);
+
+/// Provider of default values for feature flags, see the [module
+/// documentation](self).
+///
+/// This trait is automatically implemented for `Fn(&str) -> Option<bool>`
+/// closures, and for maps of feature names to default values.
+pub trait DefaultProvider: Send + Sync + 'static {
+    /// Get the default value of a feature.
+    ///
+    /// Returns `None` to use the default value of the call site.
+    fn default_for(&self, feature: &str) -> Option<bool>;
+}
+
+impl<F: Fn(&str) -> Option<bool> + Send + Sync + 'static> DefaultProvider for F {
+    fn default_for(&self, feature: &str) -> Option<bool> {
+        self(feature)
+    }
+}
+
+impl DefaultProvider for HashMap<String, bool> {
+    fn default_for(&self, feature: &str) -> Option<bool> {
+        self.get(feature).copied()
+    }
+}
+
+/// Default provider using the literal defaults of registered features.
+///
+/// This is useful when features are defined in one place, e.g. with
+/// [`flags_from_file!`](crate::flags_from_file), and checked by name
+/// elsewhere. Features registered with different defaults in different places
+/// have no default in this provider.
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+#[derive(Clone, Debug, Default)]
+pub struct RegistryDefaults {
+    defaults: HashMap<&'static str, Option<bool>>,
+}
+
+#[cfg(feature = "feature-registry")]
+impl RegistryDefaults {
+    /// Collect the defaults of the currently registered features.
+    pub fn new() -> RegistryDefaults {
+        let mut defaults = HashMap::new();
+        for info in crate::registry::all_features() {
+            defaults
+                .entry(info.name())
+                .and_modify(|default: &mut Option<bool>| {
+                    if *default != info.default() {
+                        *default = None;
+                    }
+                })
+                .or_insert(info.default());
+        }
+        RegistryDefaults { defaults }
+    }
+}
+
+#[cfg(feature = "feature-registry")]
+impl DefaultProvider for RegistryDefaults {
+    fn default_for(&self, feature: &str) -> Option<bool> {
+        self.defaults.get(feature).copied().flatten()
+    }
+}
+
+/// Set the default provider, replacing any previous provider.
+pub fn set_default_provider<P: DefaultProvider>(provider: P) {
+    *PROVIDER.write().unwrap() = Some(Arc::new(provider));
+    HAS_PROVIDER.store(true, Ordering::Release);
+}
+
+/// Remove the default provider, so that the defaults of the call sites are
+/// used.
+pub fn clear_default_provider() {
+    HAS_PROVIDER.store(false, Ordering::Release);
+    *PROVIDER.write().unwrap() = None;
+}
+
+/// Get the default value of a feature from the default provider, if any.
+pub fn default_for(feature: &str) -> Option<bool> {
+    if !HAS_PROVIDER.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let provider = PROVIDER.read().unwrap().clone()?;
+    provider.default_for(feature)
+}
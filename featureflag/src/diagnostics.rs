@@ -0,0 +1,933 @@
+//! Diagnostics for feature flag usage.
+//!
+//! With the `feature-registry` feature enabled, evaluations of registered
+//! features can be tracked with [`enable_tracking`], so that features that are
+//! never evaluated can be found with [`unused_features`], and evaluations of
+//! features that are not registered can be caught with
+//! [`set_unknown_feature_hook`].
+//!
+//! With the `callsite-tracking` feature enabled, tracked evaluations are also
+//! counted per callsite, and can be inspected with [`callsites`].
+//!
+//! The features evaluated by a piece of code can be captured with
+//! [`capture_usage`].
+//!
+//! With the `feature-registry` feature enabled, the registered features can be
+//! compared against the features known by an evaluator with [`flag_drift`], and
+//! features registered with conflicting defaults or metadata can be found with
+//! [`feature_conflicts`].
+//!
+//! A [`report`] of the installed evaluator and the registered features can be
+//! embedded in the debug or health endpoints of a service.
+
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc,
+        atomic::{self, AtomicUsize},
+    },
+};
+
+#[cfg(any(feature = "callsite-tracking", feature = "feature-registry"))]
+use std::{
+    collections::HashMap,
+    sync::{
+        RwLock,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+#[cfg(feature = "callsite-tracking")]
+use std::{
+    hash::{BuildHasherDefault, Hasher},
+    panic::Location,
+    sync::atomic::AtomicU64,
+};
+#[cfg(feature = "feature-registry")]
+use std::{
+    sync::{Condvar, LazyLock, Mutex, atomic::AtomicBool},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+#[cfg(feature = "json")]
+use std::time::UNIX_EPOCH;
+
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+#[cfg(feature = "json")]
+use crate::evaluator::HealthStatus;
+use crate::{
+    evaluator::{Evaluator, EvaluatorDescription, Health, current},
+    feature::shared_name,
+};
+#[cfg(feature = "feature-registry")]
+use crate::{
+    feature::known_features,
+    registry::{FeatureInfo, all_features, registered_features},
+};
+
+/// What [`record_evaluation`] records, so evaluations only check a single
+/// atomic when nothing is recorded.
+#[cfg(any(feature = "callsite-tracking", feature = "feature-registry"))]
+static RECORDING: AtomicU8 = AtomicU8::new(0);
+
+/// Evaluations are tracked, see [`enable_tracking`].
+#[cfg(any(feature = "callsite-tracking", feature = "feature-registry"))]
+const RECORD_TRACKING: u8 = 1 << 0;
+
+/// Evaluations of unknown features are passed to the hook set by
+/// [`set_unknown_feature_hook`].
+#[cfg(feature = "feature-registry")]
+const RECORD_UNKNOWN_FEATURES: u8 = 1 << 1;
+
+#[cfg(feature = "feature-registry")]
+static EVALUATED: LazyLock<HashMap<&'static str, AtomicBool>> = LazyLock::new(|| {
+    registered_features()
+        .iter()
+        .map(|info| (info.name(), AtomicBool::new(false)))
+        .collect()
+});
+
+/// Evaluation state of features registered with
+/// [`register_dynamic`](crate::registry::register_dynamic).
+#[cfg(feature = "feature-registry")]
+static DYNAMIC_EVALUATED: RwLock<Option<HashMap<&'static str, AtomicBool>>> = RwLock::new(None);
+
+#[cfg(feature = "feature-registry")]
+static HAS_DYNAMIC_FEATURES: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "feature-registry")]
+type UnknownFeatureHook = Arc<dyn Fn(&str) + Send + Sync>;
+
+#[cfg(feature = "feature-registry")]
+static UNKNOWN_FEATURE_HOOK: RwLock<Option<UnknownFeatureHook>> = RwLock::new(None);
+
+/// Callsites by the address of their location.
+///
+/// Hashing a [`Location`] hashes its file name, so the address is used as the
+/// key instead. The same location can have multiple addresses, which are
+/// merged by [`callsites`].
+#[cfg(feature = "callsite-tracking")]
+type CallsiteCounts = HashMap<usize, Callsite, BuildHasherDefault<AddressHasher>>;
+
+#[cfg(feature = "callsite-tracking")]
+struct Callsite {
+    location: &'static Location<'static>,
+    /// Evaluation counts per feature, which is usually a single feature.
+    counts: Vec<(Arc<str>, AtomicU64)>,
+}
+
+#[cfg(feature = "callsite-tracking")]
+impl Callsite {
+    fn count(&self, feature: &str) -> Option<&AtomicU64> {
+        self.counts
+            .iter()
+            .find(|(name, _)| &**name == feature)
+            .map(|(_, count)| count)
+    }
+}
+
+#[cfg(feature = "callsite-tracking")]
+#[derive(Default)]
+struct AddressHasher(u64);
+
+#[cfg(feature = "callsite-tracking")]
+impl Hasher for AddressHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        // addresses call `write_usize`, but other keys are hashed with FNV-1a
+        let mut hash = self.0 ^ 0xcbf2_9ce4_8422_2325;
+        for &b in bytes {
+            hash = (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+        }
+        self.0 = hash;
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        // mix the address, since the low bits of aligned addresses are zero
+        let hash = (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        self.0 = hash ^ (hash >> 32);
+    }
+}
+
+#[cfg(feature = "callsite-tracking")]
+static CALLSITES: RwLock<Option<CallsiteCounts>> = RwLock::new(None);
+
+static CAPTURING_USAGE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static USAGE_CAPTURES: RefCell<Vec<Vec<FeatureUsage>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A feature evaluation captured by [`capture_usage`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeatureUsage {
+    feature: Arc<str>,
+    state: Option<bool>,
+    enabled: Option<bool>,
+}
+
+impl FeatureUsage {
+    /// Get the name of the evaluated feature.
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    /// Get the state returned by the evaluator.
+    ///
+    /// `None` means that the feature's default value was used.
+    pub fn state(&self) -> Option<bool> {
+        self.state
+    }
+
+    /// Get whether the feature was enabled, including its default value.
+    ///
+    /// This is `None` if only the state of the feature was evaluated, e.g.
+    /// with [`Feature::get_state`](crate::Feature::get_state), since the
+    /// default value is not evaluated in that case.
+    pub fn enabled(&self) -> Option<bool> {
+        self.enabled
+    }
+
+    /// Check if the default value of the feature was used.
+    pub fn is_default(&self) -> bool {
+        self.state.is_none()
+    }
+}
+
+/// Call `f`, capturing the features evaluated on the current thread.
+///
+/// Evaluations are returned in order. Evaluations on other threads, including
+/// in futures polled on other threads, are not captured. Captures can be
+/// nested, in which case evaluations are captured by all of them.
+pub fn capture_usage<F: FnOnce() -> R, R>(f: F) -> (R, Vec<FeatureUsage>) {
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            CAPTURING_USAGE.fetch_sub(1, atomic::Ordering::Relaxed);
+            USAGE_CAPTURES.with_borrow_mut(|captures| captures.pop());
+        }
+    }
+
+    USAGE_CAPTURES.with_borrow_mut(|captures| captures.push(Vec::new()));
+    CAPTURING_USAGE.fetch_add(1, atomic::Ordering::Relaxed);
+    let guard = Guard;
+
+    let result = f();
+    let usage = USAGE_CAPTURES
+        .with_borrow_mut(|captures| captures.last_mut().map(std::mem::take))
+        .unwrap_or_default();
+
+    drop(guard);
+    (result, usage)
+}
+
+/// Record a feature evaluation for [`capture_usage`].
+pub(crate) fn record_usage(feature: &str, state: Option<bool>, enabled: Option<bool>) {
+    if CAPTURING_USAGE.load(atomic::Ordering::Relaxed) == 0 {
+        return;
+    }
+
+    USAGE_CAPTURES.with_borrow_mut(|captures| {
+        for capture in captures {
+            capture.push(FeatureUsage {
+                feature: shared_name(feature),
+                state,
+                enabled,
+            });
+        }
+    });
+}
+
+#[cfg(any(feature = "callsite-tracking", feature = "feature-registry"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "callsite-tracking", feature = "feature-registry")))
+)]
+/// Start tracking evaluations for [`unused_features`] and [`callsites`].
+///
+/// Evaluations are not tracked by default, so that evaluating a feature
+/// doesn't look it up in the registry or count its callsite. Call this early,
+/// e.g. at startup, since earlier evaluations are not tracked.
+pub fn enable_tracking() {
+    RECORDING.fetch_or(RECORD_TRACKING, Ordering::Relaxed);
+}
+
+/// Record that a feature has been evaluated.
+#[inline]
+#[cfg_attr(feature = "callsite-tracking", track_caller)]
+pub(crate) fn record_evaluation(feature: &str) {
+    #[cfg(any(feature = "callsite-tracking", feature = "feature-registry"))]
+    {
+        let recording = RECORDING.load(Ordering::Relaxed);
+        if recording != 0 {
+            record_evaluation_slow(feature, recording);
+        }
+    }
+
+    #[cfg(not(any(feature = "callsite-tracking", feature = "feature-registry")))]
+    let _ = feature;
+}
+
+#[cfg(any(feature = "callsite-tracking", feature = "feature-registry"))]
+#[cold]
+#[cfg_attr(feature = "callsite-tracking", track_caller)]
+fn record_evaluation_slow(feature: &str, recording: u8) {
+    let tracking = recording & RECORD_TRACKING != 0;
+
+    #[cfg(feature = "callsite-tracking")]
+    if tracking {
+        record_callsite(feature, Location::caller());
+    }
+    #[cfg(feature = "feature-registry")]
+    match EVALUATED.get(feature) {
+        // only write once, so evaluations on many threads don't contend
+        Some(evaluated) if tracking && !evaluated.load(Ordering::Relaxed) => {
+            evaluated.store(true, Ordering::Relaxed)
+        }
+        Some(_) => {}
+        None if HAS_DYNAMIC_FEATURES.load(Ordering::Acquire)
+            && record_dynamic(feature, tracking) => {}
+        None if recording & RECORD_UNKNOWN_FEATURES != 0 => {
+            // the hook is called without holding the lock, so it can replace itself
+            let hook = UNKNOWN_FEATURE_HOOK.read().unwrap().clone();
+            if let Some(hook) = hook {
+                hook(feature);
+            }
+        }
+        None => {}
+    }
+}
+
+/// Record that a dynamic feature has been evaluated, returning `false` if the
+/// feature is not registered.
+#[cfg(feature = "feature-registry")]
+fn record_dynamic(feature: &str, tracking: bool) -> bool {
+    match DYNAMIC_EVALUATED
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|evaluated| evaluated.get(feature))
+    {
+        Some(evaluated) => {
+            if tracking {
+                evaluated.store(true, Ordering::Relaxed);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Start tracking evaluations of a feature registered at runtime.
+#[cfg(feature = "feature-registry")]
+pub(crate) fn register_dynamic_feature(name: &'static str) {
+    if EVALUATED.contains_key(name) {
+        return;
+    }
+
+    DYNAMIC_EVALUATED
+        .write()
+        .unwrap()
+        .get_or_insert_default()
+        .entry(name)
+        .or_insert_with(|| AtomicBool::new(false));
+    HAS_DYNAMIC_FEATURES.store(true, Ordering::Release);
+}
+
+#[cfg(feature = "callsite-tracking")]
+fn record_callsite(feature: &str, location: &'static Location<'static>) {
+    let address = location as *const Location<'static> as usize;
+    if let Some(count) = CALLSITES
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|callsites| callsites.get(&address)?.count(feature))
+    {
+        count.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+
+    let mut callsites = CALLSITES.write().unwrap();
+    let callsite = callsites
+        .get_or_insert_default()
+        .entry(address)
+        .or_insert_with(|| Callsite {
+            location,
+            counts: Vec::new(),
+        });
+    match callsite.count(feature) {
+        Some(count) => {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+        None => callsite
+            .counts
+            .push((shared_name(feature), AtomicU64::new(1))),
+    }
+}
+
+#[cfg(feature = "callsite-tracking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "callsite-tracking")))]
+/// Evaluation count of a feature at a single callsite, see [`callsites`].
+#[derive(Clone, Debug)]
+pub struct CallsiteUsage {
+    feature: Arc<str>,
+    location: &'static Location<'static>,
+    count: u64,
+}
+
+#[cfg(feature = "callsite-tracking")]
+impl CallsiteUsage {
+    /// Get the name of the evaluated feature.
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    /// Get the location of the callsite.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// Get the number of times the feature was evaluated at the callsite.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+#[cfg(feature = "callsite-tracking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "callsite-tracking")))]
+/// Get the evaluation counts of all callsites that have evaluated a feature,
+/// sorted by feature name and location.
+///
+/// Callsites are the locations of [`is_enabled!`](crate::is_enabled) macros,
+/// or calls to methods like [`Feature::is_enabled`](crate::Feature::is_enabled).
+/// Only evaluations after [`enable_tracking`] are counted.
+pub fn callsites() -> Vec<CallsiteUsage> {
+    let mut usages = CALLSITES
+        .read()
+        .unwrap()
+        .iter()
+        .flat_map(|callsites| callsites.values())
+        .flat_map(|callsite| {
+            callsite
+                .counts
+                .iter()
+                .map(|(feature, count)| CallsiteUsage {
+                    feature: feature.clone(),
+                    location: callsite.location,
+                    count: count.load(Ordering::Relaxed),
+                })
+        })
+        .collect::<Vec<_>>();
+
+    fn key(usage: &CallsiteUsage) -> (&str, &str, u32, u32) {
+        (
+            &usage.feature,
+            usage.location.file(),
+            usage.location.line(),
+            usage.location.column(),
+        )
+    }
+    usages.sort_by(|a, b| key(a).cmp(&key(b)));
+    usages.dedup_by(|next, usage| {
+        let same = key(next) == key(usage);
+        if same {
+            usage.count += next.count;
+        }
+        same
+    });
+    usages
+}
+
+#[cfg(feature = "callsite-tracking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "callsite-tracking")))]
+/// Get the evaluation counts of all callsites that have evaluated the given
+/// feature.
+///
+/// See [`callsites`] for more details.
+pub fn callsites_for(feature: &str) -> Vec<CallsiteUsage> {
+    let mut usages = callsites();
+    usages.retain(|usage| &*usage.feature == feature);
+    usages
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Get all registered features that have not been evaluated since
+/// [`enable_tracking`] was called, sorted by name.
+///
+/// See [`known_features`] for which features are registered.
+pub fn unused_features() -> Vec<&'static str> {
+    let dynamic = DYNAMIC_EVALUATED.read().unwrap();
+    let mut unused = EVALUATED
+        .iter()
+        .chain(dynamic.iter().flatten())
+        .filter(|(_, evaluated)| !evaluated.load(Ordering::Relaxed))
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    unused.sort_unstable();
+    unused
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Set a hook that is called when a feature that is not registered is evaluated.
+///
+/// This can be used to catch typos in feature names, which would otherwise
+/// silently fall back to the default value. Features created at runtime with
+/// [`Feature::new`](crate::Feature::new) are not registered, so they will also
+/// trigger the hook.
+///
+/// The hook replaces any previously set hook.
+///
+/// # Examples
+///
+/// ```
+/// featureflag::diagnostics::set_unknown_feature_hook(|feature| {
+///     debug_assert!(false, "unknown feature {feature:?}");
+/// });
+/// ```
+pub fn set_unknown_feature_hook<F>(hook: F)
+where
+    F: Fn(&str) + Send + Sync + 'static,
+{
+    *UNKNOWN_FEATURE_HOOK.write().unwrap() = Some(Arc::new(hook));
+    RECORDING.fetch_or(RECORD_UNKNOWN_FEATURES, Ordering::Relaxed);
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Remove the hook set by [`set_unknown_feature_hook`].
+pub fn clear_unknown_feature_hook() {
+    RECORDING.fetch_and(!RECORD_UNKNOWN_FEATURES, Ordering::Relaxed);
+    *UNKNOWN_FEATURE_HOOK.write().unwrap() = None;
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Periodically report unused features.
+///
+/// This spawns a background thread that calls `report` with the result of
+/// [`unused_features`] every `interval`. The thread is stopped when the returned
+/// handle is dropped.
+pub fn report_unused_features<F>(interval: Duration, report: F) -> ReportHandle
+where
+    F: Fn(&[&'static str]) + Send + 'static,
+{
+    let stop = Arc::new((Mutex::new(false), Condvar::new()));
+
+    let thread = thread::spawn({
+        let stop = stop.clone();
+        move || {
+            let (stopped, condvar) = &*stop;
+            let mut stopped = stopped.lock().unwrap();
+            loop {
+                stopped = condvar.wait_timeout(stopped, interval).unwrap().0;
+                if *stopped {
+                    break;
+                }
+                report(&unused_features());
+            }
+        }
+    });
+
+    ReportHandle {
+        stop,
+        thread: Some(thread),
+    }
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Handle for a periodic report, see [`report_unused_features`].
+///
+/// The report is stopped when the handle is dropped.
+pub struct ReportHandle {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(feature = "feature-registry")]
+impl Drop for ReportHandle {
+    fn drop(&mut self) {
+        let (stopped, condvar) = &*self.stop;
+        *stopped.lock().unwrap() = true;
+        condvar.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Compare the registered features against the features known by `evaluator`.
+///
+/// Returns `None` if the evaluator doesn't know which features it has, see
+/// [`Evaluator::known_flags`].
+///
+/// See [`known_features`] for which features are registered.
+pub fn flag_drift<E: Evaluator + ?Sized>(evaluator: &E) -> Option<FlagDrift> {
+    let known_flags = evaluator.known_flags()?;
+    let registered = known_features();
+
+    let mut missing_in_evaluator = registered
+        .iter()
+        .filter(|name| !known_flags.iter().any(|flag| flag == *name))
+        .copied()
+        .collect::<Vec<_>>();
+    missing_in_evaluator.sort_unstable();
+
+    let mut missing_in_code = known_flags
+        .into_iter()
+        .filter(|flag| !registered.contains(flag.as_str()))
+        .collect::<Vec<_>>();
+    missing_in_code.sort_unstable();
+    missing_in_code.dedup();
+
+    Some(FlagDrift {
+        missing_in_evaluator,
+        missing_in_code,
+    })
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Differences between the registered features and the features known by an
+/// evaluator, see [`flag_drift`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlagDrift {
+    missing_in_evaluator: Vec<&'static str>,
+    missing_in_code: Vec<String>,
+}
+
+#[cfg(feature = "feature-registry")]
+impl FlagDrift {
+    /// Get the registered features that are not known by the evaluator, sorted
+    /// by name.
+    ///
+    /// These features always use their default value.
+    pub fn missing_in_evaluator(&self) -> &[&'static str] {
+        &self.missing_in_evaluator
+    }
+
+    /// Get the features known by the evaluator that are not registered, sorted
+    /// by name.
+    ///
+    /// These features are either unused, or only used with names that are not
+    /// known at compile-time.
+    pub fn missing_in_code(&self) -> &[String] {
+        &self.missing_in_code
+    }
+
+    /// Check if there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.missing_in_evaluator.is_empty() && self.missing_in_code.is_empty()
+    }
+}
+
+#[cfg(feature = "feature-registry")]
+impl std::fmt::Display for FlagDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("no drift");
+        }
+
+        let mut separator = "";
+        if !self.missing_in_evaluator.is_empty() {
+            write!(
+                f,
+                "missing in evaluator: {}",
+                self.missing_in_evaluator.join(", ")
+            )?;
+            separator = "; ";
+        }
+        if !self.missing_in_code.is_empty() {
+            write!(
+                f,
+                "{separator}missing in code: {}",
+                self.missing_in_code.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Get all features that are registered with conflicting definitions, sorted
+/// by name.
+///
+/// Definitions conflict if they have different default values, or if they both
+/// have metadata and the metadata is different. Conflicting defaults make the
+/// behavior of a feature depend on where it is evaluated, which is usually a
+/// mistake.
+pub fn feature_conflicts() -> Vec<FeatureConflict> {
+    let mut conflicts = Vec::new();
+
+    // registered features are sorted by name
+    for definitions in registered_features().chunk_by(|a, b| a.name() == b.name()) {
+        let conflicting = definitions.iter().any(|a| {
+            definitions.iter().any(|b| {
+                a.default_expr() != b.default_expr()
+                    || (!a.metadata().is_empty()
+                        && !b.metadata().is_empty()
+                        && a.metadata() != b.metadata())
+            })
+        });
+
+        if conflicting {
+            conflicts.push(FeatureConflict {
+                name: definitions[0].name(),
+                definitions: definitions.to_vec(),
+            });
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// Call `warn` for each feature with conflicting definitions.
+///
+/// This is intended to be called once at startup, with a function that logs
+/// the conflict as a warning. Returns `true` if any conflicts were found.
+///
+/// See [`feature_conflicts`] for which definitions conflict.
+///
+/// # Examples
+///
+/// ```
+/// featureflag::diagnostics::warn_feature_conflicts(|conflict| {
+///     eprintln!("warning: {conflict}");
+/// });
+/// ```
+pub fn warn_feature_conflicts<F: FnMut(&FeatureConflict)>(mut warn: F) -> bool {
+    let conflicts = feature_conflicts();
+    for conflict in &conflicts {
+        warn(conflict);
+    }
+    !conflicts.is_empty()
+}
+
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+/// A feature registered with conflicting definitions, see [`feature_conflicts`].
+#[derive(Clone, Debug)]
+pub struct FeatureConflict {
+    name: &'static str,
+    definitions: Vec<FeatureInfo>,
+}
+
+#[cfg(feature = "feature-registry")]
+impl FeatureConflict {
+    /// Get the name of the feature.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get all definitions of the feature, including ones that don't conflict.
+    pub fn definitions(&self) -> &[FeatureInfo] {
+        &self.definitions
+    }
+}
+
+#[cfg(feature = "feature-registry")]
+impl std::fmt::Display for FeatureConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "feature {:?} has conflicting definitions", self.name)?;
+        for (i, info) in self.definitions.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { ", " };
+            write!(
+                f,
+                "{separator}default {} at {}:{}",
+                info.default_expr(),
+                info.file(),
+                info.line()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Diagnostics report of the default evaluator and the registered features,
+/// see [`report`].
+#[derive(Clone, Debug)]
+pub struct DiagnosticsReport {
+    evaluator: Option<EvaluatorDescription>,
+    health: Health,
+    known_flags: Option<Vec<String>>,
+    #[cfg(feature = "feature-registry")]
+    features: Vec<FeatureInfo>,
+    #[cfg(feature = "feature-registry")]
+    unused_features: Vec<&'static str>,
+}
+
+/// Build a diagnostics report of the default evaluator currently in scope.
+///
+/// The report contains the description, health and known flags of the
+/// evaluator, and with the `feature-registry` feature enabled, the registered
+/// features and which of them are unused. It can be serialized with
+/// [`DiagnosticsReport::to_json`].
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::{diagnostics, evaluator::{NoEvaluator, with_default}};
+///
+/// with_default(NoEvaluator, || {
+///     let report = diagnostics::report();
+///     assert!(report.evaluator().is_some());
+///     assert!(report.health().is_ready());
+/// });
+/// ```
+pub fn report() -> DiagnosticsReport {
+    let evaluator = current();
+    DiagnosticsReport {
+        evaluator: evaluator.as_ref().map(|evaluator| evaluator.describe()),
+        health: crate::evaluator::health(),
+        known_flags: evaluator.and_then(|evaluator| evaluator.known_flags()),
+        #[cfg(feature = "feature-registry")]
+        features: all_features(),
+        #[cfg(feature = "feature-registry")]
+        unused_features: unused_features(),
+    }
+}
+
+impl DiagnosticsReport {
+    /// Get the description of the evaluator, or `None` if no evaluator is set.
+    pub fn evaluator(&self) -> Option<&EvaluatorDescription> {
+        self.evaluator.as_ref()
+    }
+
+    /// Get the health of the evaluator, including when it was last refreshed.
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// Get the flags known by the evaluator, see [`Evaluator::known_flags`].
+    pub fn known_flags(&self) -> Option<&[String]> {
+        self.known_flags.as_deref()
+    }
+
+    /// Get the registered features, see [`registered_features`] and
+    /// [`dynamic_features`](crate::registry::dynamic_features).
+    #[cfg(feature = "feature-registry")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+    pub fn features(&self) -> &[FeatureInfo] {
+        &self.features
+    }
+
+    /// Get the registered features that have not been evaluated, see
+    /// [`unused_features`].
+    #[cfg(feature = "feature-registry")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+    pub fn unused_features(&self) -> &[&'static str] {
+        &self.unused_features
+    }
+
+    /// Serialize the report as a JSON object.
+    ///
+    /// The object has the fields `evaluator` (a tree of `name`, `summary` and
+    /// `children`, or `null`), `health` (with `status`, `reason` and
+    /// `last_refresh` in milliseconds since the Unix epoch), and `known_flags`.
+    /// With the `feature-registry` feature enabled, it also has the fields
+    /// `features` and `unused_features`.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn to_json(&self) -> String {
+        let report = ReportJson {
+            evaluator: self.evaluator.as_ref().map(DescriptionJson::new),
+            health: HealthJson {
+                status: match self.health.status() {
+                    HealthStatus::Ready => "ready",
+                    HealthStatus::Degraded(_) => "degraded",
+                    HealthStatus::NotReady(_) => "not_ready",
+                },
+                reason: self.health.reason(),
+                last_refresh: self
+                    .health
+                    .last_refresh()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .and_then(|time| u64::try_from(time.as_millis()).ok()),
+            },
+            known_flags: self.known_flags.as_deref(),
+            #[cfg(feature = "feature-registry")]
+            features: self
+                .features
+                .iter()
+                .map(|info| FeatureJson {
+                    name: info.name(),
+                    default: info.default(),
+                    module_path: info.module_path(),
+                    file: info.file(),
+                    line: info.line(),
+                })
+                .collect(),
+            #[cfg(feature = "feature-registry")]
+            unused_features: &self.unused_features,
+        };
+        serde_json::to_string(&report).expect("diagnostics report is serializable")
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct ReportJson<'a> {
+    evaluator: Option<DescriptionJson<'a>>,
+    health: HealthJson<'a>,
+    known_flags: Option<&'a [String]>,
+    #[cfg(feature = "feature-registry")]
+    features: Vec<FeatureJson>,
+    #[cfg(feature = "feature-registry")]
+    unused_features: &'a [&'static str],
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct DescriptionJson<'a> {
+    name: &'a str,
+    summary: Option<&'a str>,
+    children: Vec<DescriptionJson<'a>>,
+}
+
+#[cfg(feature = "json")]
+impl DescriptionJson<'_> {
+    fn new(description: &EvaluatorDescription) -> DescriptionJson<'_> {
+        DescriptionJson {
+            name: description.name(),
+            summary: description.summary(),
+            children: description
+                .children()
+                .iter()
+                .map(DescriptionJson::new)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct HealthJson<'a> {
+    status: &'static str,
+    reason: Option<&'a str>,
+    last_refresh: Option<u64>,
+}
+
+#[cfg(all(feature = "json", feature = "feature-registry"))]
+#[derive(Serialize)]
+struct FeatureJson {
+    name: &'static str,
+    default: Option<bool>,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+}
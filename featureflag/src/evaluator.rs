@@ -2,27 +2,78 @@
 //!
 //! This module defines the [`Evaluator`] trait, which is used to evaluate feature flags
 //! at runtime. It also provides utilities for composing evaluators, such as
-//! [`Filter`] and [`Chain`], as well as a default evaluator, [`NoEvaluator`], which
+//! [`Filter`], [`Chain`] and [`Hooked`], as well as a default evaluator, [`NoEvaluator`], which
 //! always returns `None` for feature flags.
 //!
 //! # Global evaluator
 //!
 //! The global evaluator is used by default evaluating feature flags. It can be
 //! set globally using the [`set_global_default`] and [`try_set_global_default`] functions,
-//! locally to a thread using the [`set_thread_default`] and [`try_set_thread_default`] functions,
+//! locally to a thread using the [`set_thread_default`], [`try_set_thread_default`] and
+//! [`set_thread_default_scoped`] functions,
 //! or in a specific scope using the [`with_default`] or [`AnyExt::wrap_evaluator`](crate::utils::AnyExt::wrap_evaluator)
 //! functions. The global evaluator can be accessed using the [`get_default`] function.
 
+#[cfg(feature = "std")]
+mod audit;
+#[cfg(feature = "json")]
+mod bootstrap;
+#[cfg(feature = "std")]
+mod cache;
+mod describe;
+mod either;
+#[cfg(feature = "std")]
+mod exposure;
+#[cfg(feature = "std")]
+mod fallback;
 mod global;
-
-use std::sync::{Arc, LazyLock, Weak};
+mod health;
+mod hook;
+#[cfg(feature = "std")]
+mod indexed;
+#[cfg(feature = "std")]
+mod lazy;
+mod map_names;
+mod map_result;
+#[cfg(feature = "std")]
+mod memoize;
+mod namespace;
+#[cfg(feature = "std")]
+mod persistent;
+#[cfg(feature = "std")]
+mod required;
+mod shadow;
+#[cfg(feature = "std")]
+mod sticky;
+#[cfg(feature = "std")]
+mod switch;
+#[cfg(feature = "std")]
+mod tenant;
+mod trace;
+
+use alloc::sync::{Arc, Weak};
+use core::{any::type_name, marker::PhantomData};
+#[cfg(feature = "std")]
+use std::sync::LazyLock;
 
 use crate::{
-    context::{Context, ContextRef},
+    context::{Context, ContextId, ContextRef},
     fields::Fields,
+    prelude::*,
 };
 
-pub use self::global::*;
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub use self::bootstrap::*;
+#[cfg(feature = "std")]
+pub use self::{
+    audit::*, cache::*, exposure::*, fallback::*, indexed::*, lazy::*, memoize::*, persistent::*,
+    required::*, sticky::*, switch::*, tenant::*,
+};
+pub use self::{
+    describe::*, either::*, global::*, health::*, hook::*, map_names::*, map_result::*,
+    namespace::*, shadow::*, trace::*,
+};
 
 /// Evaluator of feature flags.
 ///
@@ -39,15 +90,75 @@ pub trait Evaluator: Send + Sync {
     /// - `None` if the feature's default value should be used.
     fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool>;
 
+    /// Checks if a feature is enabled in the given context, recording the
+    /// evaluators consulted in `trace`.
+    ///
+    /// The default implementation calls [`is_enabled`](Self::is_enabled) and
+    /// records a single step named after the type of the evaluator. Evaluators
+    /// that delegate to other evaluators should override this method, so that
+    /// the trace shows which inner evaluator produced the result.
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let result = self.is_enabled(feature, context);
+        trace.record(type_name::<Self>(), result);
+        result
+    }
+
+    /// Describe the evaluator, for debugging.
+    ///
+    /// The default implementation returns a description named after the type
+    /// of the evaluator. Evaluators that are composed of other evaluators
+    /// should override this method to include the descriptions of the inner
+    /// evaluators.
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new(type_name::<Self>())
+    }
+
+    /// Get the names of the features known by this evaluator.
+    ///
+    /// This is used to find features that are used in code but not known by
+    /// the evaluator, or the other way around, see
+    /// [`flag_drift`](crate::diagnostics::flag_drift). The default
+    /// implementation returns `None`, which means that the features are not
+    /// known.
+    ///
+    /// Evaluators that delegate to other evaluators should override this
+    /// method to combine the features of the inner evaluators.
+    fn known_flags(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Returns the health of the evaluator.
+    ///
+    /// This is used to check if the evaluator is ready to evaluate features,
+    /// for example in a readiness probe, see [`readiness`]. The default
+    /// implementation returns [`Health::ready`].
+    ///
+    /// Evaluators that delegate to other evaluators should override this
+    /// method to combine the health of the inner evaluators.
+    fn health(&self) -> Health {
+        Health::ready()
+    }
+
     /// Called when the evaluator is registered.
     ///
     /// Functions like [`set_global_default`], [`set_thread_default`] and [`with_default`]
     /// will call this method to notify the evaluator that it has been registered. The evaluator
     /// can use this method to perform any necessary initialization.
     ///
-    /// This method may be called multiple times, so the evaluator should ensure
-    /// that it can handle this.
-    fn on_registration(&self) {}
+    /// This method may be called multiple times. `info` tells where the
+    /// evaluator was registered, and how many times it has been registered,
+    /// so initialization like spawning background tasks can be done only once.
+    ///
+    /// Evaluators that delegate to other evaluators should pass `info` on to
+    /// the inner evaluators.
+    fn on_registration(&self, info: &RegistrationInfo) {
+        let _ = info;
+    }
 
     /// Called when a new context is created.
     ///
@@ -67,6 +178,17 @@ pub trait Evaluator: Send + Sync {
         let _ = context;
     }
 
+    /// Called when the process is shutting down.
+    ///
+    /// [`shutdown`] will call this method on the current evaluator, so that it
+    /// can flush buffered events, metrics or network sessions before the
+    /// process exits. The evaluator may still be used after this method has
+    /// been called.
+    ///
+    /// Evaluators that delegate to other evaluators should override this
+    /// method to shut down the inner evaluators.
+    fn on_shutdown(&self) {}
+
     /// Converts the evaluator into an [`EvaluatorRef`].
     ///
     /// The default implementation calls `EvaluatorRef::from_arc(Arc::new(self))`.
@@ -87,8 +209,29 @@ impl<E: Evaluator> Evaluator for Arc<E> {
         self.as_ref().is_enabled(feature, context)
     }
 
-    fn on_registration(&self) {
-        self.as_ref().on_registration()
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.as_ref().is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        self.as_ref().describe()
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.as_ref().known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.as_ref().health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.as_ref().on_registration(info)
     }
 
     fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
@@ -99,6 +242,10 @@ impl<E: Evaluator> Evaluator for Arc<E> {
         self.as_ref().on_close_context(context)
     }
 
+    fn on_shutdown(&self) {
+        self.as_ref().on_shutdown()
+    }
+
     fn into_ref(self) -> EvaluatorRef
     where
         Self: Sized + 'static,
@@ -112,8 +259,29 @@ impl Evaluator for Arc<dyn Evaluator + Send + Sync> {
         self.as_ref().is_enabled(feature, context)
     }
 
-    fn on_registration(&self) {
-        self.as_ref().on_registration()
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.as_ref().is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        self.as_ref().describe()
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.as_ref().known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.as_ref().health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.as_ref().on_registration(info)
     }
 
     fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
@@ -124,6 +292,10 @@ impl Evaluator for Arc<dyn Evaluator + Send + Sync> {
         self.as_ref().on_close_context(context)
     }
 
+    fn on_shutdown(&self) {
+        self.as_ref().on_shutdown()
+    }
+
     fn into_ref(self) -> EvaluatorRef
     where
         Self: Sized + Send + Sync + 'static,
@@ -141,6 +313,11 @@ impl Evaluator for NoEvaluator {
         None
     }
 
+    fn known_flags(&self) -> Option<Vec<String>> {
+        Some(Vec::new())
+    }
+
+    #[cfg(feature = "std")]
     fn into_ref(self) -> EvaluatorRef {
         static GLOBAL_NO_EVALUATOR: LazyLock<Arc<NoEvaluator>> =
             LazyLock::new(|| Arc::new(NoEvaluator));
@@ -160,6 +337,12 @@ impl EvaluatorRef {
         Self { arc }
     }
 
+    /// Get the address of the evaluator, which identifies its allocation.
+    #[cfg(feature = "std")]
+    pub(crate) fn address(&self) -> usize {
+        Arc::as_ptr(&self.arc).cast::<()>().addr()
+    }
+
     /// Downgrade into a [`WeakEvaluatorRef`].
     pub fn downgrade(&self) -> WeakEvaluatorRef {
         WeakEvaluatorRef {
@@ -173,8 +356,29 @@ impl Evaluator for EvaluatorRef {
         self.arc.is_enabled(feature, context)
     }
 
-    fn on_registration(&self) {
-        self.arc.on_registration()
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.arc.is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        self.arc.describe()
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.arc.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.arc.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.arc.on_registration(info)
     }
 
     fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
@@ -185,11 +389,92 @@ impl Evaluator for EvaluatorRef {
         self.arc.on_close_context(context)
     }
 
+    fn on_shutdown(&self) {
+        self.arc.on_shutdown()
+    }
+
     fn into_ref(self) -> EvaluatorRef {
         self
     }
 }
 
+/// Chains the evaluators in order.
+///
+/// Each evaluator is called in turn until one of them returns `Some`, like
+/// [`EvaluatorExt::chain`]. An empty vector always returns `None`.
+impl Evaluator for Vec<EvaluatorRef> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.iter()
+            .find_map(|evaluator| evaluator.is_enabled(feature, context))
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.iter()
+            .find_map(|evaluator| evaluator.is_enabled_traced(feature, context, trace))
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        self.iter()
+            .fold(EvaluatorDescription::new("Chain"), |desc, evaluator| {
+                desc.with_child(evaluator.describe())
+            })
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        merge_known_flags(self.iter().map(|evaluator| evaluator.known_flags()))
+    }
+
+    fn health(&self) -> Health {
+        Health::combine(self.iter().map(|evaluator| evaluator.health()))
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        for evaluator in self {
+            evaluator.on_registration(info);
+        }
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        for evaluator in self {
+            evaluator.on_new_context(context.by_mut(), fields.clone());
+        }
+    }
+
+    fn on_close_context(&self, mut context: ContextRef<'_>) {
+        for evaluator in self {
+            evaluator.on_close_context(context.by_mut());
+        }
+    }
+
+    fn on_shutdown(&self) {
+        for evaluator in self {
+            evaluator.on_shutdown();
+        }
+    }
+}
+
+/// Merge the known features of multiple evaluators, see
+/// [`Evaluator::known_flags`].
+///
+/// Returns `None` if the features of any of the evaluators are not known.
+pub(crate) fn merge_known_flags<I>(flags: I) -> Option<Vec<String>>
+where
+    I: IntoIterator<Item = Option<Vec<String>>>,
+{
+    let mut merged = Vec::new();
+    for flags in flags {
+        merged.extend(flags?);
+    }
+    merged.sort_unstable();
+    merged.dedup();
+    Some(merged)
+}
+
 /// A weak reference to an [`Evaluator`].
 #[derive(Clone)]
 pub struct WeakEvaluatorRef {
@@ -210,6 +495,17 @@ impl WeakEvaluatorRef {
     pub fn upgrade(&self) -> Option<EvaluatorRef> {
         self.weak.upgrade().map(|arc| EvaluatorRef { arc })
     }
+
+    /// Check if the evaluator has not been dropped yet.
+    #[cfg(feature = "std")]
+    pub(crate) fn is_alive(&self) -> bool {
+        self.weak.strong_count() > 0
+    }
+
+    /// Check if this is a reference to `evaluator`.
+    pub(crate) fn refers_to(&self, evaluator: &EvaluatorRef) -> bool {
+        core::ptr::addr_eq(self.weak.as_ptr(), Arc::as_ptr(&evaluator.arc))
+    }
 }
 
 impl Default for WeakEvaluatorRef {
@@ -235,6 +531,27 @@ pub trait EvaluatorExt: Evaluator {
         }
     }
 
+    /// Filter contexts based on a filter function.
+    ///
+    /// This method will only notify the evaluator about new contexts if the
+    /// filter function returns `true` for their fields, and only notify it
+    /// about closed contexts it was notified about. This avoids storing
+    /// extensions in contexts that can't affect the features evaluated by
+    /// the evaluator, for example for an evaluator of a small namespace of
+    /// features that only depend on a few fields.
+    ///
+    /// Features are still evaluated by the evaluator in all contexts.
+    fn filter_contexts<F>(self, filter_fn: F) -> FilterContext<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Fields<'_>) -> bool + Send + Sync + 'static,
+    {
+        FilterContext {
+            evaluator: self,
+            filter_fn,
+        }
+    }
+
     /// Chain two evaluators together.
     ///
     /// This method will call the first evaluator, and if it returns `None`, it will
@@ -246,6 +563,106 @@ pub trait EvaluatorExt: Evaluator {
     {
         Chain(self, other)
     }
+
+    /// Supply last-resort values for features.
+    ///
+    /// If the evaluator returns `None` for one of the given features, the given
+    /// value is returned instead. This allows shipping safe defaults separately
+    /// from the default values at each call site.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    fn fallback<I, K>(self, values: I) -> Fallback<Self>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = (K, bool)>,
+        K: Into<String>,
+    {
+        Fallback {
+            evaluator: self,
+            values: values.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
+
+    /// Rewrite feature names before evaluating them.
+    ///
+    /// The mapper can be a `HashMap<String, String>` from old to new names, or
+    /// a closure returning the new name. Use [`MapNames::on_alias`] to be
+    /// notified when an old name is used.
+    fn map_names<M>(self, mapper: M) -> MapNames<Self, M>
+    where
+        Self: Sized,
+        M: NameMapper,
+    {
+        MapNames {
+            evaluator: self,
+            mapper,
+            on_alias: None,
+        }
+    }
+
+    /// Transform the results of the evaluator.
+    ///
+    /// The function is called with the feature name and the result of the
+    /// evaluator, and returns the new result. This can be used to e.g. invert a
+    /// legacy feature, or force features off during a maintenance window.
+    fn map_result<F>(self, map_fn: F) -> MapResult<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&str, Option<bool>) -> Option<bool> + Send + Sync + 'static,
+    {
+        MapResult {
+            evaluator: self,
+            map_fn,
+        }
+    }
+
+    /// Compare the evaluator against a candidate evaluator.
+    ///
+    /// Both evaluators are called for every feature, but only the result of
+    /// this evaluator is returned. If the results differ, `on_mismatch` is
+    /// called with the feature name, this evaluator's result and the
+    /// candidate's result. This is useful when migrating between backends.
+    fn shadow<C, F>(self, candidate: C, on_mismatch: F) -> Shadow<Self, C, F>
+    where
+        Self: Sized,
+        C: Evaluator,
+        F: Fn(&str, Option<bool>, Option<bool>) + Send + Sync + 'static,
+    {
+        Shadow {
+            primary: self,
+            candidate,
+            on_mismatch,
+        }
+    }
+
+    /// Attach an [`EvaluationHook`] to the evaluator.
+    ///
+    /// The hook is called before and after every feature evaluated by this
+    /// evaluator.
+    fn with_hook<H>(self, hook: H) -> Hooked<Self, H>
+    where
+        Self: Sized,
+        H: EvaluationHook,
+    {
+        Hooked {
+            evaluator: self,
+            hook,
+        }
+    }
+
+    /// Memoize the results of the evaluator per context.
+    ///
+    /// The first result of each feature in a context is stored in the context,
+    /// and returned for later evaluations of the same feature in that context.
+    /// Evaluations in the root context are not memoized.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    fn memoize(self) -> Memoize<Self>
+    where
+        Self: Sized,
+    {
+        Memoize::new(self)
+    }
 }
 
 impl<E: ?Sized + Evaluator> EvaluatorExt for E {}
@@ -269,8 +686,36 @@ where
         }
     }
 
-    fn on_registration(&self) {
-        self.evaluator.on_registration()
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        if (self.filter_fn)(feature) {
+            self.evaluator.is_enabled_traced(feature, context, trace)
+        } else {
+            trace.record(type_name::<Self>(), None);
+            None
+        }
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("Filter").with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        let mut flags = self.evaluator.known_flags()?;
+        flags.retain(|feature| (self.filter_fn)(feature));
+        Some(flags)
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
     }
 
     fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
@@ -280,6 +725,82 @@ where
     fn on_close_context(&self, context: ContextRef<'_>) {
         self.evaluator.on_close_context(context)
     }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
+
+/// Context filter evaluator, see [`EvaluatorExt::filter_contexts`].
+pub struct FilterContext<E, F> {
+    filter_fn: F,
+    evaluator: E,
+}
+
+/// Extension marking a context the evaluator of a [`FilterContext`] was
+/// notified about.
+///
+/// The identifier is stored because extensions are inherited by child
+/// contexts.
+struct ContextFiltered<F>(ContextId, PhantomData<fn() -> F>);
+
+impl<E, F> Evaluator for FilterContext<E, F>
+where
+    E: Evaluator,
+    F: Fn(&Fields<'_>) -> bool + Send + Sync + 'static,
+{
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.evaluator.is_enabled(feature, context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.evaluator.is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("FilterContext").with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        if (self.filter_fn)(&fields) {
+            let id = context.id();
+            context
+                .extensions_mut()
+                .insert(ContextFiltered::<F>(id, PhantomData));
+            self.evaluator.on_new_context(context, fields)
+        }
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        if context
+            .extensions()
+            .get::<ContextFiltered<F>>()
+            .is_some_and(|filtered| filtered.0 == context.id())
+        {
+            self.evaluator.on_close_context(context)
+        }
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
 }
 
 /// Chain evaluator, see [`EvaluatorExt::chain`].
@@ -292,9 +813,34 @@ impl<T: Evaluator, U: Evaluator> Evaluator for Chain<T, U> {
             .or_else(|| self.1.is_enabled(feature, context))
     }
 
-    fn on_registration(&self) {
-        self.0.on_registration();
-        self.1.on_registration();
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.0
+            .is_enabled_traced(feature, context, trace)
+            .or_else(|| self.1.is_enabled_traced(feature, context, trace))
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("Chain")
+            .with_child(self.0.describe())
+            .with_child(self.1.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        merge_known_flags([self.0.known_flags(), self.1.known_flags()])
+    }
+
+    fn health(&self) -> Health {
+        Health::combine([self.0.health(), self.1.health()])
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.0.on_registration(info);
+        self.1.on_registration(info);
     }
 
     fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
@@ -307,6 +853,11 @@ impl<T: Evaluator, U: Evaluator> Evaluator for Chain<T, U> {
         self.1.on_close_context(context);
     }
 
+    fn on_shutdown(&self) {
+        self.0.on_shutdown();
+        self.1.on_shutdown();
+    }
+
     fn into_ref(self) -> EvaluatorRef
     where
         Self: Sized + 'static,
@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    feature::shared_name,
+    fields::Fields,
+    value::Value,
+};
+
+/// A record of a single feature flag evaluation, see [`AuditEvaluator`].
+#[derive(Clone, Debug)]
+pub struct AuditRecord {
+    feature: Arc<str>,
+    result: Option<bool>,
+    fields: Vec<(String, Value<'static>)>,
+    timestamp: SystemTime,
+}
+
+impl AuditRecord {
+    /// Get the name of the evaluated feature.
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    /// Get the result of the evaluation.
+    ///
+    /// `None` means that the feature's default value was used.
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    /// Get the selected context fields of the evaluation.
+    ///
+    /// Only fields selected with [`AuditEvaluator::with_fields`] and present in
+    /// the context (or its parents) are included.
+    pub fn fields(&self) -> &[(String, Value<'static>)] {
+        &self.fields
+    }
+
+    /// Get a selected context field by its key.
+    pub fn field(&self, key: &str) -> Option<&Value<'static>> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Get the time of the evaluation.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+/// Sink for [`AuditRecord`]s, see [`AuditEvaluator`].
+///
+/// This trait is automatically implemented for `Fn(AuditRecord)` closures.
+pub trait AuditSink: Send + Sync {
+    /// Record an evaluation.
+    fn record(&self, record: AuditRecord);
+}
+
+impl<F: Fn(AuditRecord) + Send + Sync> AuditSink for F {
+    fn record(&self, record: AuditRecord) {
+        self(record)
+    }
+}
+
+/// Evaluator that records evaluations to an [`AuditSink`].
+///
+/// By default every evaluation is recorded. Use [`sample_every`](Self::sample_every)
+/// and [`max_per_second`](Self::max_per_second) to limit the number of records.
+pub struct AuditEvaluator<E, S> {
+    id: u64,
+    evaluator: E,
+    sink: S,
+    fields: Vec<String>,
+    sample_every: u64,
+    max_per_second: Option<u64>,
+    counter: AtomicU64,
+    window: Mutex<(Instant, u64)>,
+}
+
+/// Extension storing the selected fields of each [`AuditEvaluator`] in a context.
+struct AuditFields(HashMap<u64, Vec<(String, Value<'static>)>>);
+
+impl<E, S> AuditEvaluator<E, S> {
+    /// Create a new audit evaluator, recording evaluations of `evaluator` to `sink`.
+    pub fn new(evaluator: E, sink: S) -> AuditEvaluator<E, S> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        AuditEvaluator {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            evaluator,
+            sink,
+            fields: Vec::new(),
+            sample_every: 1,
+            max_per_second: None,
+            counter: AtomicU64::new(0),
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Select context fields to include in the records.
+    pub fn with_fields<I>(mut self, fields: I) -> AuditEvaluator<E, S>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.fields.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only record every `n`th evaluation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn sample_every(mut self, n: u64) -> AuditEvaluator<E, S> {
+        assert!(n > 0, "sample rate must be non-zero");
+        self.sample_every = n;
+        self
+    }
+
+    /// Record at most `n` evaluations per second.
+    ///
+    /// Evaluations over the limit are dropped.
+    pub fn max_per_second(mut self, n: u64) -> AuditEvaluator<E, S> {
+        self.max_per_second = Some(n);
+        self
+    }
+
+    fn should_record(&self) -> bool {
+        if self.counter.fetch_add(1, Ordering::Relaxed) % self.sample_every != 0 {
+            return false;
+        }
+
+        let Some(max_per_second) = self.max_per_second else {
+            return true;
+        };
+
+        let mut window = self.window.lock().unwrap();
+        let now = Instant::now();
+        if now.duration_since(window.0) >= Duration::from_secs(1) {
+            *window = (now, 0);
+        }
+
+        if window.1 < max_per_second {
+            window.1 += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn record_evaluation(&self, feature: &str, context: &Context, result: Option<bool>)
+    where
+        S: AuditSink,
+    {
+        if self.should_record() {
+            self.sink.record(AuditRecord {
+                feature: shared_name(feature),
+                result,
+                fields: self.selected_fields(context),
+                timestamp: SystemTime::now(),
+            });
+        }
+    }
+
+    fn selected_fields(&self, context: &Context) -> Vec<(String, Value<'static>)> {
+        self.fields
+            .iter()
+            .filter_map(|key| {
+                context
+                    .iter()
+                    .filter_map(|context| context.extensions().get::<AuditFields>())
+                    .filter_map(|fields| fields.0.get(&self.id))
+                    .find_map(|fields| fields.iter().find(|(k, _)| k == key))
+                    .cloned()
+            })
+            .collect()
+    }
+}
+
+impl<E: Evaluator, S: AuditSink> Evaluator for AuditEvaluator<E, S> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        let result = self.evaluator.is_enabled(feature, context);
+        self.record_evaluation(feature, context, result);
+        result
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let result = self.evaluator.is_enabled_traced(feature, context, trace);
+        self.record_evaluation(feature, context, result);
+        result
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        let mut summary = format!("sample every {}", self.sample_every);
+        if let Some(max_per_second) = self.max_per_second {
+            summary += &format!(", max {max_per_second}/s");
+        }
+        if !self.fields.is_empty() {
+            summary += &format!(", fields {:?}", self.fields);
+        }
+
+        EvaluatorDescription::new("AuditEvaluator")
+            .with_summary(summary)
+            .with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        let selected = fields
+            .pairs()
+            .filter(|(key, _)| self.fields.iter().any(|k| k == key))
+            .map(|(key, value)| (key.to_string(), value.to_static()))
+            .collect::<Vec<_>>();
+        if !selected.is_empty() {
+            let extensions = context.extensions_mut();
+            match extensions.get_mut::<AuditFields>() {
+                Some(fields) => {
+                    fields.0.insert(self.id, selected);
+                }
+                None => {
+                    extensions.insert(AuditFields(HashMap::from([(self.id, selected)])));
+                }
+            }
+        }
+
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
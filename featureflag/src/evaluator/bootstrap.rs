@@ -0,0 +1,246 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    context::Context,
+    evaluator::{Evaluator, EvaluatorDescription},
+};
+
+/// Evaluator with a fixed set of feature values, typically embedded at compile time.
+///
+/// The values are parsed from a JSON object mapping feature names to `true`,
+/// `false` or `null`, where `null` means the feature's default value is used.
+/// Use the [`bootstrap!`](macro@crate::bootstrap) macro to embed a snapshot
+/// file into the binary.
+///
+/// # Environments
+///
+/// A feature can also be mapped to an object with a `default` value and
+/// overrides for different environments, so that one file can describe all
+/// deployment targets:
+///
+/// ```json
+/// {
+///     "new_ui": { "default": false, "staging": true },
+///     "beta": { "default": true, "production": null }
+/// }
+/// ```
+///
+/// The environment is selected when parsing the file with
+/// [`from_json_for_env`](Self::from_json_for_env). Features without an
+/// override for the environment use their `default` value, and features with
+/// neither are not set.
+///
+/// This is intended to be used as the last evaluator in a
+/// [`chain`](crate::evaluator::EvaluatorExt::chain), so that features have
+/// deterministic values when no other evaluator has a value for them.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::BootstrapEvaluator;
+///
+/// let evaluator =
+///     BootstrapEvaluator::from_json(r#"{ "new_ui": true, "beta": false }"#).unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BootstrapEvaluator {
+    values: HashMap<String, bool>,
+}
+
+impl BootstrapEvaluator {
+    /// Create a bootstrap evaluator from the given feature values.
+    pub fn new<I, K>(values: I) -> BootstrapEvaluator
+    where
+        I: IntoIterator<Item = (K, bool)>,
+        K: Into<String>,
+    {
+        BootstrapEvaluator {
+            values: values.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
+
+    /// Parse a bootstrap evaluator from a JSON object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not an object of `true`, `false` or `null`
+    /// values, or of environment objects of such values.
+    pub fn from_json(json: &str) -> Result<BootstrapEvaluator, BootstrapError> {
+        parse(json, None).map(|values| BootstrapEvaluator { values })
+    }
+
+    /// Parse a bootstrap evaluator from a JSON object, with the overrides of
+    /// the environment `env`.
+    ///
+    /// See [Environments](Self#environments) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid bootstrap file, see
+    /// [`from_json`](Self::from_json).
+    pub fn from_json_for_env(json: &str, env: &str) -> Result<BootstrapEvaluator, BootstrapError> {
+        parse(json, Some(env)).map(|values| BootstrapEvaluator { values })
+    }
+
+    /// Get the value of a feature, if it is set.
+    pub fn get(&self, feature: &str) -> Option<bool> {
+        self.values.get(feature).copied()
+    }
+
+    /// Iterate over the features that are set, in arbitrary order.
+    pub fn features(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+impl Evaluator for BootstrapEvaluator {
+    fn is_enabled(&self, feature: &str, _context: &Context) -> Option<bool> {
+        self.get(feature)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("BootstrapEvaluator")
+            .with_summary(format!("{} features", self.values.len()))
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        Some(self.values.keys().cloned().collect())
+    }
+}
+
+/// Error parsing a [`BootstrapEvaluator`] from JSON.
+#[derive(Debug)]
+pub struct BootstrapError {
+    kind: BootstrapErrorKind,
+}
+
+#[derive(Debug)]
+enum BootstrapErrorKind {
+    Json(serde_json::Error),
+    Value(String),
+}
+
+impl BootstrapError {
+    fn value(feature: &str, env: Option<&str>) -> BootstrapError {
+        let message = match env {
+            Some(env) => format!(
+                "expected `true`, `false` or `null` for feature {feature:?} in environment {env:?}"
+            ),
+            None => {
+                format!("expected `true`, `false`, `null` or an object for feature {feature:?}")
+            }
+        };
+        BootstrapError {
+            kind: BootstrapErrorKind::Value(message),
+        }
+    }
+}
+
+impl fmt::Display for BootstrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            BootstrapErrorKind::Json(err) => err.fmt(f),
+            BootstrapErrorKind::Value(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for BootstrapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            BootstrapErrorKind::Json(err) => Some(err),
+            BootstrapErrorKind::Value(_) => None,
+        }
+    }
+}
+
+/// Parse a JSON object of booleans or environment objects.
+fn parse(json: &str, env: Option<&str>) -> Result<HashMap<String, bool>, BootstrapError> {
+    let features =
+        serde_json::from_str::<Map<String, Value>>(json).map_err(|err| BootstrapError {
+            kind: BootstrapErrorKind::Json(err),
+        })?;
+
+    let mut values = HashMap::new();
+    for (name, value) in features {
+        let value = match value {
+            Value::Object(environments) => select_environment(&name, &environments, env)?,
+            Value::Bool(value) => Some(value),
+            Value::Null => None,
+            _ => return Err(BootstrapError::value(&name, None)),
+        };
+        if let Some(value) = value {
+            values.insert(name, value);
+        }
+    }
+    Ok(values)
+}
+
+/// Select the value of the environment `env` from an environment object.
+fn select_environment(
+    name: &str,
+    environments: &Map<String, Value>,
+    env: Option<&str>,
+) -> Result<Option<bool>, BootstrapError> {
+    let mut default = None;
+    let mut selected = None;
+    for (key, value) in environments {
+        let value = match value {
+            Value::Bool(value) => Some(*value),
+            Value::Null => None,
+            _ => return Err(BootstrapError::value(name, Some(key))),
+        };
+        if key == "default" {
+            default = value;
+        } else if env == Some(key.as_str()) {
+            selected = Some(value);
+        }
+    }
+    Ok(selected.unwrap_or(default))
+}
+
+/// Embed a JSON feature snapshot into the binary as a [`BootstrapEvaluator`].
+///
+/// The path is resolved like [`include_str!`], relative to the current file.
+/// The file is embedded at compile time and parsed when the macro is evaluated,
+/// so an environment can be selected at runtime with `env = ...`, see
+/// [`BootstrapEvaluator::from_json_for_env`].
+///
+/// # Panics
+///
+/// Panics if the file is not valid, see [`BootstrapEvaluator::from_json`].
+///
+/// # Examples
+///
+/// ```ignore
+/// use featureflag::evaluator::EvaluatorExt;
+///
+/// let evaluator = remote_evaluator.chain(featureflag::bootstrap!("flags.json"));
+///
+/// let env = std::env::var("DEPLOY_ENV").unwrap_or_default();
+/// let evaluator = remote_evaluator.chain(featureflag::bootstrap!("flags.json", env = &env));
+/// ```
+#[macro_export]
+macro_rules! bootstrap {
+    ($path:literal $(,)?) => {
+        match $crate::evaluator::BootstrapEvaluator::from_json(::core::include_str!($path)) {
+            ::core::result::Result::Ok(evaluator) => evaluator,
+            ::core::result::Result::Err(err) => {
+                ::core::panic!("invalid bootstrap file {}: {}", $path, err)
+            }
+        }
+    };
+    ($path:literal, env = $env:expr $(,)?) => {
+        match $crate::evaluator::BootstrapEvaluator::from_json_for_env(
+            ::core::include_str!($path),
+            $env,
+        ) {
+            ::core::result::Result::Ok(evaluator) => evaluator,
+            ::core::result::Result::Err(err) => {
+                ::core::panic!("invalid bootstrap file {}: {}", $path, err)
+            }
+        }
+    };
+}
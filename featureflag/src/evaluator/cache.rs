@@ -0,0 +1,185 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+    sync::Mutex,
+};
+
+use crate::context::{Context, ContextId};
+
+/// Bounded cache of values per context, for memoizing work in evaluators.
+///
+/// Entries are keyed by the [`ContextId`] of the context they were stored in,
+/// so the cache doesn't keep contexts alive. Evaluators should call
+/// [`remove_context`](Self::remove_context) from
+/// [`Evaluator::on_close_context`](crate::Evaluator::on_close_context) to free
+/// the entries of closed contexts.
+///
+/// The cache holds at most `capacity` entries in total. When it is full, the
+/// entries of the oldest contexts are evicted first. Nothing is cached in the
+/// root context, since it is never closed.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::{
+///     Context, Evaluator,
+///     context::ContextRef,
+///     evaluator::ContextCache,
+/// };
+///
+/// struct Remote {
+///     cache: ContextCache<String, Option<bool>>,
+/// }
+///
+/// impl Evaluator for Remote {
+///     fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+///         self.cache
+///             .get_or_insert_with(context, feature.to_string(), || {
+///                 // expensive lookup
+///                 Some(true)
+///             })
+///     }
+///
+///     fn on_close_context(&self, context: ContextRef<'_>) {
+///         self.cache.remove_context(context.id());
+///     }
+/// }
+/// ```
+pub struct ContextCache<K, V> {
+    capacity: usize,
+    inner: Mutex<Inner<K, V>>,
+}
+
+struct Inner<K, V> {
+    contexts: BTreeMap<ContextId, HashMap<K, V>>,
+    len: usize,
+}
+
+impl<K, V> ContextCache<K, V> {
+    /// Create a new cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> ContextCache<K, V> {
+        ContextCache {
+            capacity,
+            inner: Mutex::new(Inner {
+                contexts: BTreeMap::new(),
+                len: 0,
+            }),
+        }
+    }
+
+    /// Get the maximum number of entries in the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().len
+    }
+
+    /// Check if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all entries of a context.
+    pub fn remove_context(&self, id: ContextId) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(entries) = inner.contexts.remove(&id) {
+            inner.len -= entries.len();
+        }
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.contexts.clear();
+        inner.len = 0;
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> ContextCache<K, V> {
+    /// Get a cached value in a context.
+    pub fn get<Q>(&self, context: &Context, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let inner = self.inner.lock().unwrap();
+        inner.contexts.get(&context.id())?.get(key).cloned()
+    }
+
+    /// Store a value in a context.
+    ///
+    /// Values stored in the root context are ignored.
+    pub fn insert(&self, context: &Context, key: K, value: V) {
+        if context.is_root() || self.capacity == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let id = context.id();
+        let exists = inner
+            .contexts
+            .get(&id)
+            .is_some_and(|entries| entries.contains_key(&key));
+        if !exists {
+            inner.evict(self.capacity - 1, id);
+            inner.len += 1;
+        }
+        inner.contexts.entry(id).or_default().insert(key, value);
+    }
+
+    /// Get a cached value in a context, or compute and store it with `f`.
+    ///
+    /// The cache isn't locked while `f` runs, so concurrent calls may compute
+    /// the same value more than once.
+    pub fn get_or_insert_with<F>(&self, context: &Context, key: K, f: F) -> V
+    where
+        F: FnOnce() -> V,
+    {
+        if let Some(value) = self.get(context, &key) {
+            return value;
+        }
+
+        let value = f();
+        self.insert(context, key, value.clone());
+        value
+    }
+}
+
+impl<K, V> Inner<K, V> {
+    /// Evict entries until at most `max_len` remain, evicting the entries of
+    /// `keep` only if no other context has any.
+    fn evict(&mut self, max_len: usize, keep: ContextId) {
+        while self.len > max_len {
+            let oldest = self.contexts.keys().copied().find(|&id| id != keep);
+            let Some(oldest) = oldest else {
+                let Some(entries) = self.contexts.get_mut(&keep) else {
+                    break;
+                };
+                let excess = self.len - max_len;
+                let mut seen = 0;
+                entries.retain(|_, _| {
+                    seen += 1;
+                    seen > excess
+                });
+                self.len -= excess;
+                break;
+            };
+            let entries = self.contexts.remove(&oldest).unwrap();
+            self.len -= entries.len();
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for ContextCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
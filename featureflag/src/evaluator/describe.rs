@@ -0,0 +1,71 @@
+use alloc::borrow::Cow;
+use core::fmt;
+
+use crate::prelude::*;
+
+/// Description of an evaluator and the evaluators it is composed of.
+///
+/// Descriptions are returned by [`Evaluator::describe`](crate::evaluator::Evaluator::describe),
+/// and can be printed as a tree using the [`Display`](fmt::Display) implementation.
+#[derive(Clone, Debug)]
+pub struct EvaluatorDescription {
+    name: Cow<'static, str>,
+    summary: Option<String>,
+    children: Vec<EvaluatorDescription>,
+}
+
+impl EvaluatorDescription {
+    /// Create a new description with the given name.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> EvaluatorDescription {
+        EvaluatorDescription {
+            name: name.into(),
+            summary: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Set a short summary of the evaluator's configuration.
+    pub fn with_summary(mut self, summary: impl Into<String>) -> EvaluatorDescription {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    /// Add the description of an inner evaluator.
+    pub fn with_child(mut self, child: EvaluatorDescription) -> EvaluatorDescription {
+        self.children.push(child);
+        self
+    }
+
+    /// Get the name of the evaluator.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the summary of the evaluator's configuration, if any.
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    /// Get the descriptions of the inner evaluators.
+    pub fn children(&self) -> &[EvaluatorDescription] {
+        &self.children
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.name, indent = depth * 2)?;
+        if let Some(summary) = &self.summary {
+            write!(f, " ({summary})")?;
+        }
+        for child in &self.children {
+            writeln!(f)?;
+            child.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for EvaluatorDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    fields::Fields,
+    prelude::*,
+};
+
+/// Evaluator that is one of two evaluators.
+///
+/// This can be used to choose between two evaluator types at runtime, e.g.
+/// based on configuration, without boxing them into an [`EvaluatorRef`](crate::evaluator::EvaluatorRef).
+#[derive(Clone, Debug)]
+pub enum Either<L, R> {
+    /// The left evaluator.
+    Left(L),
+    /// The right evaluator.
+    Right(R),
+}
+
+impl<L: Evaluator, R: Evaluator> Evaluator for Either<L, R> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        match self {
+            Either::Left(evaluator) => evaluator.is_enabled(feature, context),
+            Either::Right(evaluator) => evaluator.is_enabled(feature, context),
+        }
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        match self {
+            Either::Left(evaluator) => evaluator.is_enabled_traced(feature, context, trace),
+            Either::Right(evaluator) => evaluator.is_enabled_traced(feature, context, trace),
+        }
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        match self {
+            Either::Left(evaluator) => evaluator.describe(),
+            Either::Right(evaluator) => evaluator.describe(),
+        }
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        match self {
+            Either::Left(evaluator) => evaluator.known_flags(),
+            Either::Right(evaluator) => evaluator.known_flags(),
+        }
+    }
+
+    fn health(&self) -> Health {
+        match self {
+            Either::Left(evaluator) => evaluator.health(),
+            Either::Right(evaluator) => evaluator.health(),
+        }
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        match self {
+            Either::Left(evaluator) => evaluator.on_registration(info),
+            Either::Right(evaluator) => evaluator.on_registration(info),
+        }
+    }
+
+    fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
+        match self {
+            Either::Left(evaluator) => evaluator.on_new_context(context, fields),
+            Either::Right(evaluator) => evaluator.on_new_context(context, fields),
+        }
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        match self {
+            Either::Left(evaluator) => evaluator.on_close_context(context),
+            Either::Right(evaluator) => evaluator.on_close_context(context),
+        }
+    }
+
+    fn on_shutdown(&self) {
+        match self {
+            Either::Left(evaluator) => evaluator.on_shutdown(),
+            Either::Right(evaluator) => evaluator.on_shutdown(),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::{
+        Arc, Condvar, Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    context::{Context, ContextId, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    feature::shared_name,
+    fields::Fields,
+};
+
+/// An exposure of a feature flag in a context, see [`ExposureEvaluator`].
+#[derive(Clone, Debug)]
+pub struct ExposureEvent {
+    feature: Arc<str>,
+    context_id: ContextId,
+    result: Option<bool>,
+    timestamp: SystemTime,
+}
+
+impl ExposureEvent {
+    /// Get the name of the exposed feature.
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    /// Get the identifier of the context the feature was exposed in.
+    pub fn context_id(&self) -> ContextId {
+        self.context_id
+    }
+
+    /// Get the result of the first evaluation of the feature in the context.
+    ///
+    /// `None` means that the feature's default value was used.
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    /// Get the time of the first evaluation of the feature in the context.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+/// Sink for batches of [`ExposureEvent`]s, see [`ExposureEvaluator`].
+///
+/// This trait is automatically implemented for `Fn(Vec<ExposureEvent>)` closures.
+pub trait ExposureSink: Send + Sync + 'static {
+    /// Send a batch of exposure events.
+    fn send(&self, events: Vec<ExposureEvent>);
+}
+
+impl<F: Fn(Vec<ExposureEvent>) + Send + Sync + 'static> ExposureSink for F {
+    fn send(&self, events: Vec<ExposureEvent>) {
+        self(events)
+    }
+}
+
+/// Evaluator that emits one [`ExposureEvent`] per context and feature to an
+/// [`ExposureSink`].
+///
+/// Events are buffered and sent in batches from a background thread, either
+/// when [`batch_size`](Self::batch_size) events are buffered, or every
+/// [`flush_interval`](Self::flush_interval). Any remaining events are sent when
+/// the evaluator is dropped, or when [`flush`](Self::flush) is called.
+pub struct ExposureEvaluator<E, S: ExposureSink> {
+    id: u64,
+    evaluator: E,
+    shared: Arc<Shared<S>>,
+    thread: OnceLock<JoinHandle<()>>,
+}
+
+struct Shared<S> {
+    sink: S,
+    batch_size: usize,
+    flush_interval: Duration,
+    state: Mutex<State>,
+    condvar: Condvar,
+    untracked_seen: Mutex<UntrackedSeen>,
+}
+
+/// Maximum number of exposures remembered for contexts without a [`Seen`]
+/// extension, before they are forgotten.
+const MAX_UNTRACKED_SEEN: usize = 10_000;
+
+/// Features already exposed in contexts without a [`Seen`] extension, like the
+/// root context and contexts created before the evaluator was registered.
+#[derive(Default)]
+struct UntrackedSeen {
+    contexts: HashMap<ContextId, HashSet<Arc<str>>>,
+    len: usize,
+}
+
+#[derive(Default)]
+struct State {
+    buffer: Vec<ExposureEvent>,
+    stopped: bool,
+}
+
+/// Extension storing the features already exposed by each
+/// [`ExposureEvaluator`] in a context.
+struct Seen(HashMap<u64, Mutex<HashSet<Arc<str>>>>);
+
+impl<E, S: ExposureSink> ExposureEvaluator<E, S> {
+    /// Create a new exposure evaluator, sending exposures of `evaluator` to `sink`.
+    ///
+    /// By default, events are sent in batches of 100, or every 10 seconds.
+    pub fn new(evaluator: E, sink: S) -> ExposureEvaluator<E, S> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        ExposureEvaluator {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            evaluator,
+            shared: Arc::new(Shared {
+                sink,
+                batch_size: 100,
+                flush_interval: Duration::from_secs(10),
+                state: Mutex::new(State::default()),
+                condvar: Condvar::new(),
+                untracked_seen: Mutex::default(),
+            }),
+            thread: OnceLock::new(),
+        }
+    }
+
+    /// Set the number of events that triggers sending a batch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after the first event has been emitted.
+    pub fn batch_size(mut self, batch_size: usize) -> ExposureEvaluator<E, S> {
+        self.shared_mut().batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Set the longest time events are buffered before being sent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after the first event has been emitted.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> ExposureEvaluator<E, S> {
+        self.shared_mut().flush_interval = flush_interval;
+        self
+    }
+
+    /// Send all buffered events to the sink.
+    pub fn flush(&self) {
+        self.shared.flush();
+    }
+
+    fn shared_mut(&mut self) -> &mut Shared<S> {
+        Arc::get_mut(&mut self.shared).expect("exposure evaluator already started")
+    }
+
+    fn expose(&self, feature: &str, context: &Context, result: Option<bool>) {
+        let feature = match context
+            .extensions()
+            .get::<Seen>()
+            .and_then(|seen| seen.0.get(&self.id))
+        {
+            Some(seen) => {
+                let mut seen = seen.lock().unwrap();
+                if seen.contains(feature) {
+                    return;
+                }
+                let feature = shared_name(feature);
+                seen.insert(feature.clone());
+                feature
+            }
+            None => {
+                let mut untracked = self.shared.untracked_seen.lock().unwrap();
+                if untracked
+                    .contexts
+                    .get(&context.id())
+                    .is_some_and(|seen| seen.contains(feature))
+                {
+                    return;
+                }
+                if untracked.len >= MAX_UNTRACKED_SEEN {
+                    // forgetting exposures only causes duplicate events
+                    *untracked = UntrackedSeen::default();
+                }
+                let feature = shared_name(feature);
+                untracked.len += 1;
+                untracked
+                    .contexts
+                    .entry(context.id())
+                    .or_default()
+                    .insert(feature.clone());
+                feature
+            }
+        };
+
+        self.thread.get_or_init(|| {
+            let shared = self.shared.clone();
+            thread::spawn(move || shared.run())
+        });
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.buffer.push(ExposureEvent {
+            feature,
+            context_id: context.id(),
+            result,
+            timestamp: SystemTime::now(),
+        });
+        if state.buffer.len() >= self.shared.batch_size {
+            self.shared.condvar.notify_all();
+        }
+    }
+}
+
+impl<S: ExposureSink> Shared<S> {
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.stopped {
+            state = self
+                .condvar
+                .wait_timeout_while(state, self.flush_interval, |state| {
+                    !state.stopped && state.buffer.len() < self.batch_size
+                })
+                .unwrap()
+                .0;
+
+            let events = mem::take(&mut state.buffer);
+            if !events.is_empty() {
+                drop(state);
+                self.sink.send(events);
+                state = self.state.lock().unwrap();
+            }
+        }
+    }
+
+    fn flush(&self) {
+        let events = mem::take(&mut self.state.lock().unwrap().buffer);
+        if !events.is_empty() {
+            self.sink.send(events);
+        }
+    }
+}
+
+impl<E, S: ExposureSink> Drop for ExposureEvaluator<E, S> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.condvar.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        self.shared.flush();
+    }
+}
+
+impl<E: Evaluator, S: ExposureSink> Evaluator for ExposureEvaluator<E, S> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        let result = self.evaluator.is_enabled(feature, context);
+        self.expose(feature, context, result);
+        result
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let result = self.evaluator.is_enabled_traced(feature, context, trace);
+        self.expose(feature, context, result);
+        result
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("ExposureEvaluator")
+            .with_summary(format!(
+                "batch size {}, flush interval {:?}",
+                self.shared.batch_size, self.shared.flush_interval
+            ))
+            .with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        let extensions = context.extensions_mut();
+        match extensions.get_mut::<Seen>() {
+            Some(seen) => {
+                seen.0.insert(self.id, Mutex::default());
+            }
+            None => {
+                extensions.insert(Seen(HashMap::from([(self.id, Mutex::default())])));
+            }
+        }
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.flush();
+        self.evaluator.on_shutdown()
+    }
+}
@@ -0,0 +1,72 @@
+use std::{any::type_name, collections::HashMap};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{
+        EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo,
+        merge_known_flags,
+    },
+    fields::Fields,
+};
+
+/// Fallback evaluator, see [`EvaluatorExt::fallback`](crate::evaluator::EvaluatorExt::fallback).
+pub struct Fallback<E> {
+    pub(super) evaluator: E,
+    pub(super) values: HashMap<String, bool>,
+}
+
+impl<E: Evaluator> Evaluator for Fallback<E> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.evaluator
+            .is_enabled(feature, context)
+            .or_else(|| self.values.get(feature).copied())
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.evaluator
+            .is_enabled_traced(feature, context, trace)
+            .or_else(|| {
+                let result = self.values.get(feature).copied();
+                trace.record(type_name::<Self>(), result);
+                result
+            })
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("Fallback")
+            .with_summary(format!("{} features", self.values.len()))
+            .with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        merge_known_flags([
+            self.evaluator.known_flags(),
+            Some(self.values.keys().cloned().collect()),
+        ])
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
@@ -1,21 +1,64 @@
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use core::sync::atomic::AtomicPtr;
+use core::{fmt, sync::atomic::Ordering};
+#[cfg(feature = "std")]
 use std::{
     borrow::Cow,
-    cell::{OnceCell, RefCell},
-    fmt,
+    cell::RefCell,
+    collections::HashMap,
+    marker::PhantomData,
     panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
-    sync::OnceLock,
+    sync::{Mutex, OnceLock, PoisonError, atomic::AtomicUsize},
+    thread::LocalKey,
 };
 
+#[cfg(feature = "std")]
+use crate::evaluator::WeakEvaluatorRef;
 use crate::evaluator::{Evaluator, EvaluatorRef};
 
+#[cfg(feature = "std")]
 static GLOBAL_EVALUATOR: OnceLock<EvaluatorRef> = OnceLock::new();
+#[cfg(not(feature = "std"))]
+static GLOBAL_EVALUATOR: GlobalSlot = GlobalSlot::new();
 
+#[cfg(feature = "std")]
 thread_local! {
-    static THREAD_EVALUATOR: OnceCell<EvaluatorRef> = const { OnceCell::new() };
+    static THREAD_EVALUATOR: RefCell<Option<EvaluatorRef>> = const { RefCell::new(None) };
 
     static TASK_EVALUATOR: RefCell<Option<EvaluatorRef>> = const { RefCell::new(None) };
 }
 
+/// Number of thread and task evaluators currently set, on all threads.
+///
+/// While this is zero, [`get_default`] uses the global evaluator without
+/// accessing the thread-locals. Evaluators that are still set when a thread
+/// exits are never uncounted, which only disables this fast path.
+#[cfg(feature = "std")]
+static LOCAL_EVALUATORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Replace the evaluator in a thread-local, keeping [`LOCAL_EVALUATORS`]
+/// up to date.
+#[cfg(feature = "std")]
+fn replace_local(
+    key: &'static LocalKey<RefCell<Option<EvaluatorRef>>>,
+    evaluator: Option<EvaluatorRef>,
+) -> Option<EvaluatorRef> {
+    let is_set = evaluator.is_some();
+    let previous = key.replace(evaluator);
+    match (previous.is_some(), is_set) {
+        (false, true) => {
+            LOCAL_EVALUATORS.fetch_add(1, Ordering::Relaxed);
+        }
+        (true, false) => {
+            LOCAL_EVALUATORS.fetch_sub(1, Ordering::Relaxed);
+        }
+        _ => {}
+    }
+    previous
+}
+
 /// Set the global evaluator.
 ///
 /// # Panics
@@ -34,14 +77,22 @@ pub fn set_global_default<E: Evaluator + Send + Sync + 'static>(evaluator: E) {
 pub fn try_set_global_default<E: Evaluator + Send + Sync + 'static>(
     evaluator: E,
 ) -> Result<(), SetGlobalDefaultError> {
-    let mut initialized = false;
-
-    GLOBAL_EVALUATOR.get_or_init(|| {
-        initialized = true;
-        evaluator.into_ref()
-    });
+    #[cfg(feature = "std")]
+    let initialized = {
+        let mut initialized = false;
+        GLOBAL_EVALUATOR.get_or_init(|| {
+            initialized = true;
+            evaluator.into_ref()
+        });
+        initialized
+    };
+    #[cfg(not(feature = "std"))]
+    let initialized = GLOBAL_EVALUATOR.set(evaluator.into_ref());
 
     if initialized {
+        if let Some(evaluator) = GLOBAL_EVALUATOR.get() {
+            register(evaluator, RegistrationKind::Global);
+        }
         Ok(())
     } else {
         Err(SetGlobalDefaultError { _private: () })
@@ -56,6 +107,8 @@ pub fn try_set_global_default<E: Evaluator + Send + Sync + 'static>(
 ///
 /// Panics if the thread evaluator is already set.
 /// For a non-panicking version, use [`try_set_thread_default`].
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 pub fn set_thread_default<E: Evaluator + Send + Sync + 'static>(evaluator: E) {
     try_set_thread_default(evaluator).expect("failed to set thread default");
 }
@@ -67,48 +120,141 @@ pub fn set_thread_default<E: Evaluator + Send + Sync + 'static>(evaluator: E) {
 /// # Errors
 ///
 /// Returns an error if the thread evaluator is already set.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 pub fn try_set_thread_default<E: Evaluator + Send + Sync + 'static>(
     evaluator: E,
 ) -> Result<(), SetThreadDefaultError> {
-    THREAD_EVALUATOR.with(|cell| {
-        let mut initialized = false;
+    if THREAD_EVALUATOR.with_borrow(Option::is_some) {
+        return Err(SetThreadDefaultError { _private: () });
+    }
 
-        cell.get_or_init(|| {
-            initialized = true;
-            evaluator.into_ref()
-        });
+    let evaluator = evaluator.into_ref();
+    register(&evaluator, RegistrationKind::Thread);
+    replace_local(&THREAD_EVALUATOR, Some(evaluator));
+    Ok(())
+}
 
-        if initialized {
-            Ok(())
-        } else {
-            Err(SetThreadDefaultError { _private: () })
-        }
-    })
+/// Set the thread evaluator until the returned guard is dropped.
+///
+/// Unlike [`set_thread_default`], this replaces any thread evaluator that is
+/// already set, and restores it when the [`ThreadDefaultGuard`] is dropped.
+/// This makes it possible to reconfigure long-lived threads, like thread pool
+/// workers.
+///
+/// Like [`set_thread_default`], this is overridden by [`with_default`] and
+/// [`set_default`].
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub fn set_thread_default_scoped<E: Evaluator + Send + Sync + 'static>(
+    evaluator: E,
+) -> ThreadDefaultGuard {
+    let evaluator = evaluator.into_ref();
+    register(&evaluator, RegistrationKind::Thread);
+    let previous = replace_local(&THREAD_EVALUATOR, Some(evaluator));
+    ThreadDefaultGuard {
+        previous,
+        _not_send: PhantomData,
+    }
+}
+
+/// Guard that restores the previous thread evaluator when dropped.
+///
+/// This guard is returned by [`set_thread_default_scoped`].
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[must_use = "the evaluator is only set until the guard is dropped"]
+pub struct ThreadDefaultGuard {
+    previous: Option<EvaluatorRef>,
+    _not_send: PhantomData<*const ()>,
+}
+
+#[cfg(feature = "std")]
+impl Drop for ThreadDefaultGuard {
+    fn drop(&mut self) {
+        replace_local(&THREAD_EVALUATOR, self.previous.take());
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for ThreadDefaultGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadDefaultGuard").finish_non_exhaustive()
+    }
 }
 
 /// Set the evaluator inside the given closure.
 ///
 /// This function overrides the thread evaluator set by [`set_global_default`]
 /// and [`set_thread_default`].
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 pub fn with_default<E: Evaluator + Send + Sync + 'static, F: FnOnce() -> R, R>(
     evaluator: E,
     f: F,
 ) -> R {
-    evaluator.on_registration();
-    with_default_no_registration(evaluator.into_ref(), f)
+    let evaluator = evaluator.into_ref();
+    register(&evaluator, RegistrationKind::Scoped);
+    with_default_no_registration(evaluator, f)
+}
+
+/// Set the evaluator until the returned guard is dropped.
+///
+/// Like [`with_default`], this function overrides the evaluators set by
+/// [`set_global_default`] and [`set_thread_default`] on the current thread.
+/// When the [`DefaultGuard`] is dropped, the previous evaluator is restored.
+///
+/// Unlike [`set_thread_default`], this can be called multiple times on the same
+/// thread, which makes it useful for tests and tools that switch evaluators.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub fn set_default<E: Evaluator + Send + Sync + 'static>(evaluator: E) -> DefaultGuard {
+    let evaluator = evaluator.into_ref();
+    register(&evaluator, RegistrationKind::Scoped);
+    let previous = replace_local(&TASK_EVALUATOR, Some(evaluator));
+    DefaultGuard {
+        previous,
+        _not_send: PhantomData,
+    }
+}
+
+/// Guard that restores the previous evaluator when dropped.
+///
+/// This guard is returned by [`set_default`].
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[must_use = "the evaluator is only set until the guard is dropped"]
+pub struct DefaultGuard {
+    previous: Option<EvaluatorRef>,
+    _not_send: PhantomData<*const ()>,
+}
+
+#[cfg(feature = "std")]
+impl Drop for DefaultGuard {
+    fn drop(&mut self) {
+        replace_local(&TASK_EVALUATOR, self.previous.take());
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for DefaultGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultGuard").finish_non_exhaustive()
+    }
 }
 
 /// Set the evaluator inside the given closure, without calling
 /// [`Evaluator::on_registration`].
+#[cfg(feature = "std")]
 pub(crate) fn with_default_no_registration<F: FnOnce() -> R, R>(
     evaluator: EvaluatorRef,
     f: F,
 ) -> R {
-    let old_thread_evaluator = TASK_EVALUATOR.replace(Some(evaluator));
+    let old_thread_evaluator = replace_local(&TASK_EVALUATOR, Some(evaluator));
 
     let result = catch_unwind(AssertUnwindSafe(f));
 
-    TASK_EVALUATOR.set(old_thread_evaluator);
+    replace_local(&TASK_EVALUATOR, old_thread_evaluator);
 
     match result {
         Ok(result) => result,
@@ -116,21 +262,216 @@ pub(crate) fn with_default_no_registration<F: FnOnce() -> R, R>(
     }
 }
 
+/// Where an evaluator was registered, see [`RegistrationInfo::kind`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum RegistrationKind {
+    /// Registered by [`set_global_default`] or [`try_set_global_default`].
+    Global,
+
+    /// Registered by [`set_thread_default`], [`try_set_thread_default`] or
+    /// [`set_thread_default_scoped`].
+    Thread,
+
+    /// Registered by [`with_default`], [`set_default`] or
+    /// [`AnyExt::wrap_evaluator`](crate::utils::AnyExt::wrap_evaluator), or
+    /// by an evaluator for an inner evaluator it creates.
+    Scoped,
+}
+
+/// Information about a registration, passed to [`Evaluator::on_registration`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct RegistrationInfo {
+    kind: RegistrationKind,
+    count: usize,
+}
+
+impl RegistrationInfo {
+    /// Creates a new `RegistrationInfo`.
+    ///
+    /// This is useful for evaluators that create and register inner
+    /// evaluators themselves, and for testing.
+    pub fn new(kind: RegistrationKind, count: usize) -> RegistrationInfo {
+        RegistrationInfo { kind, count }
+    }
+
+    /// Get where the evaluator was registered.
+    pub fn kind(&self) -> RegistrationKind {
+        self.kind
+    }
+
+    /// Get how many times the evaluator has been registered, including this
+    /// registration.
+    ///
+    /// Registrations are counted per allocation, so registering clones of the
+    /// same `Arc` or [`EvaluatorRef`] counts as registering the same
+    /// evaluator, while registering an evaluator by value always counts as a
+    /// first registration. Evaluators that start background work can use this
+    /// to only start it on the first registration.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Check if this is the first registration of the evaluator.
+    pub fn is_first(&self) -> bool {
+        self.count == 1
+    }
+}
+
+/// Number of shards of [`REGISTRATIONS`], so evaluators registered on
+/// different threads rarely wait for each other.
+#[cfg(feature = "std")]
+const REGISTRATION_SHARDS: usize = 16;
+
+/// Registration counts of evaluators, keyed by their address.
+#[cfg(feature = "std")]
+static REGISTRATIONS: [Mutex<Registrations>; REGISTRATION_SHARDS] =
+    [const { Mutex::new(Registrations::new()) }; REGISTRATION_SHARDS];
+
+#[cfg(feature = "std")]
+struct Registrations {
+    /// The entries hold weak references, so the address of an evaluator is
+    /// not reused by another evaluator while it has an entry.
+    counts: Option<HashMap<usize, (WeakEvaluatorRef, usize)>>,
+    /// Number of entries after dropped evaluators were last removed.
+    live: usize,
+}
+
+#[cfg(feature = "std")]
+impl Registrations {
+    const fn new() -> Registrations {
+        Registrations {
+            counts: None,
+            live: 0,
+        }
+    }
+
+    /// Count a registration of `evaluator`, returning the number of
+    /// registrations so far.
+    fn count(&mut self, evaluator: &EvaluatorRef) -> usize {
+        let counts = self.counts.get_or_insert_default();
+        // only remove dropped evaluators when the entries have doubled, so
+        // registering is amortized O(1)
+        if counts.len() >= (2 * self.live).max(16) {
+            counts.retain(|_, (weak, _)| weak.is_alive());
+            self.live = counts.len();
+        }
+
+        let (_, count) = counts
+            .entry(evaluator.address())
+            .or_insert_with(|| (evaluator.downgrade(), 0));
+        *count += 1;
+        *count
+    }
+}
+
+/// Call [`Evaluator::on_registration`] on a newly registered evaluator.
+pub(crate) fn register(evaluator: &EvaluatorRef, kind: RegistrationKind) {
+    #[cfg(feature = "std")]
+    let count = {
+        let address = evaluator.address();
+        // the low bits of aligned addresses are always zero
+        REGISTRATIONS[(address >> 4) % REGISTRATION_SHARDS]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .count(evaluator)
+    };
+    // without `std`, only the global evaluator can be registered, and only once
+    #[cfg(not(feature = "std"))]
+    let count = 1;
+
+    evaluator.on_registration(&RegistrationInfo::new(kind, count));
+}
+
 /// Get the default evaluator currently in scope.
 ///
 /// This function will use the first of the following:
-/// 1. The evaluator set by [`with_default`].
-/// 2. The evaluator set by [`set_thread_default`].
+/// 1. The evaluator set by [`with_default`] or [`set_default`].
+/// 2. The evaluator set by [`set_thread_default`] or [`set_thread_default_scoped`].
 /// 3. The evaluator set by [`set_global_default`].
+///
+/// Without the `std` feature, only the global evaluator is used.
+#[cfg(feature = "std")]
 pub fn get_default<F: FnOnce(Option<&EvaluatorRef>) -> R, R>(f: F) -> R {
+    if LOCAL_EVALUATORS.load(Ordering::Relaxed) == 0 {
+        return f(GLOBAL_EVALUATOR.get());
+    }
+
     let evaluator = TASK_EVALUATOR
         .with_borrow(|evaluator| evaluator.clone().map(Cow::Owned))
-        .or_else(|| THREAD_EVALUATOR.with(|cell| cell.get().cloned().map(Cow::Owned)))
+        .or_else(|| THREAD_EVALUATOR.with_borrow(|evaluator| evaluator.clone().map(Cow::Owned)))
         .or_else(|| GLOBAL_EVALUATOR.get().map(Cow::Borrowed));
 
     f(evaluator.as_deref())
 }
 
+/// Get the default evaluator currently in scope.
+///
+/// Without the `std` feature, this is the evaluator set by
+/// [`set_global_default`].
+#[cfg(not(feature = "std"))]
+pub fn get_default<F: FnOnce(Option<&EvaluatorRef>) -> R, R>(f: F) -> R {
+    f(GLOBAL_EVALUATOR.get())
+}
+
+/// Write-once slot for the global evaluator, used without `OnceLock`.
+#[cfg(not(feature = "std"))]
+struct GlobalSlot {
+    evaluator: AtomicPtr<EvaluatorRef>,
+}
+
+#[cfg(not(feature = "std"))]
+impl GlobalSlot {
+    const fn new() -> GlobalSlot {
+        GlobalSlot {
+            evaluator: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    fn get(&self) -> Option<&EvaluatorRef> {
+        // SAFETY: the pointer is either null, or was leaked by `set` and is
+        // never changed or freed afterwards
+        unsafe { self.evaluator.load(Ordering::Acquire).as_ref() }
+    }
+
+    fn set(&self, evaluator: EvaluatorRef) -> bool {
+        let ptr = Box::into_raw(Box::new(evaluator));
+        let result = self.evaluator.compare_exchange(
+            core::ptr::null_mut(),
+            ptr,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        if result.is_err() {
+            // SAFETY: the pointer was created above and has not been stored
+            drop(unsafe { Box::from_raw(ptr) });
+        }
+        result.is_ok()
+    }
+}
+
+/// Get a clone of the default evaluator currently in scope.
+///
+/// This uses the same evaluator as [`get_default`], but returns an owned
+/// reference, which can be stored or passed to
+/// [`AnyExt::wrap_evaluator`](crate::utils::AnyExt::wrap_evaluator).
+pub fn current() -> Option<EvaluatorRef> {
+    get_default(|evaluator| evaluator.cloned())
+}
+
+/// Shut down the default evaluator currently in scope.
+///
+/// This calls [`Evaluator::on_shutdown`] on the evaluator returned by
+/// [`current`], which is passed on to all evaluators it delegates to. This
+/// should be called before the process exits, so that buffered events can be
+/// flushed.
+pub fn shutdown() {
+    if let Some(evaluator) = current() {
+        evaluator.on_shutdown();
+    }
+}
+
 /// Error returned when trying to set the global evaluator
 /// when one is already set.
 ///
@@ -146,21 +487,25 @@ impl fmt::Display for SetGlobalDefaultError {
     }
 }
 
-impl std::error::Error for SetGlobalDefaultError {}
+impl core::error::Error for SetGlobalDefaultError {}
 
 /// Error returned when trying to set the thread evaluator
 /// when one is already set.
 ///
 /// This error is returned by [`try_set_thread_default`].
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 #[derive(Debug)]
 pub struct SetThreadDefaultError {
     _private: (),
 }
 
+#[cfg(feature = "std")]
 impl fmt::Display for SetThreadDefaultError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("thread evaluator already set")
     }
 }
 
-impl std::error::Error for SetThreadDefaultError {}
+#[cfg(feature = "std")]
+impl core::error::Error for SetThreadDefaultError {}
@@ -0,0 +1,167 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+use crate::{
+    evaluator::{Evaluator, current},
+    prelude::*,
+};
+
+/// Health of an evaluator, returned by
+/// [`Evaluator::health`](crate::evaluator::Evaluator::health).
+///
+/// The health can be checked before serving traffic, to make sure that an
+/// evaluator backed by a remote service has loaded its flags, see
+/// [`readiness`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Health {
+    status: HealthStatus,
+    #[cfg(feature = "std")]
+    last_refresh: Option<SystemTime>,
+}
+
+/// Status of an evaluator, see [`Health`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthStatus {
+    /// The evaluator is ready.
+    Ready,
+    /// The evaluator can evaluate features, but may return stale or default
+    /// results.
+    Degraded(String),
+    /// The evaluator can not evaluate features yet.
+    NotReady(String),
+}
+
+impl Health {
+    /// Create a new health for a ready evaluator.
+    pub fn ready() -> Health {
+        Health::new(HealthStatus::Ready)
+    }
+
+    /// Create a new health for a degraded evaluator.
+    pub fn degraded(reason: impl Into<String>) -> Health {
+        Health::new(HealthStatus::Degraded(reason.into()))
+    }
+
+    /// Create a new health for an evaluator that is not ready.
+    pub fn not_ready(reason: impl Into<String>) -> Health {
+        Health::new(HealthStatus::NotReady(reason.into()))
+    }
+
+    fn new(status: HealthStatus) -> Health {
+        Health {
+            status,
+            #[cfg(feature = "std")]
+            last_refresh: None,
+        }
+    }
+
+    /// Set the time of the last successful refresh of the evaluator.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn with_last_refresh(mut self, last_refresh: SystemTime) -> Health {
+        self.last_refresh = Some(last_refresh);
+        self
+    }
+
+    /// Get the status of the evaluator.
+    pub fn status(&self) -> &HealthStatus {
+        &self.status
+    }
+
+    /// Get the reason the evaluator is degraded or not ready, if any.
+    pub fn reason(&self) -> Option<&str> {
+        match &self.status {
+            HealthStatus::Ready => None,
+            HealthStatus::Degraded(reason) | HealthStatus::NotReady(reason) => Some(reason),
+        }
+    }
+
+    /// Get the time of the last successful refresh of the evaluator, if known.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn last_refresh(&self) -> Option<SystemTime> {
+        self.last_refresh
+    }
+
+    /// Check if the evaluator can evaluate features.
+    ///
+    /// This returns `true` for both ready and degraded evaluators.
+    pub fn is_ready(&self) -> bool {
+        !matches!(self.status, HealthStatus::NotReady(_))
+    }
+
+    /// Combine the health of multiple evaluators.
+    ///
+    /// The combined status is the worst status of the evaluators, with the
+    /// reasons of all evaluators with that status, and the last refresh is the
+    /// oldest last refresh of the evaluators. An empty iterator is ready.
+    pub fn combine(healths: impl IntoIterator<Item = Health>) -> Health {
+        let mut combined = Health::ready();
+        for health in healths {
+            combined.status = match (combined.status, health.status) {
+                (HealthStatus::Degraded(a), HealthStatus::Degraded(b)) => {
+                    HealthStatus::Degraded(format!("{a}; {b}"))
+                }
+                (HealthStatus::NotReady(a), HealthStatus::NotReady(b)) => {
+                    HealthStatus::NotReady(format!("{a}; {b}"))
+                }
+                (a, b) => a.max(b),
+            };
+            #[cfg(feature = "std")]
+            {
+                combined.last_refresh = match (combined.last_refresh, health.last_refresh) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+        }
+        combined
+    }
+}
+
+impl fmt::Display for Health {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            HealthStatus::Ready => write!(f, "ready"),
+            HealthStatus::Degraded(reason) => write!(f, "degraded: {reason}"),
+            HealthStatus::NotReady(reason) => write!(f, "not ready: {reason}"),
+        }
+    }
+}
+
+/// Get the health of the default evaluator currently in scope.
+///
+/// If there is no evaluator in scope, the evaluator is not ready.
+pub fn health() -> Health {
+    match current() {
+        Some(evaluator) => evaluator.health(),
+        None => Health::not_ready("no evaluator set"),
+    }
+}
+
+/// Check if the default evaluator currently in scope is ready.
+///
+/// This returns `Ok` with the health of the evaluator if it is ready or
+/// degraded, and `Err` if it is not ready, so it can be used directly in a
+/// readiness probe.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::{NoEvaluator, readiness, with_default};
+///
+/// assert!(readiness().is_err());
+///
+/// with_default(NoEvaluator, || {
+///     assert_eq!(readiness().unwrap().to_string(), "ready");
+/// });
+/// ```
+pub fn readiness() -> Result<Health, Health> {
+    let health = health();
+    if health.is_ready() {
+        Ok(health)
+    } else {
+        Err(health)
+    }
+}
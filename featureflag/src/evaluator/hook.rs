@@ -0,0 +1,92 @@
+use alloc::sync::Arc;
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    fields::Fields,
+    prelude::*,
+};
+
+/// Hook for observing feature flag evaluations.
+///
+/// Hooks are attached to an evaluator with [`EvaluatorExt::with_hook`](crate::evaluator::EvaluatorExt::with_hook),
+/// and are called for every feature evaluated by that evaluator.
+pub trait EvaluationHook: Send + Sync {
+    /// Called before a feature is evaluated.
+    fn before_evaluation(&self, feature: &str, context: &Context) {
+        let _ = (feature, context);
+    }
+
+    /// Called after a feature has been evaluated.
+    ///
+    /// The result is the result returned by the evaluator, where `None` means
+    /// that the feature's default value will be used.
+    fn after_evaluation(&self, feature: &str, context: &Context, result: Option<bool>) {
+        let _ = (feature, context, result);
+    }
+}
+
+impl<H: ?Sized + EvaluationHook> EvaluationHook for Arc<H> {
+    fn before_evaluation(&self, feature: &str, context: &Context) {
+        self.as_ref().before_evaluation(feature, context)
+    }
+
+    fn after_evaluation(&self, feature: &str, context: &Context, result: Option<bool>) {
+        self.as_ref().after_evaluation(feature, context, result)
+    }
+}
+
+/// Hooked evaluator, see [`EvaluatorExt::with_hook`](crate::evaluator::EvaluatorExt::with_hook).
+pub struct Hooked<E, H> {
+    pub(super) evaluator: E,
+    pub(super) hook: H,
+}
+
+impl<E: Evaluator, H: EvaluationHook> Evaluator for Hooked<E, H> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.hook.before_evaluation(feature, context);
+        let result = self.evaluator.is_enabled(feature, context);
+        self.hook.after_evaluation(feature, context, result);
+        result
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.hook.before_evaluation(feature, context);
+        let result = self.evaluator.is_enabled_traced(feature, context, trace);
+        self.hook.after_evaluation(feature, context, result);
+        result
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("Hooked").with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{
+        EvaluationTrace, Evaluator, EvaluatorDescription, EvaluatorRef, Health, RegistrationInfo,
+        merge_known_flags,
+    },
+    fields::Fields,
+};
+
+/// Chain of evaluators that routes features by their known flags.
+///
+/// Like a chain of evaluators in a `Vec<EvaluatorRef>`, each evaluator is
+/// called in turn until one of them returns `Some`. But instead of calling
+/// every evaluator, the features known by each evaluator, as returned by
+/// [`Evaluator::known_flags`], are indexed, and only the evaluators that know
+/// a feature are called for it. Evaluators that don't know their features are
+/// called for all features, in chain order.
+///
+/// The index is built when the chain is created and when it is registered.
+/// If the known flags of the evaluators change later, call
+/// [`reindex`](Self::reindex) to rebuild it.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::{BootstrapEvaluator, Evaluator, IndexedChain, NoEvaluator};
+///
+/// let chain = IndexedChain::new([
+///     BootstrapEvaluator::from_json(r#"{"a": true}"#).unwrap().into_ref(),
+///     NoEvaluator.into_ref(),
+/// ]);
+/// ```
+pub struct IndexedChain {
+    evaluators: Vec<EvaluatorRef>,
+    index: RwLock<Arc<Index>>,
+}
+
+#[derive(Default)]
+struct Index {
+    /// Indices of the evaluators to call for each known feature, in order.
+    features: HashMap<String, Vec<usize>>,
+    /// Indices of the evaluators that don't know their features, which are
+    /// called for all other features.
+    unknown: Vec<usize>,
+}
+
+impl IndexedChain {
+    /// Create a new chain of the given evaluators.
+    pub fn new<I: IntoIterator<Item = EvaluatorRef>>(evaluators: I) -> IndexedChain {
+        let chain = IndexedChain {
+            evaluators: evaluators.into_iter().collect(),
+            index: RwLock::new(Arc::default()),
+        };
+        chain.reindex();
+        chain
+    }
+
+    /// Rebuild the index of the known flags of the evaluators.
+    pub fn reindex(&self) {
+        let mut index = Index::default();
+        for (i, evaluator) in self.evaluators.iter().enumerate() {
+            match evaluator.known_flags() {
+                Some(flags) => {
+                    for flag in flags {
+                        index.features.entry(flag).or_default().push(i);
+                    }
+                }
+                None => index.unknown.push(i),
+            }
+        }
+        for evaluators in index.features.values_mut() {
+            evaluators.extend(&index.unknown);
+            evaluators.sort_unstable();
+            evaluators.dedup();
+        }
+
+        *self.index.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(index);
+    }
+
+    /// Call `f` with the evaluators to call for `feature` until it returns `Some`.
+    fn find_map<F, R>(&self, feature: &str, f: F) -> Option<R>
+    where
+        F: FnMut(&EvaluatorRef) -> Option<R>,
+    {
+        // clone the index, so evaluators can evaluate features while it's rebuilt
+        let index = self
+            .index
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        index
+            .features
+            .get(feature)
+            .unwrap_or(&index.unknown)
+            .iter()
+            .map(|&i| &self.evaluators[i])
+            .find_map(f)
+    }
+}
+
+impl Evaluator for IndexedChain {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.find_map(feature, |evaluator| evaluator.is_enabled(feature, context))
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.find_map(feature, |evaluator| {
+            evaluator.is_enabled_traced(feature, context, trace)
+        })
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        self.evaluators.iter().fold(
+            EvaluatorDescription::new("IndexedChain"),
+            |desc, evaluator| desc.with_child(evaluator.describe()),
+        )
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        merge_known_flags(
+            self.evaluators
+                .iter()
+                .map(|evaluator| evaluator.known_flags()),
+        )
+    }
+
+    fn health(&self) -> Health {
+        Health::combine(self.evaluators.iter().map(|evaluator| evaluator.health()))
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        for evaluator in &self.evaluators {
+            evaluator.on_registration(info);
+        }
+        self.reindex();
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        for evaluator in &self.evaluators {
+            evaluator.on_new_context(context.by_mut(), fields.clone());
+        }
+    }
+
+    fn on_close_context(&self, mut context: ContextRef<'_>) {
+        for evaluator in &self.evaluators {
+            evaluator.on_close_context(context.by_mut());
+        }
+    }
+
+    fn on_shutdown(&self) {
+        for evaluator in &self.evaluators {
+            evaluator.on_shutdown();
+        }
+    }
+}
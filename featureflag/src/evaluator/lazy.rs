@@ -0,0 +1,152 @@
+use std::{
+    mem,
+    sync::{Mutex, MutexGuard, OnceLock},
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    fields::Fields,
+};
+
+/// Evaluator that is built on first use.
+///
+/// The evaluator is built by calling the closure the first time a feature is
+/// evaluated or a context is created. This allows evaluators with expensive
+/// setup, like network clients, to be set up before everything they depend on
+/// exists, e.g. before an async runtime has been started.
+///
+/// Registrations before the evaluator is built are passed on to it when it is
+/// built.
+///
+/// If the closure panics, the panic is propagated, and the evaluator panics
+/// on every later use.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::{Lazy, NoEvaluator, set_global_default};
+///
+/// set_global_default(Lazy::new(|| NoEvaluator));
+/// ```
+pub struct Lazy<E, F = fn() -> E> {
+    evaluator: OnceLock<E>,
+    init: Mutex<Init<F>>,
+}
+
+struct Init<F> {
+    /// The closure, until the evaluator is being built.
+    init: Option<F>,
+    /// Registrations before the evaluator was built.
+    registrations: Vec<RegistrationInfo>,
+}
+
+impl<E, F: FnOnce() -> E> Lazy<E, F> {
+    /// Create a new lazy evaluator, built by calling `init`.
+    pub const fn new(init: F) -> Lazy<E, F> {
+        Lazy {
+            evaluator: OnceLock::new(),
+            init: Mutex::new(Init {
+                init: Some(init),
+                registrations: Vec::new(),
+            }),
+        }
+    }
+
+    /// Get the evaluator, building it if it has not been built yet.
+    pub fn force(&self) -> &E
+    where
+        E: Evaluator,
+    {
+        self.evaluator.get_or_init(|| {
+            let (init, registrations) = {
+                let mut init = self.lock_init();
+                let registrations = mem::take(&mut init.registrations);
+                let init = init
+                    .init
+                    .take()
+                    .expect("lazy evaluator initialization panicked");
+                (init, registrations)
+            };
+
+            let evaluator = init();
+            for info in &registrations {
+                evaluator.on_registration(info);
+            }
+            evaluator
+        })
+    }
+
+    /// Get the evaluator, if it has been built.
+    pub fn get(&self) -> Option<&E> {
+        self.evaluator.get()
+    }
+
+    fn lock_init(&self) -> MutexGuard<'_, Init<F>> {
+        self.init.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<E: Evaluator, F: FnOnce() -> E + Send> Evaluator for Lazy<E, F> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.force().is_enabled(feature, context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.force().is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        match self.get() {
+            Some(evaluator) => EvaluatorDescription::new("Lazy").with_child(evaluator.describe()),
+            None => EvaluatorDescription::new("Lazy").with_summary("not built"),
+        }
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.get()?.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.force().health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        if let Some(evaluator) = self.get() {
+            return evaluator.on_registration(info);
+        }
+
+        let mut init = self.lock_init();
+        if init.init.is_some() {
+            init.registrations.push(*info);
+            return;
+        }
+        drop(init);
+
+        // the evaluator is being built, and the registrations before this one
+        // are passed on to it before `force` returns
+        self.force().on_registration(info)
+    }
+
+    fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
+        self.force().on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        // contexts can only be created after the evaluator has been built
+        if let Some(evaluator) = self.get() {
+            evaluator.on_close_context(context)
+        }
+    }
+
+    fn on_shutdown(&self) {
+        if let Some(evaluator) = self.get() {
+            evaluator.on_shutdown()
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::borrow::Cow;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    fields::Fields,
+    prelude::*,
+};
+
+/// Rewrites feature names, see [`EvaluatorExt::map_names`](crate::evaluator::EvaluatorExt::map_names).
+///
+/// This trait is implemented for `HashMap<String, String>`, mapping old names
+/// to new names, and for `Fn(&str) -> Option<String>` closures.
+pub trait NameMapper: Send + Sync {
+    /// Map a feature name to a new name.
+    ///
+    /// Returns `None` if the name should not be changed.
+    fn map_name<'a>(&'a self, feature: &'a str) -> Option<Cow<'a, str>>;
+}
+
+#[cfg(feature = "std")]
+impl NameMapper for HashMap<String, String> {
+    fn map_name<'a>(&'a self, feature: &'a str) -> Option<Cow<'a, str>> {
+        self.get(feature).map(|name| Cow::Borrowed(name.as_str()))
+    }
+}
+
+impl<F: Fn(&str) -> Option<String> + Send + Sync> NameMapper for F {
+    fn map_name<'a>(&'a self, feature: &'a str) -> Option<Cow<'a, str>> {
+        self(feature).map(Cow::Owned)
+    }
+}
+
+type AliasHook = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+/// Name-mapping evaluator, see [`EvaluatorExt::map_names`](crate::evaluator::EvaluatorExt::map_names).
+pub struct MapNames<E, M> {
+    pub(super) evaluator: E,
+    pub(super) mapper: M,
+    pub(super) on_alias: Option<AliasHook>,
+}
+
+impl<E, M> MapNames<E, M> {
+    /// Set a hook that is called when a feature name is mapped to a new name.
+    ///
+    /// The hook is called with the old and new name, and can be used to report
+    /// uses of deprecated names.
+    pub fn on_alias<F>(mut self, hook: F) -> MapNames<E, M>
+    where
+        F: Fn(&str, &str) + Send + Sync + 'static,
+    {
+        self.on_alias = Some(Box::new(hook));
+        self
+    }
+}
+
+impl<E: Evaluator, M: NameMapper> MapNames<E, M> {
+    fn map_name<'a>(&'a self, feature: &'a str) -> Cow<'a, str> {
+        match self.mapper.map_name(feature) {
+            Some(name) => {
+                if let Some(on_alias) = &self.on_alias {
+                    on_alias(feature, &name);
+                }
+                name
+            }
+            None => Cow::Borrowed(feature),
+        }
+    }
+}
+
+impl<E: Evaluator, M: NameMapper> Evaluator for MapNames<E, M> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.evaluator.is_enabled(&self.map_name(feature), context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.evaluator
+            .is_enabled_traced(&self.map_name(feature), context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("MapNames").with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        // the inner evaluator knows the mapped names, which can't be mapped back
+        None
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
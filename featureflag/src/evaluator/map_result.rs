@@ -0,0 +1,66 @@
+use core::any::type_name;
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    fields::Fields,
+    prelude::*,
+};
+
+/// Result-mapping evaluator, see [`EvaluatorExt::map_result`](crate::evaluator::EvaluatorExt::map_result).
+pub struct MapResult<E, F> {
+    pub(super) evaluator: E,
+    pub(super) map_fn: F,
+}
+
+impl<E, F> Evaluator for MapResult<E, F>
+where
+    E: Evaluator,
+    F: Fn(&str, Option<bool>) -> Option<bool> + Send + Sync + 'static,
+{
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        (self.map_fn)(feature, self.evaluator.is_enabled(feature, context))
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let inner = self.evaluator.is_enabled_traced(feature, context, trace);
+        let result = (self.map_fn)(feature, inner);
+        if result != inner {
+            trace.record(type_name::<Self>(), result);
+        }
+        result
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("MapResult").with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
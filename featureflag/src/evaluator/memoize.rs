@@ -0,0 +1,124 @@
+use std::{
+    any::type_name,
+    collections::HashMap,
+    sync::{
+        RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    fields::Fields,
+};
+
+/// Memoizing evaluator, see [`EvaluatorExt::memoize`](crate::evaluator::EvaluatorExt::memoize).
+pub struct Memoize<E> {
+    id: u64,
+    evaluator: E,
+}
+
+/// Extension storing the memoized results of each [`Memoize`] in a context.
+struct MemoCache(HashMap<u64, RwLock<HashMap<String, Option<bool>>>>);
+
+impl<E> Memoize<E> {
+    pub(super) fn new(evaluator: E) -> Memoize<E> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        Memoize {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            evaluator,
+        }
+    }
+}
+
+impl<E: Evaluator> Memoize<E> {
+    fn evaluate(
+        &self,
+        feature: &str,
+        context: &Context,
+        evaluate: impl FnOnce() -> Option<bool>,
+    ) -> Option<bool> {
+        let Some(cache) = context
+            .extensions()
+            .get::<MemoCache>()
+            .and_then(|cache| cache.0.get(&self.id))
+        else {
+            return evaluate();
+        };
+
+        if let Some(result) = cache.read().unwrap().get(feature) {
+            return *result;
+        }
+
+        let result = evaluate();
+        *cache
+            .write()
+            .unwrap()
+            .entry(feature.to_string())
+            .or_insert(result)
+    }
+}
+
+impl<E: Evaluator> Evaluator for Memoize<E> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.evaluate(feature, context, || {
+            self.evaluator.is_enabled(feature, context)
+        })
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let mut evaluated = false;
+        let result = self.evaluate(feature, context, || {
+            evaluated = true;
+            self.evaluator.is_enabled_traced(feature, context, trace)
+        });
+        if !evaluated {
+            trace.record(type_name::<Self>(), result);
+        }
+        result
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("Memoize").with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        let extensions = context.extensions_mut();
+        match extensions.get_mut::<MemoCache>() {
+            Some(cache) => {
+                cache.0.insert(self.id, RwLock::default());
+            }
+            None => {
+                extensions.insert(MemoCache(HashMap::from([(self.id, RwLock::default())])));
+            }
+        }
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
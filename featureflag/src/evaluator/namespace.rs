@@ -0,0 +1,222 @@
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{
+        EvaluationTrace, Evaluator, EvaluatorDescription, EvaluatorRef, Health, RegistrationInfo,
+        merge_known_flags,
+    },
+    fields::Fields,
+    prelude::*,
+};
+
+/// Evaluator that routes features to inner evaluators by prefix.
+///
+/// Each feature is routed to the evaluator with the longest matching prefix.
+/// Features that don't match any prefix are routed to the evaluator set with
+/// [`otherwise`](Self::otherwise), or return `None` if there is none.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::{Namespace, NoEvaluator};
+///
+/// let evaluator = Namespace::new()
+///     .route_stripped("exp.", NoEvaluator)
+///     .route("ops.", NoEvaluator);
+/// ```
+pub struct Namespace {
+    routes: Vec<Route>,
+    otherwise: Option<EvaluatorRef>,
+}
+
+struct Route {
+    prefix: String,
+    strip_prefix: bool,
+    evaluator: EvaluatorRef,
+}
+
+impl Namespace {
+    /// Create a new namespace evaluator without any routes.
+    pub fn new() -> Namespace {
+        Namespace {
+            routes: Vec::new(),
+            otherwise: None,
+        }
+    }
+
+    /// Route features starting with `prefix` to `evaluator`.
+    ///
+    /// The evaluator is called with the full feature name, including the prefix.
+    pub fn route<E>(self, prefix: impl Into<String>, evaluator: E) -> Namespace
+    where
+        E: Evaluator + 'static,
+    {
+        self.add_route(prefix.into(), false, evaluator.into_ref())
+    }
+
+    /// Route features starting with `prefix` to `evaluator`, stripping the prefix.
+    ///
+    /// The evaluator is called with the feature name without the prefix.
+    pub fn route_stripped<E>(self, prefix: impl Into<String>, evaluator: E) -> Namespace
+    where
+        E: Evaluator + 'static,
+    {
+        self.add_route(prefix.into(), true, evaluator.into_ref())
+    }
+
+    /// Route features that don't match any prefix to `evaluator`.
+    pub fn otherwise<E>(mut self, evaluator: E) -> Namespace
+    where
+        E: Evaluator + 'static,
+    {
+        self.otherwise = Some(evaluator.into_ref());
+        self
+    }
+
+    fn add_route(
+        mut self,
+        prefix: String,
+        strip_prefix: bool,
+        evaluator: EvaluatorRef,
+    ) -> Namespace {
+        let index = self
+            .routes
+            .partition_point(|route| route.prefix.len() >= prefix.len());
+        self.routes.insert(
+            index,
+            Route {
+                prefix,
+                strip_prefix,
+                evaluator,
+            },
+        );
+        self
+    }
+
+    fn route_index(&self, feature: &str) -> Option<usize> {
+        self.routes
+            .iter()
+            .position(|route| feature.starts_with(&route.prefix))
+    }
+
+    fn resolve<'a>(&'a self, feature: &'a str) -> Option<(&'a EvaluatorRef, &'a str)> {
+        self.routes
+            .iter()
+            .find_map(|route| {
+                let rest = feature.strip_prefix(&route.prefix)?;
+                Some((
+                    &route.evaluator,
+                    if route.strip_prefix { rest } else { feature },
+                ))
+            })
+            .or_else(|| Some((self.otherwise.as_ref()?, feature)))
+    }
+
+    fn evaluators(&self) -> impl Iterator<Item = &EvaluatorRef> {
+        self.routes
+            .iter()
+            .map(|route| &route.evaluator)
+            .chain(&self.otherwise)
+    }
+}
+
+impl Default for Namespace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluator for Namespace {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        let (evaluator, feature) = self.resolve(feature)?;
+        evaluator.is_enabled(feature, context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let (evaluator, feature) = self.resolve(feature)?;
+        evaluator.is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        let mut description = EvaluatorDescription::new("Namespace");
+        for route in &self.routes {
+            let summary = if route.strip_prefix {
+                format!("prefix {:?}, stripped", route.prefix)
+            } else {
+                format!("prefix {:?}", route.prefix)
+            };
+            description = description.with_child(
+                EvaluatorDescription::new("Route")
+                    .with_summary(summary)
+                    .with_child(route.evaluator.describe()),
+            );
+        }
+        if let Some(otherwise) = &self.otherwise {
+            description = description.with_child(
+                EvaluatorDescription::new("Otherwise").with_child(otherwise.describe()),
+            );
+        }
+        description
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        let routes = self.routes.iter().enumerate().map(|(index, route)| {
+            let flags = route.evaluator.known_flags()?.into_iter().map(|feature| {
+                if route.strip_prefix {
+                    format!("{}{feature}", route.prefix)
+                } else {
+                    feature
+                }
+            });
+            Some(
+                flags
+                    .filter(|feature| self.route_index(feature) == Some(index))
+                    .collect(),
+            )
+        });
+
+        let otherwise = self.otherwise.as_ref().map(|otherwise| {
+            Some(
+                otherwise
+                    .known_flags()?
+                    .into_iter()
+                    .filter(|feature| self.route_index(feature).is_none())
+                    .collect(),
+            )
+        });
+
+        merge_known_flags(routes.chain(otherwise))
+    }
+
+    fn health(&self) -> Health {
+        Health::combine(self.evaluators().map(|evaluator| evaluator.health()))
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        for evaluator in self.evaluators() {
+            evaluator.on_registration(info);
+        }
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        for evaluator in self.evaluators() {
+            evaluator.on_new_context(context.by_mut(), fields.clone());
+        }
+    }
+
+    fn on_close_context(&self, mut context: ContextRef<'_>) {
+        for evaluator in self.evaluators() {
+            evaluator.on_close_context(context.by_mut());
+        }
+    }
+
+    fn on_shutdown(&self) {
+        for evaluator in self.evaluators() {
+            evaluator.on_shutdown();
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    any::type_name,
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{
+        EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo,
+        merge_known_flags,
+    },
+    fields::Fields,
+};
+
+/// Evaluator that persists the last known feature values to disk.
+///
+/// Results of the inner evaluator in the root context are stored, and written
+/// to the cache file by [`flush`](Self::flush), on
+/// [`shutdown`](crate::shutdown), and when the cache is dropped. When the
+/// inner evaluator returns `None`, e.g. because a remote backend has not
+/// finished its first fetch yet, the last known value is returned instead.
+///
+/// Only results in the root context are stored, since results in other
+/// contexts may depend on the context fields.
+///
+/// The cache file contains one `name=true` or `name=false` line per feature.
+/// The characters `%`, `=`, `\r` and `\n` in names are percent-encoded.
+pub struct PersistentCache<E> {
+    evaluator: E,
+    path: PathBuf,
+    values: RwLock<HashMap<String, bool>>,
+    dirty: AtomicBool,
+    /// Held while writing the cache file, so writers don't share the
+    /// temporary file.
+    writing: Mutex<()>,
+    /// Why the cache file could not be loaded or written, reported by
+    /// [`health`](Evaluator::health).
+    error: Mutex<Option<String>>,
+}
+
+impl<E> PersistentCache<E> {
+    /// Create a new persistent cache for `evaluator`, stored at `path`.
+    ///
+    /// If the file exists, the last known values are loaded from it. If the
+    /// file is corrupt, the cache starts empty, and is reported as degraded by
+    /// [`health`](Evaluator::health) until it has been written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but could not be read.
+    pub fn new(evaluator: E, path: impl Into<PathBuf>) -> io::Result<PersistentCache<E>> {
+        let path = path.into();
+        let (values, error) = match fs::read(&path) {
+            Ok(contents) => match parse(&contents) {
+                Ok(values) => (values, None),
+                Err(err) => (
+                    HashMap::new(),
+                    Some(format!(
+                        "ignored corrupt feature cache {}: {err}",
+                        path.display()
+                    )),
+                ),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (HashMap::new(), None),
+            Err(err) => return Err(err),
+        };
+
+        Ok(PersistentCache {
+            evaluator,
+            path,
+            values: RwLock::new(values),
+            dirty: AtomicBool::new(false),
+            writing: Mutex::new(()),
+            error: Mutex::new(error),
+        })
+    }
+
+    /// Get the path of the cache file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the last known values to the cache file, if they have changed.
+    ///
+    /// The file is written atomically, by writing to a temporary file and
+    /// renaming it.
+    pub fn flush(&self) -> io::Result<()> {
+        let _writing = self.writing.lock().unwrap_or_else(|err| err.into_inner());
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+
+        let mut values = self
+            .values
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, enabled)| format!("{}={enabled}\n", escape(name)))
+            .collect::<Vec<_>>();
+        values.sort();
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let result = fs::write(&tmp, values.concat()).and_then(|()| fs::rename(&tmp, &self.path));
+        *self.error.lock().unwrap() = match &result {
+            Ok(()) => None,
+            Err(err) => {
+                self.dirty.store(true, Ordering::Release);
+                Some(format!(
+                    "failed to write feature cache {}: {err}",
+                    self.path.display()
+                ))
+            }
+        };
+        result
+    }
+
+    fn update(&self, feature: &str, context: &Context, result: Option<bool>) -> Option<bool> {
+        let Some(enabled) = result else {
+            return self.values.read().unwrap().get(feature).copied();
+        };
+
+        if context.is_root() && self.values.read().unwrap().get(feature) != Some(&enabled) {
+            self.values
+                .write()
+                .unwrap()
+                .insert(feature.to_string(), enabled);
+            self.dirty.store(true, Ordering::Release);
+        }
+        Some(enabled)
+    }
+}
+
+fn parse(contents: &[u8]) -> io::Result<HashMap<String, bool>> {
+    let contents = std::str::from_utf8(contents)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, value) = line.split_once('=').ok_or_else(|| invalid_line(line))?;
+            let name = unescape(name).ok_or_else(|| invalid_line(line))?;
+            let value = value.parse().map_err(|_| invalid_line(line))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '%' | '=' | '\r' | '\n' => escaped.push_str(&format!("%{:02X}", c as u8)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(name: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(i) = rest.find('%') {
+        unescaped.push_str(&rest[..i]);
+        let byte = u8::from_str_radix(rest.get(i + 1..i + 3)?, 16).ok()?;
+        if !matches!(byte, b'%' | b'=' | b'\r' | b'\n') {
+            return None;
+        }
+        unescaped.push(byte as char);
+        rest = &rest[i + 3..];
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
+
+fn invalid_line(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid feature cache line: {line:?}"),
+    )
+}
+
+impl<E> Drop for PersistentCache<E> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl<E: Evaluator> Evaluator for PersistentCache<E> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        let result = self.evaluator.is_enabled(feature, context);
+        self.update(feature, context, result)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let result = self.evaluator.is_enabled_traced(feature, context, trace);
+        let cached = self.update(feature, context, result);
+        if result.is_none() {
+            trace.record(type_name::<Self>(), cached);
+        }
+        cached
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("PersistentCache")
+            .with_summary(format!("{}", self.path.display()))
+            .with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        let cached = self.values.read().unwrap().keys().cloned().collect();
+        merge_known_flags([self.evaluator.known_flags(), Some(cached)])
+    }
+
+    fn health(&self) -> Health {
+        let own = match &*self.error.lock().unwrap() {
+            Some(error) => Health::degraded(error.clone()),
+            None => Health::ready(),
+        };
+        Health::combine([self.evaluator.health(), own])
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, context: ContextRef<'_>, fields: Fields<'_>) {
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        let _ = self.flush();
+        self.evaluator.on_shutdown()
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    feature::shared_name,
+    fields::Fields,
+};
+
+/// An evaluation missing required context fields, see [`RequiredFields`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingFields {
+    feature: Arc<str>,
+    missing: Vec<String>,
+}
+
+impl MissingFields {
+    /// Get the name of the evaluated feature.
+    pub fn feature(&self) -> &str {
+        &self.feature
+    }
+
+    /// Get the required fields that were missing from the context.
+    pub fn missing(&self) -> &[String] {
+        &self.missing
+    }
+}
+
+impl fmt::Display for MissingFields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "feature {:?} evaluated without required fields: {}",
+            self.feature,
+            self.missing.join(", ")
+        )
+    }
+}
+
+/// Evaluator that reports evaluations missing required context fields.
+///
+/// Features that depend on a context field, e.g. percentage rollouts on
+/// `user_id`, silently return the wrong result when the field is missing.
+/// This evaluator checks that the required fields are set in the context
+/// (or its parents) when a feature is evaluated, and reports any missing
+/// fields to the function set with [`on_missing`](Self::on_missing). The first
+/// evaluation of each feature with missing fields is also reported by
+/// [`health`](Evaluator::health), which is degraded until the evaluator is
+/// dropped.
+///
+/// Required fields are set with [`require`](Self::require), or loaded from the
+/// `requires` metadata of registered features with
+/// [`from_registry`](Self::from_registry). The result of the inner evaluator
+/// is returned unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::{NoEvaluator, RequiredFields};
+///
+/// let evaluator = RequiredFields::new(NoEvaluator)
+///     .require("rollout", ["user_id"])
+///     .on_missing(|missing| panic!("{missing}"));
+/// ```
+pub struct RequiredFields<E> {
+    evaluator: E,
+    required: HashMap<String, Vec<Arc<str>>>,
+    /// All required fields, shared by the field keys of contexts.
+    fields: Vec<Arc<str>>,
+    on_missing: Option<MissingHook>,
+    /// The first evaluation of each feature with missing fields.
+    reported: Mutex<Vec<MissingFields>>,
+}
+
+type MissingHook = Box<dyn Fn(&MissingFields) + Send + Sync>;
+
+/// Extension storing the required field keys of a context.
+struct FieldKeys(Vec<Arc<str>>);
+
+impl<E> RequiredFields<E> {
+    /// Create a new evaluator checking the required fields of `evaluator`.
+    pub fn new(evaluator: E) -> RequiredFields<E> {
+        RequiredFields {
+            evaluator,
+            required: HashMap::new(),
+            fields: Vec::new(),
+            on_missing: None,
+            reported: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Create a new evaluator checking the required fields of all registered
+    /// features, see [`FeatureInfo::required_fields`](crate::registry::FeatureInfo::required_fields).
+    #[cfg(feature = "feature-registry")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+    pub fn from_registry(evaluator: E) -> RequiredFields<E> {
+        crate::registry::registered_features()
+            .iter()
+            .filter(|info| info.required_fields().next().is_some())
+            .fold(RequiredFields::new(evaluator), |evaluator, info| {
+                evaluator.require(info.name(), info.required_fields())
+            })
+    }
+
+    /// Require `fields` to be set in the context when evaluating `feature`.
+    pub fn require<I>(mut self, feature: impl Into<String>, fields: I) -> RequiredFields<E>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let required = self.required.entry(feature.into()).or_default();
+        for field in fields {
+            let field = field.into();
+            let field = match self.fields.iter().find(|f| ***f == field) {
+                Some(field) => field.clone(),
+                None => {
+                    let field = Arc::<str>::from(field);
+                    self.fields.push(field.clone());
+                    field
+                }
+            };
+            if !required.contains(&field) {
+                required.push(field);
+            }
+        }
+        self
+    }
+
+    /// Set the function called when an evaluation is missing required fields.
+    pub fn on_missing<F>(mut self, on_missing: F) -> RequiredFields<E>
+    where
+        F: Fn(&MissingFields) + Send + Sync + 'static,
+    {
+        self.on_missing = Some(Box::new(on_missing));
+        self
+    }
+
+    fn check(&self, feature: &str, context: &Context) {
+        let Some(required) = self.required.get(feature) else {
+            return;
+        };
+
+        let missing = required
+            .iter()
+            .filter(|field| {
+                !context
+                    .iter()
+                    .filter_map(|context| context.extensions().get::<FieldKeys>())
+                    .any(|keys| keys.0.contains(field))
+            })
+            .map(|field| field.to_string())
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return;
+        }
+
+        let missing = MissingFields {
+            feature: shared_name(feature),
+            missing,
+        };
+        {
+            let mut reported = self.reported.lock().unwrap();
+            if !reported.iter().any(|r| r.feature == missing.feature) {
+                reported.push(missing.clone());
+            }
+        }
+        if let Some(on_missing) = &self.on_missing {
+            on_missing(&missing);
+        }
+    }
+}
+
+impl<E: Evaluator> Evaluator for RequiredFields<E> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.check(feature, context);
+        self.evaluator.is_enabled(feature, context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.check(feature, context);
+        self.evaluator.is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("RequiredFields")
+            .with_summary(format!("{} features", self.required.len()))
+            .with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        let reported = self.reported.lock().unwrap();
+        Health::combine(
+            [self.evaluator.health()].into_iter().chain(
+                reported
+                    .iter()
+                    .map(|missing| Health::degraded(missing.to_string())),
+            ),
+        )
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        // only the keys of required fields are stored, if any
+        let keys = fields
+            .pairs()
+            .filter_map(|(key, _)| self.fields.iter().find(|field| ***field == *key))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !keys.is_empty() {
+            context.extensions_mut().insert(FieldKeys(keys));
+        }
+
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
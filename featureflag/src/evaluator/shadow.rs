@@ -0,0 +1,86 @@
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    fields::Fields,
+    prelude::*,
+};
+
+/// Shadow evaluator, see [`EvaluatorExt::shadow`](crate::evaluator::EvaluatorExt::shadow).
+pub struct Shadow<E, C, F> {
+    pub(super) primary: E,
+    pub(super) candidate: C,
+    pub(super) on_mismatch: F,
+}
+
+impl<E, C, F> Shadow<E, C, F>
+where
+    C: Evaluator,
+    F: Fn(&str, Option<bool>, Option<bool>) + Send + Sync + 'static,
+{
+    fn compare(&self, feature: &str, context: &Context, primary: Option<bool>) {
+        let candidate = self.candidate.is_enabled(feature, context);
+        if primary != candidate {
+            (self.on_mismatch)(feature, primary, candidate);
+        }
+    }
+}
+
+impl<E, C, F> Evaluator for Shadow<E, C, F>
+where
+    E: Evaluator,
+    C: Evaluator,
+    F: Fn(&str, Option<bool>, Option<bool>) + Send + Sync + 'static,
+{
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        let result = self.primary.is_enabled(feature, context);
+        self.compare(feature, context, result);
+        result
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let result = self.primary.is_enabled_traced(feature, context, trace);
+        self.compare(feature, context, result);
+        result
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("Shadow")
+            .with_child(self.primary.describe())
+            .with_child(self.candidate.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.primary.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        // the candidate is only compared against, so it does not affect health
+        self.primary.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.primary.on_registration(info);
+        self.candidate.on_registration(info);
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        self.primary
+            .on_new_context(context.by_mut(), fields.clone());
+        self.candidate.on_new_context(context, fields);
+    }
+
+    fn on_close_context(&self, mut context: ContextRef<'_>) {
+        self.primary.on_close_context(context.by_mut());
+        self.candidate.on_close_context(context);
+    }
+
+    fn on_shutdown(&self) {
+        self.primary.on_shutdown();
+        self.candidate.on_shutdown();
+    }
+}
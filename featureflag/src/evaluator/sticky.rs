@@ -0,0 +1,230 @@
+use std::{
+    any::type_name,
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{
+        EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo,
+        tenant::tenant_key,
+    },
+    fields::Fields,
+};
+
+/// Store of sticky assignments, see [`Sticky`].
+///
+/// Assignments are stored per context key, such as a user or session ID, and
+/// feature. Implementations can persist them in a session store or database,
+/// so that assignments survive restarts and changes to the inner evaluator.
+pub trait StickyStore: Send + Sync {
+    /// Get the stored assignment of a feature for a context key.
+    fn get(&self, key: &str, feature: &str) -> Option<bool>;
+
+    /// Store the assignment of a feature for a context key.
+    fn set(&self, key: &str, feature: &str, enabled: bool);
+}
+
+impl<S: StickyStore + ?Sized> StickyStore for Arc<S> {
+    fn get(&self, key: &str, feature: &str) -> Option<bool> {
+        (**self).get(key, feature)
+    }
+
+    fn set(&self, key: &str, feature: &str, enabled: bool) {
+        (**self).set(key, feature, enabled)
+    }
+}
+
+/// In-memory [`StickyStore`].
+///
+/// Assignments are kept for the lifetime of the store, which is mainly useful
+/// for tests, or together with a persistent store of its own.
+#[derive(Debug, Default)]
+pub struct MemoryStickyStore {
+    assignments: Mutex<HashMap<(String, String), bool>>,
+}
+
+impl MemoryStickyStore {
+    /// Create a new empty store.
+    pub fn new() -> MemoryStickyStore {
+        MemoryStickyStore::default()
+    }
+
+    /// Get the number of stored assignments.
+    pub fn len(&self) -> usize {
+        self.assignments.lock().unwrap().len()
+    }
+
+    /// Check if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl StickyStore for MemoryStickyStore {
+    fn get(&self, key: &str, feature: &str) -> Option<bool> {
+        self.assignments
+            .lock()
+            .unwrap()
+            .get(&(key.to_string(), feature.to_string()))
+            .copied()
+    }
+
+    fn set(&self, key: &str, feature: &str, enabled: bool) {
+        self.assignments
+            .lock()
+            .unwrap()
+            .insert((key.to_string(), feature.to_string()), enabled);
+    }
+}
+
+/// Evaluator that keeps the assignments of an evaluator sticky per context
+/// key.
+///
+/// When a context is created with the key field, e.g. `user_id`, the key is
+/// selected for the context and its children. Evaluations in contexts with a
+/// key consult the [`StickyStore`] first, and the first result of the inner
+/// evaluator for each key and feature is stored. This keeps assignments stable
+/// when the inner evaluator changes, e.g. when a rollout percentage is
+/// increased.
+///
+/// Key fields can be string or integer values. Contexts without a key, and
+/// features the inner evaluator returns `None` for, are not stored.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::{MemoryStickyStore, NoEvaluator, Sticky};
+///
+/// let evaluator = Sticky::new(NoEvaluator, "user_id", MemoryStickyStore::new());
+/// ```
+pub struct Sticky<E, S> {
+    id: u64,
+    evaluator: E,
+    key: String,
+    store: S,
+}
+
+/// Extension storing the selected keys of each [`Sticky`] in a context.
+struct StickyKeys(HashMap<u64, Arc<str>>);
+
+impl<E, S> Sticky<E, S> {
+    /// Create a new sticky evaluator on the context field `key`, storing the
+    /// assignments of `evaluator` in `store`.
+    pub fn new(evaluator: E, key: impl Into<String>, store: S) -> Sticky<E, S> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        Sticky {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            evaluator,
+            key: key.into(),
+            store,
+        }
+    }
+
+    /// Get the store of the assignments.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    fn selected<'a>(&self, mut contexts: impl Iterator<Item = &'a Context>) -> Option<&'a str> {
+        contexts.find_map(|context| {
+            let keys = context.extensions().get::<StickyKeys>()?;
+            keys.0.get(&self.id).map(|key| &**key)
+        })
+    }
+}
+
+impl<E: Evaluator, S: StickyStore> Sticky<E, S> {
+    fn evaluate(
+        &self,
+        feature: &str,
+        context: &Context,
+        evaluate: impl FnOnce() -> Option<bool>,
+    ) -> Option<bool> {
+        let Some(key) = self.selected(context.iter()) else {
+            return evaluate();
+        };
+
+        if let Some(enabled) = self.store.get(key, feature) {
+            return Some(enabled);
+        }
+
+        let result = evaluate();
+        if let Some(enabled) = result {
+            self.store.set(key, feature, enabled);
+        }
+        result
+    }
+}
+
+impl<E: Evaluator, S: StickyStore> Evaluator for Sticky<E, S> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.evaluate(feature, context, || {
+            self.evaluator.is_enabled(feature, context)
+        })
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        let mut evaluated = false;
+        let result = self.evaluate(feature, context, || {
+            evaluated = true;
+            self.evaluator.is_enabled_traced(feature, context, trace)
+        });
+        if !evaluated {
+            trace.record(type_name::<Self>(), result);
+        }
+        result
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        EvaluatorDescription::new("Sticky")
+            .with_summary(format!("field {:?}", self.key))
+            .with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        if let Some(key) = fields.get(&self.key).and_then(tenant_key) {
+            let extensions = context.extensions_mut();
+            match extensions.get_mut::<StickyKeys>() {
+                Some(keys) => {
+                    keys.0.insert(self.id, key.into());
+                }
+                None => {
+                    extensions.insert(StickyKeys(HashMap::from([(self.id, key.into())])));
+                }
+            }
+        }
+
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
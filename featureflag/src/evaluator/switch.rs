@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{
+        EvaluationTrace, Evaluator, EvaluatorDescription, EvaluatorRef, Health, RegistrationInfo,
+        merge_known_flags,
+    },
+    fields::Fields,
+    value::{ToValue, Value},
+};
+
+/// Evaluator that selects an inner evaluator based on a context field.
+///
+/// When a context is created with the field, the evaluator registered for the
+/// field's value with [`case`](Self::case) is selected for that context and its
+/// children. Contexts without a matching case use the evaluator set with
+/// [`otherwise`](Self::otherwise), or return `None` if there is none.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::evaluator::{NoEvaluator, SwitchBy};
+///
+/// let evaluator = SwitchBy::new("tenant")
+///     .case("acme", NoEvaluator)
+///     .otherwise(NoEvaluator);
+/// ```
+pub struct SwitchBy {
+    id: u64,
+    key: String,
+    cases: Vec<(Value<'static>, EvaluatorRef)>,
+    otherwise: Option<EvaluatorRef>,
+}
+
+/// Extension storing the selected cases of each [`SwitchBy`] in a context.
+///
+/// `None` means that the field was set, but did not match any case.
+struct Selection(HashMap<u64, Option<usize>>);
+
+impl SwitchBy {
+    /// Create a new switch evaluator on the context field `key`, without any cases.
+    pub fn new(key: impl Into<String>) -> SwitchBy {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        SwitchBy {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            key: key.into(),
+            cases: Vec::new(),
+            otherwise: None,
+        }
+    }
+
+    /// Use `evaluator` for contexts where the field is equal to `value`.
+    pub fn case<V, E>(mut self, value: V, evaluator: E) -> SwitchBy
+    where
+        V: ToValue,
+        E: Evaluator + 'static,
+    {
+        self.cases
+            .push((value.to_value().into_static(), evaluator.into_ref()));
+        self
+    }
+
+    /// Use `evaluator` for contexts that don't match any case.
+    pub fn otherwise<E>(mut self, evaluator: E) -> SwitchBy
+    where
+        E: Evaluator + 'static,
+    {
+        self.otherwise = Some(evaluator.into_ref());
+        self
+    }
+
+    fn resolve(&self, context: &Context) -> Option<&EvaluatorRef> {
+        let selected = context
+            .iter()
+            .filter_map(|context| context.extensions().get::<Selection>())
+            .find_map(|selection| selection.0.get(&self.id).copied())
+            .flatten();
+
+        match selected {
+            Some(index) => Some(&self.cases[index].1),
+            None => self.otherwise.as_ref(),
+        }
+    }
+
+    fn evaluators(&self) -> impl Iterator<Item = &EvaluatorRef> {
+        self.cases
+            .iter()
+            .map(|(_, evaluator)| evaluator)
+            .chain(&self.otherwise)
+    }
+}
+
+impl Evaluator for SwitchBy {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.resolve(context)?.is_enabled(feature, context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.resolve(context)?
+            .is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        let mut description =
+            EvaluatorDescription::new("SwitchBy").with_summary(format!("field {:?}", self.key));
+        for (value, evaluator) in &self.cases {
+            description = description.with_child(
+                EvaluatorDescription::new("Case")
+                    .with_summary(format!("{value:?}"))
+                    .with_child(evaluator.describe()),
+            );
+        }
+        if let Some(otherwise) = &self.otherwise {
+            description = description.with_child(
+                EvaluatorDescription::new("Otherwise").with_child(otherwise.describe()),
+            );
+        }
+        description
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        merge_known_flags(self.evaluators().map(|evaluator| evaluator.known_flags()))
+    }
+
+    fn health(&self) -> Health {
+        Health::combine(self.evaluators().map(|evaluator| evaluator.health()))
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        for evaluator in self.evaluators() {
+            evaluator.on_registration(info);
+        }
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        if let Some(value) = fields.get(&self.key) {
+            let selected = self.cases.iter().position(|(v, _)| v == value);
+            let extensions = context.extensions_mut();
+            match extensions.get_mut::<Selection>() {
+                Some(selection) => {
+                    selection.0.insert(self.id, selected);
+                }
+                None => {
+                    extensions.insert(Selection(HashMap::from([(self.id, selected)])));
+                }
+            }
+        }
+
+        for evaluator in self.evaluators() {
+            evaluator.on_new_context(context.by_mut(), fields.clone());
+        }
+    }
+
+    fn on_close_context(&self, mut context: ContextRef<'_>) {
+        for evaluator in self.evaluators() {
+            evaluator.on_close_context(context.by_mut());
+        }
+    }
+
+    fn on_shutdown(&self) {
+        for evaluator in self.evaluators() {
+            evaluator.on_shutdown();
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    context::{Context, ContextRef},
+    evaluator::{
+        EvaluationTrace, Evaluator, EvaluatorDescription, EvaluatorRef, Health, RegistrationInfo,
+        RegistrationKind, register,
+    },
+    fields::Fields,
+    value::Value,
+};
+
+/// Evaluator that routes evaluations to a separate evaluator per tenant.
+///
+/// When a context is created with the tenant field, the evaluator for that
+/// tenant is selected for the context and its children. Evaluators are created
+/// lazily by the factory function the first time a tenant is seen, and are
+/// cached until they are evicted.
+///
+/// Tenant keys can be string or integer values. Contexts without a tenant use
+/// the evaluator set with [`otherwise`](Self::otherwise), or return `None` if
+/// there is none.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use featureflag::evaluator::{NoEvaluator, TenantRouter};
+///
+/// let evaluator = TenantRouter::new("tenant", |_tenant: &str| NoEvaluator)
+///     .idle_timeout(Duration::from_secs(600));
+/// ```
+pub struct TenantRouter<F> {
+    id: u64,
+    key: String,
+    factory: F,
+    otherwise: Option<EvaluatorRef>,
+    idle_timeout: Option<Duration>,
+    epoch: Instant,
+    tenants: Mutex<HashMap<String, Arc<Tenant>>>,
+}
+
+struct Tenant {
+    evaluator: EvaluatorRef,
+    last_used: AtomicU64,
+}
+
+/// Extension storing the selected tenants of each [`TenantRouter`] in a context.
+struct Selection(HashMap<u64, Arc<Tenant>>);
+
+impl<F, E> TenantRouter<F>
+where
+    F: Fn(&str) -> E + Send + Sync,
+    E: Evaluator + 'static,
+{
+    /// Create a new tenant router on the context field `key`.
+    ///
+    /// The factory is called to create the evaluator for each new tenant. It
+    /// is called without holding any locks, so if contexts for a new tenant
+    /// are created concurrently, it may be called more than once for the
+    /// tenant, and only one of the evaluators is used.
+    pub fn new(key: impl Into<String>, factory: F) -> TenantRouter<F> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        TenantRouter {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            key: key.into(),
+            factory,
+            otherwise: None,
+            idle_timeout: None,
+            epoch: Instant::now(),
+            tenants: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use `evaluator` for contexts without a tenant.
+    pub fn otherwise<U>(mut self, evaluator: U) -> TenantRouter<F>
+    where
+        U: Evaluator + 'static,
+    {
+        self.otherwise = Some(evaluator.into_ref());
+        self
+    }
+
+    /// Evict tenants that have not been used for `timeout`.
+    ///
+    /// Idle tenants are evicted when a new tenant is created, or when
+    /// [`evict_idle`](Self::evict_idle) is called. Contexts that were created
+    /// before a tenant was evicted keep using its evaluator.
+    pub fn idle_timeout(mut self, timeout: Duration) -> TenantRouter<F> {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Get the number of cached tenants.
+    pub fn tenant_count(&self) -> usize {
+        self.tenants.lock().unwrap().len()
+    }
+
+    /// Evict tenants that have been idle for longer than the idle timeout.
+    ///
+    /// Does nothing if no idle timeout has been set.
+    pub fn evict_idle(&self) {
+        self.evict_idle_locked(&mut self.tenants.lock().unwrap());
+    }
+
+    fn evict_idle_locked(&self, tenants: &mut HashMap<String, Arc<Tenant>>) {
+        let Some(timeout) = self.idle_timeout else {
+            return;
+        };
+
+        let now = self.now();
+        let timeout = timeout.as_nanos().try_into().unwrap_or(u64::MAX);
+        tenants.retain(|_, tenant| {
+            now.saturating_sub(tenant.last_used.load(Ordering::Relaxed)) < timeout
+        });
+    }
+
+    fn now(&self) -> u64 {
+        self.epoch
+            .elapsed()
+            .as_nanos()
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+
+    fn tenant(&self, key: &str) -> Arc<Tenant> {
+        if let Some(tenant) = self.tenants.lock().unwrap().get(key) {
+            tenant.last_used.store(self.now(), Ordering::Relaxed);
+            return tenant.clone();
+        }
+
+        // the evaluator is built without holding the lock, so other tenants
+        // can be used meanwhile
+        let evaluator = (self.factory)(key).into_ref();
+
+        let mut tenants = self.tenants.lock().unwrap();
+        self.evict_idle_locked(&mut tenants);
+        match tenants.entry(key.to_string()) {
+            // another thread built an evaluator for the tenant first
+            Entry::Occupied(entry) => {
+                let tenant = entry.get();
+                tenant.last_used.store(self.now(), Ordering::Relaxed);
+                tenant.clone()
+            }
+            Entry::Vacant(entry) => {
+                register(&evaluator, RegistrationKind::Scoped);
+                entry
+                    .insert(Arc::new(Tenant {
+                        evaluator,
+                        last_used: AtomicU64::new(self.now()),
+                    }))
+                    .clone()
+            }
+        }
+    }
+
+    fn selected<'a>(
+        &self,
+        mut contexts: impl Iterator<Item = &'a Context>,
+    ) -> Option<&'a Arc<Tenant>> {
+        contexts.find_map(|context| context.extensions().get::<Selection>()?.0.get(&self.id))
+    }
+
+    fn resolve<'a>(&'a self, context: &'a Context) -> Option<&'a EvaluatorRef> {
+        match self.selected(context.iter()) {
+            Some(tenant) => {
+                tenant.last_used.store(self.now(), Ordering::Relaxed);
+                Some(&tenant.evaluator)
+            }
+            None => self.otherwise.as_ref(),
+        }
+    }
+}
+
+pub(super) fn tenant_key(value: &Value<'_>) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(s.to_string()),
+        Value::I64(n) => Some(n.to_string()),
+        Value::U64(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+impl<F, E> Evaluator for TenantRouter<F>
+where
+    F: Fn(&str) -> E + Send + Sync,
+    E: Evaluator + 'static,
+{
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.resolve(context)?.is_enabled(feature, context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.resolve(context)?
+            .is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        let mut summary = format!("field {:?}, {} tenants", self.key, self.tenant_count());
+        if let Some(timeout) = self.idle_timeout {
+            summary += &format!(", idle timeout {timeout:?}");
+        }
+
+        let mut description = EvaluatorDescription::new("TenantRouter").with_summary(summary);
+        if let Some(otherwise) = &self.otherwise {
+            description = description.with_child(
+                EvaluatorDescription::new("Otherwise").with_child(otherwise.describe()),
+            );
+        }
+        description
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        // tenant evaluators are created lazily, so their features are not known
+        None
+    }
+
+    fn health(&self) -> Health {
+        let tenants = self
+            .tenants
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        Health::combine(
+            tenants
+                .iter()
+                .map(|tenant| tenant.evaluator.health())
+                .chain(self.otherwise.as_ref().map(|otherwise| otherwise.health())),
+        )
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        if let Some(otherwise) = &self.otherwise {
+            otherwise.on_registration(info);
+        }
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        let tenant = match fields.get(&self.key).and_then(tenant_key) {
+            Some(key) => Some(self.tenant(&key)),
+            None => self.selected(context.iter()).cloned(),
+        };
+
+        match tenant {
+            Some(tenant) => {
+                tenant.evaluator.on_new_context(context.by_mut(), fields);
+
+                let extensions = context.extensions_mut();
+                match extensions.get_mut::<Selection>() {
+                    Some(selection) => {
+                        selection.0.insert(self.id, tenant);
+                    }
+                    None => {
+                        extensions.insert(Selection(HashMap::from([(self.id, tenant)])));
+                    }
+                }
+            }
+            None => {
+                if let Some(otherwise) = &self.otherwise {
+                    otherwise.on_new_context(context, fields);
+                }
+            }
+        }
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        let tenant = context
+            .extensions()
+            .get::<Selection>()
+            .and_then(|selection| selection.0.get(&self.id))
+            .cloned();
+
+        match tenant {
+            Some(tenant) => tenant.evaluator.on_close_context(context),
+            None => {
+                if let Some(otherwise) = &self.otherwise {
+                    otherwise.on_close_context(context);
+                }
+            }
+        }
+    }
+
+    fn on_shutdown(&self) {
+        let tenants = self
+            .tenants
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for tenant in tenants {
+            tenant.evaluator.on_shutdown();
+        }
+        if let Some(otherwise) = &self.otherwise {
+            otherwise.on_shutdown();
+        }
+    }
+}
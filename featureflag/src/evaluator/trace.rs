@@ -0,0 +1,74 @@
+use core::fmt;
+
+use crate::prelude::*;
+
+/// Trace of the evaluators consulted while evaluating a feature.
+///
+/// A trace is recorded by [`Evaluator::is_enabled_traced`](crate::evaluator::Evaluator::is_enabled_traced),
+/// and can be used to find out which layer of a composed evaluator produced a result.
+#[derive(Clone, Debug, Default)]
+pub struct EvaluationTrace {
+    steps: Vec<TraceStep>,
+}
+
+/// A single step in an [`EvaluationTrace`].
+#[derive(Clone, Debug)]
+pub struct TraceStep {
+    evaluator: &'static str,
+    result: Option<bool>,
+}
+
+impl EvaluationTrace {
+    /// Create a new empty trace.
+    pub const fn new() -> EvaluationTrace {
+        EvaluationTrace { steps: Vec::new() }
+    }
+
+    /// Record the result of an evaluator.
+    ///
+    /// The evaluator is usually identified by its type name.
+    pub fn record(&mut self, evaluator: &'static str, result: Option<bool>) {
+        self.steps.push(TraceStep { evaluator, result });
+    }
+
+    /// Get the recorded steps, in the order they were recorded.
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.steps
+    }
+
+    /// Get the evaluator that produced the result, if any evaluator did.
+    pub fn decided_by(&self) -> Option<&'static str> {
+        self.steps
+            .iter()
+            .rev()
+            .find(|step| step.result.is_some())
+            .map(|step| step.evaluator)
+    }
+}
+
+impl TraceStep {
+    /// Get the name of the evaluator.
+    pub fn evaluator(&self) -> &'static str {
+        self.evaluator
+    }
+
+    /// Get the result of the evaluator.
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+}
+
+impl fmt::Display for EvaluationTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            match step.result {
+                Some(result) => write!(f, "{}: {result}", step.evaluator)?,
+                None => write!(f, "{}: none", step.evaluator)?,
+            }
+        }
+        Ok(())
+    }
+}
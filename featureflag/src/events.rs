@@ -0,0 +1,316 @@
+//! This module provides [`FlagEvents`], a broadcast channel for feature flag
+//! changes.
+//!
+//! Evaluators that reload their features, e.g. from a file or a remote
+//! service, can publish [`FlagChanged`] events when a feature changes, so
+//! that other parts of the application can invalidate caches or rebuild state
+//! when the features they depend on change.
+//!
+//! Flags that are checked in tight loops can be read from an [`AtomicFlag`],
+//! which is kept up to date by the published events.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+use crate::feature::{Feature, shared_name};
+
+/// Event published when a feature changes, see [`FlagEvents`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlagChanged {
+    /// The name of the feature.
+    pub name: Arc<str>,
+    /// The previous value of the feature, or `None` if it was not set.
+    pub old: Option<bool>,
+    /// The new value of the feature, or `None` if it was removed.
+    pub new: Option<bool>,
+}
+
+/// Broadcast channel for [`FlagChanged`] events.
+///
+/// Cloning a `FlagEvents` returns a handle to the same channel. Every
+/// subscriber receives every event published after it subscribed, or only
+/// the events for selected features with
+/// [`subscribe_to`](Self::subscribe_to).
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::events::{FlagChanged, FlagEvents};
+///
+/// let events = FlagEvents::new();
+/// let subscription = events.subscribe_to(["checkout"]);
+///
+/// events.publish(FlagChanged {
+///     name: "checkout".into(),
+///     old: Some(false),
+///     new: Some(true),
+/// });
+///
+/// assert_eq!(subscription.try_recv().unwrap().new, Some(true));
+/// ```
+#[derive(Clone, Default)]
+pub struct FlagEvents {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+struct Subscriber {
+    features: Option<Vec<Arc<str>>>,
+    /// Check if the subscriber is gone.
+    is_closed: Box<dyn Fn() -> bool + Send>,
+    /// Deliver an event, returning `false` if the subscriber is gone.
+    deliver: Box<dyn Fn(&FlagChanged) -> bool + Send>,
+}
+
+impl FlagEvents {
+    /// Create a new channel without any subscribers.
+    pub fn new() -> FlagEvents {
+        FlagEvents::default()
+    }
+
+    /// Subscribe to events for all features.
+    pub fn subscribe(&self) -> Subscription {
+        self.add_subscriber(None)
+    }
+
+    /// Subscribe to events for the given features only.
+    pub fn subscribe_to<I>(&self, features: I) -> Subscription
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.add_subscriber(Some(
+            features
+                .into_iter()
+                .map(|feature| shared_name(&feature.into()))
+                .collect(),
+        ))
+    }
+
+    fn add_subscriber(&self, features: Option<Vec<Arc<str>>>) -> Subscription {
+        let (sender, receiver) = mpsc::channel();
+        let alive = Arc::new(());
+        let weak = Arc::downgrade(&alive);
+        self.push(Subscriber {
+            features,
+            is_closed: Box::new(move || weak.strong_count() == 0),
+            deliver: Box::new(move |event| sender.send(event.clone()).is_ok()),
+        });
+        Subscription {
+            receiver,
+            _alive: alive,
+        }
+    }
+
+    /// Add a subscriber, removing subscribers that are gone.
+    fn push(&self, subscriber: Subscriber) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| !(subscriber.is_closed)());
+        subscribers.push(subscriber);
+    }
+
+    /// Get the number of subscribers that have not been dropped, including
+    /// watching [`AtomicFlag`]s.
+    pub fn subscriber_count(&self) -> usize {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .iter()
+            .filter(|subscriber| !(subscriber.is_closed)())
+            .count()
+    }
+
+    /// Publish an event to all matching subscribers.
+    ///
+    /// Subscriptions that have been dropped are removed, whether or not they
+    /// match the event.
+    pub fn publish(&self, event: FlagChanged) {
+        self.subscribers.lock().unwrap().retain(|subscriber| {
+            if (subscriber.is_closed)() {
+                return false;
+            }
+            let matches = subscriber
+                .features
+                .as_ref()
+                .is_none_or(|features| features.contains(&event.name));
+            !matches || (subscriber.deliver)(&event)
+        });
+    }
+
+    /// Publish an event for every feature that differs between `old` and `new`.
+    ///
+    /// This is useful for evaluators that reload all of their features at
+    /// once. Events are published in order of feature name.
+    pub fn publish_changes(&self, old: &HashMap<String, bool>, new: &HashMap<String, bool>) {
+        let names = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+        for name in names {
+            let (old, new) = (old.get(name).copied(), new.get(name).copied());
+            if old != new {
+                self.publish(FlagChanged {
+                    name: shared_name(name),
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+}
+
+/// Subscription to [`FlagEvents`].
+///
+/// The subscription is removed when it is dropped.
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: mpsc::Receiver<FlagChanged>,
+    /// Dropped with the subscription, so it can be removed without waiting
+    /// for an event to fail to deliver.
+    _alive: Arc<()>,
+}
+
+impl Subscription {
+    /// Wait for the next event.
+    ///
+    /// Returns `None` if all handles to the channel have been dropped.
+    pub fn recv(&self) -> Option<FlagChanged> {
+        self.receiver.recv().ok()
+    }
+
+    /// Wait for the next event, for at most `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<FlagChanged> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Get the next event, if one has already been published.
+    pub fn try_recv(&self) -> Option<FlagChanged> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Iterate over the events that have already been published.
+    pub fn try_iter(&self) -> impl Iterator<Item = FlagChanged> + '_ {
+        self.receiver.try_iter()
+    }
+}
+
+/// Feature flag value that can be read with a single atomic load.
+///
+/// The flag is evaluated in the root context when it is created, and is then
+/// updated by the [`FlagChanged`] events for the feature published to the
+/// [`FlagEvents`] it watches, or when [`refresh`](Self::refresh) is called.
+/// Since it is not evaluated per context, it should only be used for flags
+/// that don't depend on the context, and that are checked too often to be
+/// evaluated each time.
+///
+/// Cloning an `AtomicFlag` returns a handle to the same value.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::{
+///     Feature,
+///     events::{AtomicFlag, FlagChanged, FlagEvents},
+/// };
+///
+/// let events = FlagEvents::new();
+/// let flag = AtomicFlag::watch(&Feature::new("fast_path", false), &events);
+/// assert!(!flag.is_enabled());
+///
+/// events.publish(FlagChanged {
+///     name: "fast_path".into(),
+///     old: None,
+///     new: Some(true),
+/// });
+/// assert!(flag.is_enabled());
+/// ```
+#[derive(Clone)]
+pub struct AtomicFlag {
+    state: Arc<AtomicFlagState>,
+}
+
+struct AtomicFlagState {
+    name: Arc<str>,
+    default: bool,
+    enabled: AtomicBool,
+}
+
+impl AtomicFlag {
+    /// Create a new flag with the current value of `feature` in the root
+    /// context.
+    ///
+    /// The default of the feature is evaluated once, and used whenever the
+    /// feature is not set.
+    pub fn new<D: Fn() -> bool>(feature: &Feature<'_, D>) -> AtomicFlag {
+        let default = feature.default_value();
+        let enabled = feature.get_state_in(None).unwrap_or(default);
+        AtomicFlag {
+            state: Arc::new(AtomicFlagState {
+                name: shared_name(feature.name()),
+                default,
+                enabled: AtomicBool::new(enabled),
+            }),
+        }
+    }
+
+    /// Create a new flag with the current value of `feature`, which is updated
+    /// by the events published to `events`.
+    ///
+    /// The flag stops watching `events` when all handles to it are dropped.
+    pub fn watch<D: Fn() -> bool>(feature: &Feature<'_, D>, events: &FlagEvents) -> AtomicFlag {
+        let flag = AtomicFlag::new(feature);
+        let state = Arc::downgrade(&flag.state);
+        let weak = state.clone();
+        events.push(Subscriber {
+            features: Some(vec![flag.state.name.clone()]),
+            is_closed: Box::new(move || weak.strong_count() == 0),
+            deliver: Box::new(move |event| {
+                Weak::upgrade(&state)
+                    .map(|state| state.apply(event))
+                    .is_some()
+            }),
+        });
+        flag
+    }
+
+    /// Get the name of the feature.
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Check if the feature is enabled.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.state.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Evaluate the feature again in the root context.
+    ///
+    /// This can be used instead of, or in addition to, watching
+    /// [`FlagEvents`].
+    pub fn refresh(&self) {
+        let enabled = Feature::new(&self.state.name, self.state.default)
+            .get_state_in(None)
+            .unwrap_or(self.state.default);
+        self.state.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+impl AtomicFlagState {
+    fn apply(&self, event: &FlagChanged) {
+        let enabled = event.new.unwrap_or(self.default);
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for AtomicFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicFlag")
+            .field("name", &self.state.name)
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
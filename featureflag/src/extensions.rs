@@ -1,38 +1,69 @@
 //! Extensions for storing custom data in [`Context`](crate::Context)s.
 
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::any::{Any, TypeId};
+#[cfg(feature = "std")]
 use std::{
-    any::{Any, TypeId},
     collections::HashMap,
     hash::{BuildHasherDefault, Hasher},
 };
 
+use crate::prelude::*;
+
 /// Type map for storing custom data in a [`Context`](crate::Context).
+///
+/// Data inserted with [`insert`](Self::insert) only belongs to one context.
+/// Data inserted with [`insert_inherited`](Self::insert_inherited) is also
+/// visible in the context's children, which share it with the parent until
+/// they insert inherited data of their own.
 pub struct Extensions {
     map: Option<AnyMap>,
+    inherited: Option<Arc<SharedMap>>,
 }
 
 impl Extensions {
     /// Create an new empty [`Extensions`] instance.
     pub const fn new() -> Extensions {
-        Extensions { map: None }
+        Extensions {
+            map: None,
+            inherited: None,
+        }
     }
 
-    /// Check if the [`Extensions`] instance contains data of the given type.
+    /// Check if the [`Extensions`] instance contains data of the given type,
+    /// including inherited data.
     pub fn has<T: Send + Sync + 'static>(&self) -> bool {
-        self.map
-            .as_ref()
-            .is_some_and(|map| map.contains_key(&TypeId::of::<T>()))
+        let id = TypeId::of::<T>();
+        self.map.as_ref().is_some_and(|map| map.contains_key(&id))
+            || self
+                .inherited
+                .as_ref()
+                .is_some_and(|map| map.contains_key(&id))
     }
 
     /// Get a reference to the data of the given type, if it exists.
+    ///
+    /// Data inserted with [`insert`](Self::insert) takes precedence over
+    /// inherited data of the same type.
     pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        let id = TypeId::of::<T>();
         self.map
-            .as_ref()?
-            .get(&TypeId::of::<T>())
+            .as_ref()
+            .and_then(|map| map.get(&id))
             .and_then(|any| any.downcast_ref::<T>())
+            .or_else(|| {
+                self.inherited
+                    .as_ref()?
+                    .get(&id)
+                    .and_then(|any| any.downcast_ref::<T>())
+            })
     }
 
     /// Get a mutable reference to the data of the given type, if it exists.
+    ///
+    /// Inherited data is shared, and can't be borrowed mutably.
     pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
         self.map
             .as_mut()?
@@ -51,9 +82,22 @@ impl Extensions {
             .map(|boxed| *boxed)
     }
 
+    /// Insert data of the given type that is inherited by child contexts.
+    ///
+    /// Child contexts share the inherited data of their parent, so evaluators
+    /// don't have to derive it again in every
+    /// [`on_new_context`](crate::evaluator::Evaluator::on_new_context). The
+    /// shared data is only copied when a child inserts inherited data of its
+    /// own, and the copy is not visible to the parent.
+    pub fn insert_inherited<T: Send + Sync + 'static>(&mut self, value: T) {
+        Arc::make_mut(self.inherited.get_or_insert_default())
+            .insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
     /// Remove data of the given type from the [`Extensions`] instance.
     ///
     /// If data of the given type exists, it will be removed and returned.
+    /// Inherited data is not removed.
     pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
         self.map
             .as_mut()?
@@ -61,6 +105,39 @@ impl Extensions {
             .and_then(|any| any.downcast().ok())
             .map(|boxed| *boxed)
     }
+
+    /// Check if the [`Extensions`] instance contains no data, including
+    /// inherited data.
+    pub fn is_empty(&self) -> bool {
+        self.map.as_ref().is_none_or(|map| map.is_empty())
+            && self.inherited.as_ref().is_none_or(|map| map.is_empty())
+    }
+
+    /// Share the inherited data of `parent`, keeping any inherited data that
+    /// was already inserted.
+    pub(crate) fn inherit_from(&mut self, parent: &Extensions) {
+        let Some(parent) = &parent.inherited else {
+            return;
+        };
+        match &mut self.inherited {
+            Some(own) => {
+                let own = Arc::make_mut(own);
+                for (id, value) in parent.iter() {
+                    own.entry(*id).or_insert_with(|| value.clone());
+                }
+            }
+            None => self.inherited = Some(parent.clone()),
+        }
+    }
+
+    /// Check if this only contains the data inherited from `parent`.
+    pub(crate) fn is_inherited_only(&self, parent: &Extensions) -> bool {
+        self.map.as_ref().is_none_or(|map| map.is_empty())
+            && match (&self.inherited, &parent.inherited) {
+                (Some(own), Some(parent)) => Arc::ptr_eq(own, parent),
+                (own, _) => own.as_ref().is_none_or(|map| map.is_empty()),
+            }
+    }
 }
 
 impl Default for Extensions {
@@ -69,11 +146,19 @@ impl Default for Extensions {
     }
 }
 
-type AnyMap = HashMap<TypeId, Box<dyn Any + Send + Sync>, BuildHasherDefault<IdHasher>>;
+#[cfg(feature = "std")]
+type TypeMap<V> = HashMap<TypeId, V, BuildHasherDefault<IdHasher>>;
+#[cfg(not(feature = "std"))]
+type TypeMap<V> = BTreeMap<TypeId, V>;
+
+type AnyMap = TypeMap<Box<dyn Any + Send + Sync>>;
+type SharedMap = TypeMap<Arc<dyn Any + Send + Sync>>;
 
+#[cfg(feature = "std")]
 #[derive(Debug, Default)]
 struct IdHasher(u64);
 
+#[cfg(feature = "std")]
 impl Hasher for IdHasher {
     fn finish(&self) -> u64 {
         self.0
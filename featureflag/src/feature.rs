@@ -1,9 +1,20 @@
 //! Feature flags.
 
 #[cfg(feature = "feature-registry")]
-use std::{collections::HashSet, sync::LazyLock};
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Arc, LazyLock, OnceLock, RwLock},
+};
 
-use crate::{context::Context, evaluator::Evaluator};
+#[cfg(feature = "std")]
+use crate::diagnostics;
+use crate::{
+    context::Context,
+    evaluator::{EvaluationTrace, Evaluator},
+};
 
 /// Feature flag definition.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -43,14 +54,39 @@ impl<'a, D: Fn() -> bool> Feature<'a, D> {
         self.name
     }
 
+    /// Evaluate the default value of the feature.
+    ///
+    /// The [default provider](crate::defaults) takes precedence over the
+    /// default function of the feature.
+    pub(crate) fn default_value(&self) -> bool {
+        #[cfg(feature = "std")]
+        if let Some(default) = crate::defaults::default_for(self.name) {
+            return default;
+        }
+        (self.default_fn)()
+    }
+
     /// Get the state of the feature in the given context.
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
     pub fn get_state_in(&self, context: Option<&Context>) -> Option<bool> {
+        let state = self.evaluate_state(context);
+        #[cfg(feature = "std")]
+        diagnostics::record_usage(self.name, state, None);
+        state
+    }
+
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
+    fn evaluate_state(&self, context: Option<&Context>) -> Option<bool> {
+        #[cfg(feature = "std")]
+        diagnostics::record_evaluation(self.name);
+
         let context = context.unwrap_or(const { &Context::root() });
-        context.evaluator()?.is_enabled(self.name, context)
+        context.with_evaluator(|evaluator| evaluator?.is_enabled(self.name, context))
     }
 
     /// Get the state of the feature in the current context.
     #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
     pub fn get_state(&self) -> Option<bool> {
         self.get_state_in(Context::current().as_ref())
     }
@@ -58,8 +94,9 @@ impl<'a, D: Fn() -> bool> Feature<'a, D> {
     /// Check if the feature is enabled in the current context.
     ///
     /// If the current evaluator returns `None` for the feature, the default
-    /// of this feature is used.
+    /// of this feature is used, see [`defaults`](crate::defaults).
     #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
     pub fn is_enabled(&self) -> bool {
         self.is_enabled_in(Context::current().as_ref())
     }
@@ -67,11 +104,309 @@ impl<'a, D: Fn() -> bool> Feature<'a, D> {
     /// Check if the feature is enabled in the given context.
     ///
     /// If the context's evaluator returns `None` for the feature, the default
-    /// of this feature is used.
+    /// of this feature is used, see [`defaults`](crate::defaults).
     #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
     pub fn is_enabled_in(&self, context: Option<&Context>) -> bool {
-        self.get_state_in(context)
-            .unwrap_or_else(|| (self.default_fn)())
+        let state = self.evaluate_state(context);
+        let enabled = state.unwrap_or_else(|| self.default_value());
+        #[cfg(feature = "std")]
+        diagnostics::record_usage(self.name, state, Some(enabled));
+        enabled
+    }
+
+    /// Map whether the feature is enabled in the current context to a value.
+    ///
+    /// ```
+    /// let timeout = featureflag::feature!("fast_timeout", false).map(|enabled| {
+    ///     if enabled { 100 } else { 1000 }
+    /// });
+    /// assert_eq!(timeout, 1000);
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
+    pub fn map<T, F: FnOnce(bool) -> T>(&self, f: F) -> T {
+        f(self.is_enabled())
+    }
+
+    /// Map whether the feature is enabled in the given context to a value.
+    ///
+    /// See [`map`](Self::map) for more details.
+    #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
+    pub fn map_in<T, F: FnOnce(bool) -> T>(&self, context: Option<&Context>, f: F) -> T {
+        f(self.is_enabled_in(context))
+    }
+
+    /// Create a typed accessor for the feature, see [`TypedFeature`].
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub const fn typed<T, F: Fn(bool) -> T>(self, parse: F) -> TypedFeature<'a, T, D, F> {
+        TypedFeature::new(self, parse)
+    }
+
+    /// Evaluate the feature in the current context, returning details about
+    /// the evaluation.
+    ///
+    /// This is slower than [`is_enabled`](Self::is_enabled), and is intended for
+    /// debugging why a feature is enabled or disabled.
+    #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
+    pub fn evaluate(&self) -> EvaluationDetail<'a> {
+        self.evaluate_in(Context::current().as_ref())
+    }
+
+    /// Evaluate the feature in the given context, returning details about the
+    /// evaluation.
+    ///
+    /// See [`evaluate`](Self::evaluate) for more details.
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
+    pub fn evaluate_in(&self, context: Option<&Context>) -> EvaluationDetail<'a> {
+        #[cfg(feature = "std")]
+        diagnostics::record_evaluation(self.name);
+
+        let context = context.unwrap_or(const { &Context::root() });
+        let mut trace = EvaluationTrace::new();
+        let state = context.with_evaluator(|evaluator| {
+            evaluator?.is_enabled_traced(self.name, context, &mut trace)
+        });
+
+        let enabled = state.unwrap_or_else(|| self.default_value());
+        #[cfg(feature = "std")]
+        diagnostics::record_usage(self.name, state, Some(enabled));
+
+        EvaluationDetail {
+            feature: self.name,
+            state,
+            enabled,
+            trace,
+        }
+    }
+}
+
+/// Typed accessor for a feature flag.
+///
+/// The value of each state of the feature is computed by `parse` the first
+/// time it is needed, and cached, so call sites get a value of type `T`
+/// without repeating the conversion. This is useful for flags that select
+/// between two configurations, like timeouts or limits.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use featureflag::feature::TypedFeature;
+///
+/// static TIMEOUT: TypedFeature<Duration> =
+///     TypedFeature::new(featureflag::feature!("fast_timeout", false), |enabled| {
+///         Duration::from_millis(if enabled { 100 } else { 1000 })
+///     });
+///
+/// assert_eq!(*TIMEOUT.get(), Duration::from_secs(1));
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub struct TypedFeature<'a, T, D = fn() -> bool, F = fn(bool) -> T> {
+    feature: Feature<'a, D>,
+    parse: F,
+    values: [OnceLock<T>; 2],
+}
+
+#[cfg(feature = "std")]
+impl<'a, T, D: Fn() -> bool, F: Fn(bool) -> T> TypedFeature<'a, T, D, F> {
+    /// Create a typed accessor for `feature`, computing its values with `parse`.
+    pub const fn new(feature: Feature<'a, D>, parse: F) -> TypedFeature<'a, T, D, F> {
+        TypedFeature {
+            feature,
+            parse,
+            values: [OnceLock::new(), OnceLock::new()],
+        }
+    }
+
+    /// Get the underlying feature.
+    pub const fn feature(&self) -> &Feature<'a, D> {
+        &self.feature
+    }
+
+    /// Get the value of the feature in the current context.
+    #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
+    pub fn get(&self) -> &T {
+        self.value(self.feature.is_enabled())
+    }
+
+    /// Get the value of the feature in the given context.
+    #[inline]
+    #[cfg_attr(feature = "callsite-tracking", track_caller)]
+    pub fn get_in(&self, context: Option<&Context>) -> &T {
+        self.value(self.feature.is_enabled_in(context))
+    }
+
+    fn value(&self, enabled: bool) -> &T {
+        self.values[enabled as usize].get_or_init(|| (self.parse)(enabled))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug, D, F> fmt::Debug for TypedFeature<'_, T, D, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedFeature")
+            .field("feature", &self.feature.name)
+            .field("values", &self.values)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Details about the evaluation of a feature, see [`Feature::evaluate`].
+#[derive(Clone, Debug)]
+pub struct EvaluationDetail<'a> {
+    feature: &'a str,
+    state: Option<bool>,
+    enabled: bool,
+    trace: EvaluationTrace,
+}
+
+impl<'a> EvaluationDetail<'a> {
+    /// Get the name of the feature.
+    pub fn feature(&self) -> &'a str {
+        self.feature
+    }
+
+    /// Get the state returned by the evaluator.
+    pub fn state(&self) -> Option<bool> {
+        self.state
+    }
+
+    /// Check if the feature is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Check if the default value of the feature was used.
+    pub fn is_default(&self) -> bool {
+        self.state.is_none()
+    }
+
+    /// Get the trace of the evaluators consulted.
+    pub fn trace(&self) -> &EvaluationTrace {
+        &self.trace
+    }
+}
+
+/// Naming convention for feature names.
+///
+/// Names consist of one or more segments separated by `.`, so namespaced names
+/// like `checkout.new-flow` are allowed. Each segment must be non-empty, and
+/// start with a lowercase ASCII letter.
+///
+/// The [`feature!`] and [`is_enabled!`] macros check literal names against the
+/// conventions enabled with the `kebab-case-names` and `snake-case-names` crate
+/// features, and fail to compile if a name doesn't match any of them. When
+/// neither feature is enabled, names are not checked. Namespaces passed with
+/// `ns:` are checked separately, see [`matches_namespace`](Self::matches_namespace).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NameStyle {
+    /// Lowercase ASCII letters and digits, with words separated by `-`.
+    KebabCase,
+    /// Lowercase ASCII letters and digits, with words separated by `_`.
+    SnakeCase,
+}
+
+impl NameStyle {
+    /// Check if a feature name matches this naming convention.
+    pub const fn matches(&self, name: &str) -> bool {
+        self.matches_bytes(name.as_bytes())
+    }
+
+    /// Check if a namespace matches this naming convention.
+    ///
+    /// Namespaces consist of one or more segments separated by `::`, like
+    /// `my_crate::ui` from `module_path!()`, and each segment must match the
+    /// convention like a feature name.
+    pub const fn matches_namespace(&self, namespace: &str) -> bool {
+        let mut rest = namespace.as_bytes();
+        loop {
+            let mut i = 0;
+            while i + 1 < rest.len() && !(rest[i] == b':' && rest[i + 1] == b':') {
+                i += 1;
+            }
+            if i + 1 >= rest.len() {
+                return self.matches_bytes(rest);
+            }
+
+            let (segment, tail) = rest.split_at(i);
+            if !self.matches_bytes(segment) {
+                return false;
+            }
+            rest = tail.split_at(2).1;
+        }
+    }
+
+    const fn matches_bytes(&self, name: &[u8]) -> bool {
+        let separator = match self {
+            NameStyle::KebabCase => b'-',
+            NameStyle::SnakeCase => b'_',
+        };
+
+        let mut segment_start = true;
+        let mut word_start = true;
+        let mut i = 0;
+        while i < name.len() {
+            let c = name[i];
+            if c == b'.' || c == separator {
+                if word_start {
+                    return false;
+                }
+                segment_start = c == b'.';
+                word_start = true;
+            } else if c.is_ascii_lowercase() || (c.is_ascii_digit() && !segment_start) {
+                segment_start = false;
+                word_start = false;
+            } else {
+                return false;
+            }
+            i += 1;
+        }
+        !word_start
+    }
+}
+
+/// Naming conventions enabled with crate features.
+const NAME_STYLES: &[NameStyle] = &[
+    #[cfg(feature = "kebab-case-names")]
+    NameStyle::KebabCase,
+    #[cfg(feature = "snake-case-names")]
+    NameStyle::SnakeCase,
+];
+
+#[doc(hidden)]
+pub const fn __validate_name(name: &str) {
+    let mut i = 0;
+    while i < NAME_STYLES.len() {
+        if NAME_STYLES[i].matches(name) {
+            return;
+        }
+        i += 1;
+    }
+
+    if !NAME_STYLES.is_empty() {
+        panic!("feature name does not match the configured naming convention");
+    }
+}
+
+#[doc(hidden)]
+pub const fn __validate_namespace(namespace: &str) {
+    let mut i = 0;
+    while i < NAME_STYLES.len() {
+        if NAME_STYLES[i].matches_namespace(namespace) {
+            return;
+        }
+        i += 1;
+    }
+
+    if !NAME_STYLES.is_empty() {
+        panic!("feature namespace does not match the configured naming convention");
     }
 }
 
@@ -79,9 +414,16 @@ impl<'a, D: Fn() -> bool> Feature<'a, D> {
 #[macro_export]
 #[doc(hidden)]
 macro_rules! __register_feature {
-    ($name:literal) => {
+    ($name:expr, $default:expr $(, $key:ident = $value:expr)*) => {
         $crate::__reexport::inventory::submit! {
-            $crate::feature::RegisteredFeature($name)
+            $crate::registry::RegisteredFeature($crate::registry::FeatureInfo::__new(
+                $name,
+                ::core::stringify!($default),
+                ::core::module_path!(),
+                ::core::file!(),
+                ::core::line!(),
+                &[$((::core::stringify!($key), $value)),*],
+            ))
         }
     };
 }
@@ -90,7 +432,7 @@ macro_rules! __register_feature {
 #[macro_export]
 #[doc(hidden)]
 macro_rules! __register_feature {
-    ($name:literal) => {};
+    ($name:expr, $default:expr $(, $key:ident = $value:expr)*) => {};
 }
 
 /// Define a feature flag at compile-time.
@@ -100,11 +442,56 @@ macro_rules! __register_feature {
 /// its default value.
 ///
 /// If the `feature-registry` feature is enabled, the feature will be registered
-/// globally and can be accessed using the [`known_features`] function.
+/// globally and can be accessed using the [`known_features`] function, or with
+/// its default and location using [`registered_features`]. Additional metadata
+/// can be registered with `key = "value"` pairs after the default, like
+/// `feature!("new_ui", false, owner = "checkout")`.
+///
+/// [`registered_features`]: crate::registry::registered_features
+///
+/// Literal names are checked against the enabled [`NameStyle`] conventions at
+/// compile-time.
+///
+/// The name can also be any `&str` expression, like a name loaded from
+/// configuration or built with `concat!`. Such features are not registered,
+/// since their names are not known at compile-time.
+///
+/// The name can be prefixed with a namespace by passing `ns: <namespace>`,
+/// which must be a string literal or a macro like `module_path!()`. The
+/// namespace and name are joined with `::`, so
+/// `feature!(ns: module_path!(), "new_ui", false)` in the module `my_crate::ui`
+/// defines the feature `my_crate::ui::new_ui`. The namespace and name are
+/// checked separately, and each `::`-separated segment of the namespace must
+/// match a naming convention, like the name.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::feature;
+///
+/// const NEW_UI: featureflag::Feature = feature!(ns: "checkout", "new_ui", false);
+/// assert_eq!(NEW_UI.name(), "checkout::new_ui");
+/// ```
 #[macro_export]
 macro_rules! feature {
-    ($name:literal, $default:expr $(,)?) => {{
-        $crate::__register_feature!($name);
+    (ns: $ns:expr, $name:literal, $default:expr $(, $key:ident = $value:expr)* $(,)?) => {{
+        const _: () = $crate::feature::__validate_namespace($ns);
+        const _: () = $crate::feature::__validate_name($name);
+        $crate::__register_feature!(::core::concat!($ns, "::", $name), $default $(, $key = $value)*);
+        $crate::feature::Feature::new_with_default_fn(
+            ::core::concat!($ns, "::", $name),
+            || $default,
+        )
+    }};
+
+    (ns: $ns:expr, $name:literal $(,)?) => {{
+        compile_error!("missing default value for feature");
+        $crate::feature!(ns: $ns, $name, false)
+    }};
+
+    ($name:literal, $default:expr $(, $key:ident = $value:expr)* $(,)?) => {{
+        const _: () = $crate::feature::__validate_name($name);
+        $crate::__register_feature!($name, $default $(, $key = $value)*);
         $crate::feature::Feature::new_with_default_fn($name, || $default)
     }};
 
@@ -112,6 +499,15 @@ macro_rules! feature {
         compile_error!("missing default value for feature");
         $crate::feature!($name, false)
     }};
+
+    ($name:expr, $default:expr $(,)?) => {
+        $crate::feature::Feature::new_with_default_fn($name, || $default)
+    };
+
+    ($name:expr $(,)?) => {{
+        compile_error!("missing default value for feature");
+        $crate::feature!($name, false)
+    }};
 }
 
 /// Check if a feature is enabled.
@@ -120,17 +516,40 @@ macro_rules! feature {
 ///
 /// A context can be passed to use instead of the current context, by passing
 /// `is_enabled!(context: some_context, "feature", default)`.
+///
+/// Like with [`feature!`], the name can be any `&str` expression, and can be
+/// namespaced with `is_enabled!(ns: module_path!(), "feature", default)`.
 #[macro_export]
 macro_rules! is_enabled {
+    (context: $context:expr, ns: $ns:expr, $feature:literal $(, $default:expr)? $(,)?) => {
+        $crate::feature!(ns: $ns, $feature $(, $default)?).is_enabled_in(
+            $crate::context::AsContextParam::as_context_param(&$context)
+        )
+    };
+
     (context: $context:expr, $feature:literal $(, $default:expr)? $(,)?) => {
         $crate::feature!($feature $(, $default)?).is_enabled_in(
             $crate::context::AsContextParam::as_context_param(&$context)
         )
     };
 
+    (context: $context:expr, $feature:expr $(, $default:expr)? $(,)?) => {
+        $crate::feature!($feature $(, $default)?).is_enabled_in(
+            $crate::context::AsContextParam::as_context_param(&$context)
+        )
+    };
+
+    (ns: $ns:expr, $feature:literal $(, $default:expr)? $(,)?) => {
+        $crate::feature!(ns: $ns, $feature $(, $default)?).is_enabled()
+    };
+
     ($feature:literal $(, $default:expr)? $(,)?) => {
         $crate::feature!($feature $(, $default)?).is_enabled()
     };
+
+    ($feature:expr $(, $default:expr)? $(,)?) => {
+        $crate::feature!($feature $(, $default)?).is_enabled()
+    };
 }
 
 // Allow references from doc comments before the macro definition.
@@ -139,19 +558,82 @@ use crate::{feature, is_enabled};
 
 #[cfg(feature = "feature-registry")]
 #[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
-/// Get all feature flags registered with [`feature!`] or [`is_enabled!`].
-pub fn known_features() -> &'static HashSet<&'static str> {
-    static CACHED: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
-        inventory::iter::<RegisteredFeature>()
-            .map(|feature| feature.0)
-            .collect()
-    });
-    &CACHED
+/// Get the names of all feature flags registered with [`feature!`] or
+/// [`is_enabled!`].
+///
+///
+/// Features registered at runtime with
+/// [`register_dynamic`](crate::registry::register_dynamic) are also included.
+///
+/// See [`registered_features`](crate::registry::registered_features) for more
+/// information about each registered feature.
+pub fn known_features() -> HashSet<&'static str> {
+    crate::registry::registered_features()
+        .iter()
+        .chain(&crate::registry::dynamic_features())
+        .map(|feature| feature.name())
+        .collect()
 }
 
+/// Shared names of the features registered at compile-time, see [`shared_name`].
 #[cfg(feature = "feature-registry")]
-#[doc(hidden)]
-pub struct RegisteredFeature(pub &'static str);
+static REGISTERED_NAMES: LazyLock<HashMap<&'static str, Arc<str>>> = LazyLock::new(|| {
+    crate::registry::registered_features()
+        .iter()
+        .map(|info| (info.name(), Arc::from(info.name())))
+        .collect()
+});
 
-#[cfg(feature = "feature-registry")]
-inventory::collect!(RegisteredFeature);
+/// Get a shared feature name, for storing in events and records.
+///
+/// Names of features registered at compile-time are shared without locking or
+/// allocating. Other names may come from an unbounded set at runtime, so unlike
+/// with [`intern`], they are allocated each time and freed with their last
+/// reference.
+#[cfg(feature = "std")]
+pub(crate) fn shared_name(name: &str) -> Arc<str> {
+    #[cfg(feature = "feature-registry")]
+    if let Some(name) = REGISTERED_NAMES.get(name) {
+        return name.clone();
+    }
+    Arc::from(name)
+}
+
+#[cfg(feature = "std")]
+static INTERNED_NAMES: LazyLock<RwLock<HashSet<Arc<str>>>> = LazyLock::new(Default::default);
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+/// Intern a feature name.
+///
+/// Interning the same name again returns the same shared string without
+/// allocating, so events that store feature names, like
+/// [`ExposureEvent`](crate::evaluator::ExposureEvent) and
+/// [`FlagChanged`](crate::events::FlagChanged), can be created and compared
+/// cheaply.
+///
+/// Interned names are never freed, so this should only be used for names from
+/// a bounded set, like the features used in code.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use featureflag::feature::intern;
+///
+/// assert!(Arc::ptr_eq(&intern("checkout"), &intern("checkout")));
+/// ```
+pub fn intern(name: &str) -> Arc<str> {
+    if let Some(name) = INTERNED_NAMES.read().unwrap().get(name) {
+        return name.clone();
+    }
+
+    let mut names = INTERNED_NAMES.write().unwrap();
+    if let Some(name) = names.get(name) {
+        return name.clone();
+    }
+    let name = Arc::<str>::from(name);
+    names.insert(name.clone());
+    name
+}
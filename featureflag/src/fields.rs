@@ -1,8 +1,10 @@
 //! `Fields` struct and macro for creating a collection of fields for use
 //! in [`context!`](macro@crate::context).
 
-use std::fmt;
+use alloc::borrow::Cow;
+use core::{fmt, mem};
 
+use crate::prelude::*;
 use crate::value::Value;
 
 /// A struct representing a collection of fields.
@@ -28,6 +30,208 @@ impl<'a> Fields<'a> {
     pub fn get(&self, key: &str) -> Option<&'a Value<'a>> {
         self.fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
     }
+
+    /// Get a field by a dotted path, such as `"user.id"`.
+    ///
+    /// Keys containing dots are treated as flattened nested maps, so a field
+    /// with the key `"user.id"` is found by the path `"user.id"`. If no key
+    /// matches the whole path, the longest key matching a prefix of the path is
+    /// used, and the rest of the path is looked up with [`Value::get_path`].
+    pub fn get_path(&self, path: &str) -> Option<&'a Value<'a>> {
+        get_path(self.fields.iter().map(|(k, v)| (*k, v)), path)
+    }
+
+    /// Get the number of fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Check if there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// An owned collection of fields.
+///
+/// Unlike [`Fields`], which borrows its fields, this struct owns its keys and
+/// values, so it can be stored by evaluators beyond the lifetime of the
+/// original fields. Keys that are `&'static str` are stored without copying.
+///
+/// Up to four fields are stored inline, without allocating a separate buffer.
+#[derive(Clone, Default)]
+pub struct FieldsBuf {
+    fields: FieldStorage,
+}
+
+impl FieldsBuf {
+    /// Creates a new empty `FieldsBuf`.
+    pub fn new() -> FieldsBuf {
+        FieldsBuf {
+            fields: FieldStorage::new(),
+        }
+    }
+
+    /// Add a field.
+    ///
+    /// If a field with the same key already exists, it is replaced.
+    pub fn insert(&mut self, key: impl Into<Cow<'static, str>>, value: Value<'_>) {
+        let key = key.into();
+        let value = value.into_static();
+        match self.fields.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.fields.push((key, value)),
+        }
+    }
+
+    /// Iterate over the fields.
+    pub fn pairs(&self) -> impl '_ + Iterator<Item = (&str, &Value<'static>)> {
+        self.fields.iter().map(|(k, v)| (k.as_ref(), v))
+    }
+
+    /// Get the fields with their keys as stored.
+    #[cfg(feature = "std")]
+    pub(crate) fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Get a field by its key.
+    pub fn get(&self, key: &str) -> Option<&Value<'static>> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Get a field by a dotted path, such as `"user.id"`.
+    ///
+    /// See [`Fields::get_path`] for details.
+    pub fn get_path(&self, path: &str) -> Option<&Value<'static>> {
+        get_path(self.fields.iter().map(|(k, v)| (k.as_ref(), v)), path)
+    }
+
+    /// Get the number of fields.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Check if there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl From<Fields<'_>> for FieldsBuf {
+    fn from(fields: Fields<'_>) -> FieldsBuf {
+        let mut storage = FieldStorage::new();
+        for (k, v) in fields.pairs() {
+            storage.push((Cow::Owned(k.to_string()), v.to_static()));
+        }
+        FieldsBuf { fields: storage }
+    }
+}
+
+/// Number of fields stored inline in a [`FieldsBuf`].
+const INLINE_FIELDS: usize = 4;
+
+pub(crate) type Field = (Cow<'static, str>, Value<'static>);
+
+/// Field storage of a [`FieldsBuf`], which only allocates a buffer when there
+/// are more than [`INLINE_FIELDS`] fields.
+///
+/// Unused inline slots hold an empty key and a null value, which don't
+/// allocate.
+#[derive(Clone)]
+#[allow(clippy::large_enum_variant)] // storing the fields inline is the point
+enum FieldStorage {
+    Inline {
+        len: usize,
+        fields: [Field; INLINE_FIELDS],
+    },
+    Heap(Vec<Field>),
+}
+
+impl FieldStorage {
+    const EMPTY: Field = (Cow::Borrowed(""), Value::Null);
+
+    fn new() -> FieldStorage {
+        FieldStorage::Inline {
+            len: 0,
+            fields: [FieldStorage::EMPTY; INLINE_FIELDS],
+        }
+    }
+
+    fn push(&mut self, field: Field) {
+        match self {
+            FieldStorage::Inline { len, fields } if *len < INLINE_FIELDS => {
+                fields[*len] = field;
+                *len += 1;
+            }
+            FieldStorage::Inline { fields, .. } => {
+                let mut heap = Vec::with_capacity(INLINE_FIELDS * 2);
+                heap.extend(
+                    fields
+                        .iter_mut()
+                        .map(|field| mem::replace(field, FieldStorage::EMPTY)),
+                );
+                heap.push(field);
+                *self = FieldStorage::Heap(heap);
+            }
+            FieldStorage::Heap(heap) => heap.push(field),
+        }
+    }
+}
+
+impl Default for FieldStorage {
+    fn default() -> FieldStorage {
+        FieldStorage::new()
+    }
+}
+
+impl core::ops::Deref for FieldStorage {
+    type Target = [Field];
+
+    fn deref(&self) -> &[Field] {
+        match self {
+            FieldStorage::Inline { len, fields } => &fields[..*len],
+            FieldStorage::Heap(heap) => heap,
+        }
+    }
+}
+
+impl core::ops::DerefMut for FieldStorage {
+    fn deref_mut(&mut self) -> &mut [Field] {
+        match self {
+            FieldStorage::Inline { len, fields } => &mut fields[..*len],
+            FieldStorage::Heap(heap) => heap,
+        }
+    }
+}
+
+impl fmt::Debug for FieldsBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.fields.iter().map(|(k, v)| (k, v)))
+            .finish()
+    }
+}
+
+/// Look up a dotted path in a set of fields, preferring the longest matching key.
+pub(crate) fn get_path<'v, 'a: 'v, I>(pairs: I, path: &str) -> Option<&'v Value<'a>>
+where
+    I: Clone + Iterator<Item = (&'v str, &'v Value<'a>)>,
+{
+    let mut key = path;
+    loop {
+        let rest = path[key.len()..].strip_prefix('.').unwrap_or("");
+        if let Some(value) = pairs
+            .clone()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, value)| value.get_path(rest))
+        {
+            return Some(value);
+        }
+
+        let (prefix, _) = key.rsplit_once('.')?;
+        key = prefix;
+    }
 }
 
 impl fmt::Debug for Fields<'_> {
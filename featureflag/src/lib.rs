@@ -15,22 +15,69 @@
 //! macro can be used to store a [`Feature`] is a variable or constant, or the
 //! [`Feature::new`] or [`Feature::new_with_default_fn`] methods can be used
 //! directly to create new feature flags at runtime.
+//!
+//! # `no_std` support
+//!
+//! The crate can be used without the standard library by disabling the default
+//! `std` feature, as long as the `alloc` crate is available. Without `std`,
+//! only the global evaluator can be set, and there is no current context, so
+//! contexts must be passed explicitly, e.g. with [`Feature::is_enabled_in`].
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 pub mod context;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod defaults;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod diagnostics;
 pub mod evaluator;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod events;
 pub mod extensions;
 pub mod feature;
 pub mod fields;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod propagation;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub mod refresh;
+#[cfg(feature = "feature-registry")]
+#[cfg_attr(docsrs, doc(cfg(feature = "feature-registry")))]
+pub mod registry;
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 pub mod utils;
 pub mod value;
 
+/// Items from `alloc` that are not in the prelude without `std`.
+#[allow(unused_imports)]
+mod prelude {
+    pub(crate) use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+}
+
 pub use crate::{
     context::Context,
-    evaluator::{Evaluator, set_global_default, try_set_global_default},
+    evaluator::{Evaluator, set_global_default, shutdown, try_set_global_default},
     feature::Feature,
 };
 
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use featureflag_macros::{FeatureFlags, feature_gated, flags_from_file};
+
 #[doc(hidden)]
 pub mod __reexport {
 
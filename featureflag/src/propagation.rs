@@ -0,0 +1,386 @@
+//! This module provides helpers for propagating context fields through
+//! message headers.
+//!
+//! Context fields are not stored in contexts, so they are captured by the
+//! [`Propagate`] evaluator when a context is created. [`inject`] writes the
+//! captured fields of a context to the headers of an outgoing message, and
+//! [`extract`] or [`context_from_headers`] restores them in the consumer, so
+//! that processing the message uses the same targeting as the original
+//! request.
+//!
+//! A [`ContextSnapshot`] holds the captured fields of a context without
+//! keeping the context alive, so it can be logged, attached to events, or sent
+//! to another thread and restored there with [`ContextSnapshot::to_context`].
+//!
+//! Headers are accessed through the [`HeaderInjector`] and [`HeaderExtractor`]
+//! traits, which can be implemented for the header types of any message queue
+//! client.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! use featureflag::{
+//!     context,
+//!     evaluator::{NoEvaluator, with_default},
+//!     propagation::{Propagate, context_from_headers, inject},
+//! };
+//!
+//! with_default(Propagate::new(NoEvaluator), || {
+//!     let mut headers = HashMap::new();
+//!     inject(&context!(user_id = 42u64), &mut headers);
+//!
+//!     let context = context_from_headers(None, &headers);
+//! });
+//! ```
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use crate::{
+    context::{Context, ContextId, ContextRef},
+    evaluator::{EvaluationTrace, Evaluator, EvaluatorDescription, Health, RegistrationInfo},
+    extensions::Extensions,
+    fields::{Fields, FieldsBuf},
+    value::Value,
+};
+
+/// Prefix of the headers written by [`inject`].
+pub const HEADER_PREFIX: &str = "featureflag-";
+
+/// Headers of an outgoing message, see [`inject`].
+pub trait HeaderInjector {
+    /// Set a header.
+    fn set(&mut self, key: &str, value: String);
+}
+
+/// Headers of an incoming message, see [`extract`].
+pub trait HeaderExtractor {
+    /// Get a header by its key.
+    fn get(&self, key: &str) -> Option<&str>;
+
+    /// Get the keys of all headers.
+    fn keys(&self) -> Vec<&str>;
+}
+
+impl HeaderInjector for HashMap<String, String> {
+    fn set(&mut self, key: &str, value: String) {
+        self.insert(key.to_string(), value);
+    }
+}
+
+impl HeaderExtractor for HashMap<String, String> {
+    fn get(&self, key: &str) -> Option<&str> {
+        HashMap::get(self, key).map(String::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        HashMap::keys(self).map(String::as_str).collect()
+    }
+}
+
+impl HeaderInjector for Vec<(String, String)> {
+    fn set(&mut self, key: &str, value: String) {
+        self.retain(|(k, _)| k != key);
+        self.push((key.to_string(), value));
+    }
+}
+
+impl HeaderExtractor for Vec<(String, String)> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.iter().map(|(key, _)| key.as_str()).collect()
+    }
+}
+
+/// Evaluator that captures context fields for propagation.
+///
+/// By default all fields are captured. Use [`with_fields`](Self::with_fields)
+/// to only capture selected fields.
+pub struct Propagate<E> {
+    evaluator: E,
+    fields: Option<Vec<String>>,
+}
+
+/// Extension storing the captured fields of a context.
+struct PropagatedFields(FieldsBuf);
+
+impl<E> Propagate<E> {
+    /// Create a new evaluator capturing the context fields of `evaluator`.
+    pub fn new(evaluator: E) -> Propagate<E> {
+        Propagate {
+            evaluator,
+            fields: None,
+        }
+    }
+
+    /// Only capture the selected fields.
+    pub fn with_fields<I>(mut self, fields: I) -> Propagate<E>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.fields
+            .get_or_insert_with(Vec::new)
+            .extend(fields.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl<E: Evaluator> Evaluator for Propagate<E> {
+    fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+        self.evaluator.is_enabled(feature, context)
+    }
+
+    fn is_enabled_traced(
+        &self,
+        feature: &str,
+        context: &Context,
+        trace: &mut EvaluationTrace,
+    ) -> Option<bool> {
+        self.evaluator.is_enabled_traced(feature, context, trace)
+    }
+
+    fn describe(&self) -> EvaluatorDescription {
+        let mut description = EvaluatorDescription::new("Propagate");
+        if let Some(fields) = &self.fields {
+            description = description.with_summary(format!("fields {fields:?}"));
+        }
+        description.with_child(self.evaluator.describe())
+    }
+
+    fn known_flags(&self) -> Option<Vec<String>> {
+        self.evaluator.known_flags()
+    }
+
+    fn health(&self) -> Health {
+        self.evaluator.health()
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.evaluator.on_registration(info)
+    }
+
+    fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+        let mut captured = FieldsBuf::new();
+        for (key, value) in fields.pairs() {
+            if self
+                .fields
+                .as_ref()
+                .is_none_or(|selected| selected.iter().any(|k| k == key))
+            {
+                captured.insert(key.to_string(), value.clone());
+            }
+        }
+        if !captured.is_empty() {
+            context.extensions_mut().insert(PropagatedFields(captured));
+        }
+
+        self.evaluator.on_new_context(context, fields)
+    }
+
+    fn on_close_context(&self, context: ContextRef<'_>) {
+        self.evaluator.on_close_context(context)
+    }
+
+    fn on_shutdown(&self) {
+        self.evaluator.on_shutdown()
+    }
+}
+
+/// Get the fields of a context and its parents captured by [`Propagate`].
+///
+/// Fields of a context take precedence over fields with the same key in its
+/// parents.
+pub fn captured_fields(context: &Context) -> FieldsBuf {
+    let mut fields = FieldsBuf::new();
+    for context in context.iter() {
+        if let Some(captured) = context.extensions().get::<PropagatedFields>() {
+            for (key, value) in captured.0.fields() {
+                if fields.get(key).is_none() {
+                    fields.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// An immutable snapshot of the captured fields of a context, see [`snapshot`].
+///
+/// Unlike a [`Context`], a snapshot doesn't keep the context or its evaluator
+/// alive. Cloning a snapshot is cheap, since the fields are shared.
+#[derive(Clone, Debug)]
+pub struct ContextSnapshot {
+    id: ContextId,
+    fields: Arc<FieldsBuf>,
+}
+
+impl ContextSnapshot {
+    /// Get the identifier of the context the snapshot was taken of.
+    pub fn id(&self) -> ContextId {
+        self.id
+    }
+
+    /// Get the captured fields of the context and its parents.
+    ///
+    /// See [`captured_fields`] for details.
+    pub fn fields(&self) -> &FieldsBuf {
+        &self.fields
+    }
+
+    /// Get a field by its key.
+    pub fn get(&self, key: &str) -> Option<&Value<'static>> {
+        self.fields.get(key)
+    }
+
+    /// Create a new context with the fields of the snapshot.
+    ///
+    /// The new context is associated with the current evaluator, and has a
+    /// new identifier.
+    pub fn to_context(&self, parent: Option<&Context>) -> Context {
+        new_context(parent, &self.fields)
+    }
+}
+
+/// Take a snapshot of the fields of a context and its parents captured by
+/// [`Propagate`].
+pub fn snapshot(context: &Context) -> ContextSnapshot {
+    ContextSnapshot {
+        id: context.id(),
+        fields: Arc::new(captured_fields(context)),
+    }
+}
+
+/// Write the captured fields of a context to message headers.
+///
+/// Each field is written to a header named [`HEADER_PREFIX`] followed by the
+/// field key. Values are prefixed with their type, so they can be restored
+/// with the same type by [`extract`], and strings are percent-encoded.
+pub fn inject<I: HeaderInjector + ?Sized>(context: &Context, injector: &mut I) {
+    for (key, value) in captured_fields(context).pairs() {
+        injector.set(&format!("{HEADER_PREFIX}{key}"), encode(value));
+    }
+}
+
+/// Read the fields written by [`inject`] from message headers.
+///
+/// Headers that don't start with [`HEADER_PREFIX`], or that can't be decoded,
+/// are ignored.
+pub fn extract<E: HeaderExtractor + ?Sized>(extractor: &E) -> FieldsBuf {
+    let mut fields = FieldsBuf::new();
+    for header in extractor.keys() {
+        let Some(key) = header.strip_prefix(HEADER_PREFIX) else {
+            continue;
+        };
+        if let Some(value) = extractor.get(header).and_then(decode) {
+            fields.insert(key.to_string(), value);
+        }
+    }
+    fields
+}
+
+/// Create a new context with the fields read from message headers.
+///
+/// See [`extract`] for details.
+pub fn context_from_headers<E: HeaderExtractor + ?Sized>(
+    parent: Option<&Context>,
+    extractor: &E,
+) -> Context {
+    new_context(parent, &extract(extractor))
+}
+
+fn new_context(parent: Option<&Context>, fields: &FieldsBuf) -> Context {
+    Context::new_with_parts(parent, Extensions::new(), |with_fields| {
+        let pairs = fields
+            .pairs()
+            .map(|(key, value)| (key, value.clone()))
+            .collect::<Vec<_>>();
+        with_fields(Fields::new(&pairs))
+    })
+}
+
+fn encode(value: &Value<'_>) -> String {
+    match value {
+        Value::Str(value) => format!("s:{}", percent_encode(value)),
+        Value::Bytes(value) => {
+            let hex = value.iter().map(|b| format!("{b:02x}")).collect::<String>();
+            format!("x:{hex}")
+        }
+        Value::Bool(value) => format!("b:{value}"),
+        Value::I64(value) => format!("i:{value}"),
+        Value::U64(value) => format!("u:{value}"),
+        Value::F64(value) => format!("f:{value}"),
+        Value::Duration(value) => format!("d:{}", value.as_nanos()),
+        #[cfg(feature = "json")]
+        Value::Json(value) => format!("j:{}", percent_encode(&value.to_string())),
+        Value::Null => "n:".to_string(),
+    }
+}
+
+fn decode(value: &str) -> Option<Value<'static>> {
+    let (kind, value) = value.split_once(':')?;
+    Some(match kind {
+        "s" => Value::Str(percent_decode(value)?.into()),
+        "x" => {
+            if value.len() % 2 != 0 {
+                return None;
+            }
+            let bytes = (0..value.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<_>>>()?;
+            Value::Bytes(bytes.into())
+        }
+        "b" => Value::Bool(value.parse().ok()?),
+        "i" => Value::I64(value.parse().ok()?),
+        "u" => Value::U64(value.parse().ok()?),
+        "f" => Value::F64(value.parse().ok()?),
+        "d" => {
+            let nanos = value.parse::<u128>().ok()?;
+            let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+            Value::Duration(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+        }
+        #[cfg(feature = "json")]
+        "j" => Value::Json(serde_json::from_str(&percent_decode(value)?).ok()?),
+        "n" if value.is_empty() => Value::Null,
+        _ => return None,
+    })
+}
+
+/// Percent-encode a string, so it can't contain separators or line breaks
+/// that are not allowed in header values.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
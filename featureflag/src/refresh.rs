@@ -0,0 +1,214 @@
+//! This module provides [`BackgroundRefresh`], for running the polling or
+//! streaming task of a remote evaluator in the background.
+
+use std::{
+    any::Any,
+    fmt,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Builder for a background refresh task.
+///
+/// The task runs on a dedicated thread until its [`RefreshHandle`] is shut
+/// down or dropped. Polling tasks are spawned with [`spawn`](Self::spawn),
+/// and are called once immediately and then once every interval. Streaming
+/// tasks are spawned with [`spawn_with`](Self::spawn_with), and run until
+/// they return, checking the [`StopToken`] to know when to stop.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use featureflag::refresh::BackgroundRefresh;
+///
+/// let handle = BackgroundRefresh::new(Duration::from_secs(30))
+///     .thread_name("flag-refresh")
+///     .spawn(|| {
+///         // fetch flags from the backend
+///     });
+///
+/// handle.shutdown().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct BackgroundRefresh {
+    interval: Duration,
+    thread_name: Option<String>,
+}
+
+impl BackgroundRefresh {
+    /// Create a new background refresh, polling every `interval`.
+    pub fn new(interval: Duration) -> BackgroundRefresh {
+        BackgroundRefresh {
+            interval,
+            thread_name: None,
+        }
+    }
+
+    /// Set the name of the background thread.
+    pub fn thread_name(mut self, name: impl Into<String>) -> BackgroundRefresh {
+        self.thread_name = Some(name.into());
+        self
+    }
+
+    /// Spawn a polling task, which calls `refresh` once immediately and then
+    /// once every interval.
+    ///
+    /// The task can be triggered early with [`RefreshHandle::refresh_now`].
+    pub fn spawn<F>(self, mut refresh: F) -> RefreshHandle
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let interval = self.interval;
+        self.spawn_with(move |token| {
+            while !token.is_stopped() {
+                refresh();
+                token.wait(interval);
+            }
+        })
+    }
+
+    /// Spawn a streaming task, which runs until it returns.
+    ///
+    /// The task should return when the [`StopToken`] is stopped.
+    pub fn spawn_with<F>(self, task: F) -> RefreshHandle
+    where
+        F: FnOnce(&StopToken) + Send + 'static,
+    {
+        let token = StopToken {
+            signal: Arc::new(Signal::default()),
+        };
+
+        let mut builder = thread::Builder::new();
+        if let Some(name) = self.thread_name {
+            builder = builder.name(name);
+        }
+        let thread = builder
+            .spawn({
+                let token = token.clone();
+                move || task(&token)
+            })
+            .expect("failed to spawn background refresh thread");
+
+        RefreshHandle {
+            token,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Handle to a task spawned by [`BackgroundRefresh`].
+///
+/// The task is stopped and joined when the handle is dropped.
+pub struct RefreshHandle {
+    token: StopToken,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RefreshHandle {
+    /// Wake up the task, so that a polling task refreshes without waiting for
+    /// the rest of the interval.
+    pub fn refresh_now(&self) {
+        self.token.signal.notify(|state| state.woken = true);
+    }
+
+    /// Check if the task has finished.
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop the task without waiting for it to finish.
+    pub fn stop(&self) {
+        self.token.signal.notify(|state| state.stopped = true);
+    }
+
+    /// Stop the task and wait for it to finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns the panic payload if the task panicked.
+    pub fn shutdown(mut self) -> Result<(), Box<dyn Any + Send + 'static>> {
+        self.stop();
+        match self.thread.take() {
+            Some(thread) => thread.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RefreshHandle {
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl fmt::Debug for RefreshHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshHandle")
+            .field("stopped", &self.token.is_stopped())
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+/// Token passed to tasks spawned by [`BackgroundRefresh`], to signal when the
+/// task should stop.
+#[derive(Clone)]
+pub struct StopToken {
+    signal: Arc<Signal>,
+}
+
+#[derive(Default)]
+struct Signal {
+    state: Mutex<SignalState>,
+    condvar: Condvar,
+}
+
+#[derive(Default)]
+struct SignalState {
+    stopped: bool,
+    woken: bool,
+}
+
+impl Signal {
+    fn notify(&self, f: impl FnOnce(&mut SignalState)) {
+        f(&mut self.state.lock().unwrap());
+        self.condvar.notify_all();
+    }
+}
+
+impl StopToken {
+    /// Check if the task should stop.
+    pub fn is_stopped(&self) -> bool {
+        self.signal.state.lock().unwrap().stopped
+    }
+
+    /// Wait until the task should stop, the task is woken up with
+    /// [`RefreshHandle::refresh_now`], or `timeout` has elapsed.
+    ///
+    /// Returns `true` if the task should stop.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let state = self.signal.state.lock().unwrap();
+        let mut state = self
+            .signal
+            .condvar
+            .wait_timeout_while(state, timeout, |state| !state.stopped && !state.woken)
+            .unwrap()
+            .0;
+        state.woken = false;
+        state.stopped
+    }
+}
+
+impl fmt::Debug for StopToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StopToken")
+            .field("stopped", &self.is_stopped())
+            .finish()
+    }
+}
@@ -0,0 +1,414 @@
+//! Registry of feature flags defined at compile-time.
+//!
+//! Features defined with the [`feature!`](crate::feature!) and
+//! [`is_enabled!`](crate::is_enabled!) macros with literal names are
+//! registered along with their default value, where they are defined, and any
+//! additional metadata.
+//!
+//! Features created at runtime can be registered with [`register_dynamic`].
+//!
+//! Features that are meant to be enabled and disabled together, like the steps
+//! of a migration, can be declared as a [`FlagGroup`], either directly or with
+//! the `group` and `group_step` metadata keys, see [`flag_groups`].
+
+use std::{
+    panic::Location,
+    sync::{LazyLock, RwLock},
+};
+
+#[cfg(feature = "json")]
+use serde::{Serialize, Serializer};
+
+static DYNAMIC_FEATURES: RwLock<Vec<FeatureInfo>> = RwLock::new(Vec::new());
+
+/// Information about a registered feature, see [`registered_features`].
+#[derive(Copy, Clone, Debug)]
+pub struct FeatureInfo {
+    name: &'static str,
+    default: &'static str,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    metadata: &'static [(&'static str, &'static str)],
+    dynamic: bool,
+}
+
+impl FeatureInfo {
+    #[doc(hidden)]
+    pub const fn __new(
+        name: &'static str,
+        default: &'static str,
+        module_path: &'static str,
+        file: &'static str,
+        line: u32,
+        metadata: &'static [(&'static str, &'static str)],
+    ) -> FeatureInfo {
+        FeatureInfo {
+            name,
+            default,
+            module_path,
+            file,
+            line,
+            metadata,
+            dynamic: false,
+        }
+    }
+
+    /// Get the name of the feature.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get the default value of the feature, if it is a `true` or `false`
+    /// literal.
+    ///
+    /// Defaults can be arbitrary expressions, which are only evaluated when the
+    /// feature is evaluated. Use [`default_expr`](Self::default_expr) to get the
+    /// source code of the default expression.
+    pub fn default(&self) -> Option<bool> {
+        match self.default {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Get the source code of the default value expression.
+    pub fn default_expr(&self) -> &'static str {
+        self.default
+    }
+
+    /// Get the module path where the feature is defined.
+    ///
+    /// The module path is empty for features registered with
+    /// [`register_dynamic`].
+    pub fn module_path(&self) -> &'static str {
+        self.module_path
+    }
+
+    /// Get the file where the feature is defined.
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// Get the line where the feature is defined.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Get the metadata of the feature, as key-value pairs.
+    pub fn metadata(&self) -> &'static [(&'static str, &'static str)] {
+        self.metadata
+    }
+
+    /// Check if the feature was registered at runtime with [`register_dynamic`].
+    pub fn is_dynamic(&self) -> bool {
+        self.dynamic
+    }
+
+    /// Get a metadata value of the feature by key.
+    pub fn get(&self, key: &str) -> Option<&'static str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+    }
+
+    /// Get the context fields required to evaluate the feature.
+    ///
+    /// Required fields are set with the `requires` metadata key, as a
+    /// comma-separated list of field names, e.g.
+    /// `feature!("rollout", false, requires = "user_id")`. See
+    /// [`RequiredFields`](crate::evaluator::RequiredFields).
+    pub fn required_fields(&self) -> impl Iterator<Item = &'static str> + use<> {
+        self.get("requires")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+    }
+}
+
+/// Get all features registered at compile-time, sorted by name and location.
+///
+/// A feature is registered once for each place it is defined, so the same name
+/// can appear multiple times. Use [`known_features`](crate::feature::known_features)
+/// to get the set of registered names. Features registered at runtime are
+/// returned by [`dynamic_features`].
+pub fn registered_features() -> &'static [FeatureInfo] {
+    static CACHED: LazyLock<Vec<FeatureInfo>> = LazyLock::new(|| {
+        let mut features = inventory::iter::<RegisteredFeature>()
+            .map(|feature| feature.0)
+            .collect::<Vec<_>>();
+        features.sort_by_key(|info| (info.name, info.file, info.line));
+        features
+    });
+    &CACHED
+}
+
+/// Get the features registered at runtime with [`register_dynamic`], sorted by
+/// name.
+pub fn dynamic_features() -> Vec<FeatureInfo> {
+    let mut features = DYNAMIC_FEATURES.read().unwrap().clone();
+    features.sort_by_key(|info| info.name);
+    features
+}
+
+/// Get both the features registered at compile-time and at runtime.
+pub(crate) fn all_features() -> Vec<FeatureInfo> {
+    let mut features = registered_features().to_vec();
+    features.extend(dynamic_features());
+    features
+}
+
+/// Register a feature created at runtime.
+///
+/// Features created with [`Feature::new`](crate::Feature::new) or with
+/// non-literal names are not registered, so they are not included in
+/// [`known_features`](crate::feature::known_features) unless they are
+/// registered with this function. The feature is registered with the location
+/// of the caller, and is returned by [`dynamic_features`].
+///
+/// Registered features are never unregistered, and the name is leaked to give
+/// it a `'static` lifetime like compile-time features, so this should only be
+/// used for a bounded set of names.
+///
+/// Returns `false` if a feature with the same name has already been
+/// registered, at compile-time or with this function, in which case nothing is
+/// changed.
+#[track_caller]
+pub fn register_dynamic(name: &str, default: bool) -> bool {
+    if registered_features()
+        .binary_search_by_key(&name, |info| info.name)
+        .is_ok()
+    {
+        return false;
+    }
+
+    let mut features = DYNAMIC_FEATURES.write().unwrap();
+    if features.iter().any(|info| info.name == name) {
+        return false;
+    }
+
+    let name: &'static str = Box::leak(name.into());
+    let location = Location::caller();
+    features.push(FeatureInfo {
+        name,
+        default: if default { "true" } else { "false" },
+        module_path: "",
+        file: location.file(),
+        line: location.line(),
+        metadata: &[],
+        dynamic: true,
+    });
+    drop(features);
+
+    crate::diagnostics::register_dynamic_feature(name);
+    true
+}
+
+#[doc(hidden)]
+pub struct RegisteredFeature(pub FeatureInfo);
+
+inventory::collect!(RegisteredFeature);
+
+/// An ordered group of features that are meant to be enabled and disabled
+/// together.
+///
+/// The features are declared in the order they should be enabled, so each
+/// feature may depend on the features before it. To roll back, they are
+/// disabled in the reverse order.
+///
+/// # Examples
+///
+/// ```
+/// use featureflag::registry::FlagGroup;
+///
+/// let group = FlagGroup::new("orders_db", ["write_new_db", "read_new_db", "drop_old_db"]);
+/// assert_eq!(
+///     group.enable_order().collect::<Vec<_>>(),
+///     ["write_new_db", "read_new_db", "drop_old_db"],
+/// );
+/// assert_eq!(
+///     group.disable_order().collect::<Vec<_>>(),
+///     ["drop_old_db", "read_new_db", "write_new_db"],
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlagGroup {
+    name: String,
+    features: Vec<String>,
+}
+
+impl FlagGroup {
+    /// Create a new group of features, in the order they should be enabled.
+    ///
+    /// If a feature is listed multiple times, only its first position is used.
+    pub fn new<I>(name: impl Into<String>, features: I) -> FlagGroup
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut group = FlagGroup {
+            name: name.into(),
+            features: Vec::new(),
+        };
+        for feature in features {
+            let feature = feature.into();
+            if !group.features.contains(&feature) {
+                group.features.push(feature);
+            }
+        }
+        group
+    }
+
+    /// Get the name of the group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the features in the order they should be enabled.
+    pub fn enable_order(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.features.iter().map(String::as_str)
+    }
+
+    /// Get the features in the order they should be disabled.
+    ///
+    /// This is the reverse of [`enable_order`](Self::enable_order).
+    pub fn disable_order(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.enable_order().rev()
+    }
+
+    /// Check if the group is in a safe state.
+    ///
+    /// A group is in a safe state if the enabled features are a prefix of
+    /// [`enable_order`](Self::enable_order), i.e. no feature is enabled
+    /// while a feature before it is disabled.
+    pub fn is_consistent<F: FnMut(&str) -> bool>(&self, mut is_enabled: F) -> bool {
+        let mut features = self.enable_order();
+        features.by_ref().all(&mut is_enabled);
+        features.all(|feature| !is_enabled(feature))
+    }
+}
+
+/// Get the groups of registered features.
+///
+/// Features are added to a group with the `group` metadata key, and ordered
+/// within the group by the `group_step` metadata key, an integer, e.g.
+/// `feature!("read_new_db", false, group = "orders_db", group_step = "2")`.
+/// Features without a valid step come last, ordered by name.
+///
+/// Groups are sorted by name.
+pub fn flag_groups() -> Vec<FlagGroup> {
+    let mut members = registered_features()
+        .iter()
+        .filter_map(|info| {
+            let group = info.get("group")?;
+            let step = info
+                .get("group_step")
+                .and_then(|step| step.trim().parse::<i64>().ok());
+            Some((group, step.is_none(), step, info.name))
+        })
+        .collect::<Vec<_>>();
+    members.sort();
+
+    let mut groups = Vec::<FlagGroup>::new();
+    for (group, _, _, name) in members {
+        match groups.last_mut() {
+            Some(last) if last.name == group => {
+                if !last.features.iter().any(|feature| feature == name) {
+                    last.features.push(name.to_string());
+                }
+            }
+            _ => groups.push(FlagGroup::new(group, [name])),
+        }
+    }
+    groups
+}
+
+/// Export all registered features as a JSON array.
+///
+/// Each feature is exported as an object with its `name`, `default` (or `null`
+/// if the default is not a literal), `default_expr`, `module_path`, `file`,
+/// `line` and `metadata`, in the same order as [`registered_features`],
+/// followed by the [`dynamic_features`]. This
+/// can be used by tooling to compare the features used in code against the
+/// features configured elsewhere.
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub fn export_json() -> String {
+    let features = all_features()
+        .into_iter()
+        .map(|info| ExportedFeature {
+            name: info.name,
+            default: info.default(),
+            default_expr: info.default,
+            module_path: info.module_path,
+            file: info.file,
+            line: info.line,
+            metadata: info.metadata,
+        })
+        .collect::<Vec<_>>();
+    let mut out = serde_json::to_string_pretty(&features).expect("features are serializable");
+    out.push('\n');
+    out
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct ExportedFeature {
+    name: &'static str,
+    default: Option<bool>,
+    default_expr: &'static str,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    #[serde(serialize_with = "serialize_metadata")]
+    metadata: &'static [(&'static str, &'static str)],
+}
+
+/// Serialize metadata as an object, keeping the order of the keys.
+#[cfg(feature = "json")]
+fn serialize_metadata<S: Serializer>(
+    metadata: &&'static [(&'static str, &'static str)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(metadata.iter().copied())
+}
+
+/// Export all registered features as a YAML sequence.
+///
+/// The features are exported with the same fields as [`export_json`].
+#[cfg(feature = "registry-yaml")]
+#[cfg_attr(docsrs, doc(cfg(feature = "registry-yaml")))]
+pub fn export_yaml() -> String {
+    let features = all_features();
+    if features.is_empty() {
+        return String::from("[]\n");
+    }
+
+    // JSON strings are also valid YAML strings
+    let quote = |value: &str| serde_json::Value::from(value).to_string();
+
+    let mut out = String::new();
+    for info in &features {
+        out.push_str(&format!("- name: {}\n", quote(info.name)));
+        out.push_str(&format!(
+            "  default: {}\n",
+            serde_json::Value::from(info.default())
+        ));
+        out.push_str(&format!("  default_expr: {}\n", quote(info.default)));
+        out.push_str(&format!("  module_path: {}\n", quote(info.module_path)));
+        out.push_str(&format!("  file: {}\n", quote(info.file)));
+        out.push_str(&format!("  line: {}\n", info.line));
+        if info.metadata.is_empty() {
+            out.push_str("  metadata: {}\n");
+        } else {
+            out.push_str("  metadata:\n");
+            for (key, value) in info.metadata {
+                out.push_str(&format!("    {}: {}\n", quote(key), quote(value)));
+            }
+        }
+    }
+    out
+}
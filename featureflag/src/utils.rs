@@ -4,7 +4,10 @@ use std::{pin::Pin, task::Poll};
 
 use crate::{
     Context, Evaluator,
-    evaluator::{EvaluatorRef, NoEvaluator, get_default, with_default_no_registration},
+    evaluator::{
+        EvaluatorRef, NoEvaluator, RegistrationKind, get_default, register,
+        with_default_no_registration,
+    },
 };
 
 /// Extension trait for all types.
@@ -75,6 +78,8 @@ pub trait AnyExt {
     }
 }
 
+impl<T: ?Sized> AnyExt for T {}
+
 /// Wraps a type with a [`Context`].
 ///
 /// See [`AnyExt::wrap_context`] for more details.
@@ -133,7 +138,7 @@ impl<Fut: ?Sized + Future> Future for WrapEvaluator<Fut> {
         };
 
         if !*registered {
-            evaluator.on_registration();
+            register(evaluator, RegistrationKind::Scoped);
             *registered = true;
         }
 
@@ -156,7 +161,7 @@ impl<S: ?Sized + futures_core::Stream> futures_core::Stream for WrapEvaluator<S>
         };
 
         if !*registered {
-            evaluator.on_registration();
+            register(evaluator, RegistrationKind::Scoped);
             *registered = true;
         }
 
@@ -1,9 +1,12 @@
 //! Value types for the [`context!`](macro@crate::context) macro.
 
-use std::{borrow::Cow, fmt};
+use alloc::borrow::Cow;
+use core::{fmt, time::Duration};
+
+use crate::prelude::*;
 
 /// A value that can be passed as a field in a [`context!`](macro@crate::context).
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq)]
 pub enum Value<'a> {
     /// A string value.
     Str(Cow<'a, str>),
@@ -23,12 +26,22 @@ pub enum Value<'a> {
     /// A 64-bit floating-point value.
     F64(f64),
 
+    /// A duration value.
+    Duration(Duration),
+
+    /// A JSON value, for structured data that doesn't fit the other variants.
+    ///
+    /// Nested values can be looked up with [`json_path`](Self::json_path).
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    Json(Cow<'a, serde_json::Value>),
+
     /// A null value.
     #[default]
     Null,
 }
 
-impl Value<'_> {
+impl<'a> Value<'a> {
     /// Clone a new `Value` with a `'static` lifetime.
     pub fn to_static(&self) -> Value<'static> {
         match self {
@@ -38,6 +51,9 @@ impl Value<'_> {
             Value::U64(n) => Value::U64(*n),
             Value::I64(n) => Value::I64(*n),
             Value::F64(x) => Value::F64(*x),
+            Value::Duration(d) => Value::Duration(*d),
+            #[cfg(feature = "json")]
+            Value::Json(j) => Value::Json(Cow::Owned(j.clone().into_owned())),
             Value::Null => Value::Null,
         }
     }
@@ -51,6 +67,9 @@ impl Value<'_> {
             Value::U64(n) => Value::U64(n),
             Value::I64(n) => Value::I64(n),
             Value::F64(x) => Value::F64(x),
+            Value::Duration(d) => Value::Duration(d),
+            #[cfg(feature = "json")]
+            Value::Json(j) => Value::Json(Cow::Owned(j.into_owned())),
             Value::Null => Value::Null,
         }
     }
@@ -103,10 +122,89 @@ impl Value<'_> {
         }
     }
 
+    /// Get the value as a duration, if it is a duration.
+    pub fn as_duration(&self) -> Option<Duration> {
+        match self {
+            Value::Duration(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    /// Get the value as a JSON value, if it is a JSON value.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn as_json(&self) -> Option<&serde_json::Value> {
+        match self {
+            Value::Json(j) => Some(j),
+            _ => None,
+        }
+    }
+
+    /// Get a nested value of a JSON value by a dotted path, such as
+    /// `"user.roles.0"`.
+    ///
+    /// Each segment of the path is an object key, or an index into an array.
+    /// An empty path returns the JSON value itself. Returns `None` if the value
+    /// is not a JSON value, or the path doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use featureflag::value::ToValue;
+    ///
+    /// let user = serde_json::json!({ "roles": ["admin", "dev"] });
+    /// let value = user.to_value();
+    /// assert_eq!(
+    ///     value.json_path("roles.1").and_then(|v| v.as_str()),
+    ///     Some("dev")
+    /// );
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn json_path(&self, path: &str) -> Option<&serde_json::Value> {
+        let json = self.as_json()?;
+        if path.is_empty() {
+            return Some(json);
+        }
+        path.split('.').try_fold(json, |json, segment| match json {
+            serde_json::Value::Object(map) => map.get(segment),
+            serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+    }
+
     /// Check if the value is null.
     pub fn is_null(&self) -> bool {
         matches!(self, Value::Null)
     }
+
+    /// Get the name of the type of the value, as used in [`ValueConversionError`].
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Str(_) => "string",
+            Value::Bytes(_) => "bytes",
+            Value::Bool(_) => "bool",
+            Value::I64(_) => "i64",
+            Value::U64(_) => "u64",
+            Value::F64(_) => "f64",
+            Value::Duration(_) => "duration",
+            #[cfg(feature = "json")]
+            Value::Json(_) => "json",
+            Value::Null => "null",
+        }
+    }
+
+    /// Get a nested value by a dotted path, such as `"user.id"`.
+    ///
+    /// An empty path returns the value itself. Any other path returns `None`,
+    /// since the nested values of JSON values are not [`Value`]s, see
+    /// [`json_path`](Self::json_path) instead.
+    pub fn get_path(&self, path: &str) -> Option<&Value<'a>> {
+        match self {
+            _ if path.is_empty() => Some(self),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Debug for Value<'_> {
@@ -118,11 +216,183 @@ impl fmt::Debug for Value<'_> {
             Value::I64(n) => write!(f, "{:?}", n),
             Value::U64(n) => write!(f, "{:?}", n),
             Value::F64(x) => write!(f, "{:?}", x),
+            Value::Duration(d) => write!(f, "{:?}", d),
+            #[cfg(feature = "json")]
+            Value::Json(j) => write!(f, "{}", j),
             Value::Null => write!(f, "null"),
         }
     }
 }
 
+/// Error returned when a [`Value`] can't be converted to another type.
+///
+/// This error is returned by the [`TryFrom<&Value>`](TryFrom) implementations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueConversionError {
+    expected: &'static str,
+    found: &'static str,
+    out_of_range: bool,
+}
+
+impl ValueConversionError {
+    fn mismatch(expected: &'static str, value: &Value<'_>) -> ValueConversionError {
+        ValueConversionError {
+            expected,
+            found: value.type_name(),
+            out_of_range: false,
+        }
+    }
+
+    fn out_of_range(expected: &'static str, value: &Value<'_>) -> ValueConversionError {
+        ValueConversionError {
+            expected,
+            found: value.type_name(),
+            out_of_range: true,
+        }
+    }
+
+    /// Get the name of the type the value was converted to.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// Get the name of the type of the value.
+    pub fn found(&self) -> &'static str {
+        self.found
+    }
+
+    /// Check if the conversion failed because the value could not be
+    /// represented exactly in the target type.
+    pub fn is_out_of_range(&self) -> bool {
+        self.out_of_range
+    }
+}
+
+impl fmt::Display for ValueConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.out_of_range {
+            write!(f, "{} value out of range for {}", self.found, self.expected)
+        } else {
+            write!(f, "expected {}, found {}", self.expected, self.found)
+        }
+    }
+}
+
+impl core::error::Error for ValueConversionError {}
+
+impl TryFrom<&Value<'_>> for bool {
+    type Error = ValueConversionError;
+
+    fn try_from(value: &Value<'_>) -> Result<bool, ValueConversionError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(ValueConversionError::mismatch("bool", value)),
+        }
+    }
+}
+
+impl TryFrom<&Value<'_>> for i64 {
+    type Error = ValueConversionError;
+
+    /// Converts integer values, and floating-point values without a fractional
+    /// part, as long as they are in range for `i64`.
+    fn try_from(value: &Value<'_>) -> Result<i64, ValueConversionError> {
+        match value {
+            Value::I64(n) => Ok(*n),
+            Value::U64(n) => {
+                i64::try_from(*n).map_err(|_| ValueConversionError::out_of_range("i64", value))
+            }
+            Value::F64(x) => {
+                // -2^63 and 2^63 are exactly representable as f64, and in range
+                // the cast only drops the fractional part
+                if *x >= -9223372036854775808.0
+                    && *x < 9223372036854775808.0
+                    && *x as i64 as f64 == *x
+                {
+                    Ok(*x as i64)
+                } else {
+                    Err(ValueConversionError::out_of_range("i64", value))
+                }
+            }
+            _ => Err(ValueConversionError::mismatch("i64", value)),
+        }
+    }
+}
+
+impl TryFrom<&Value<'_>> for u64 {
+    type Error = ValueConversionError;
+
+    /// Converts integer values, and floating-point values without a fractional
+    /// part, as long as they are in range for `u64`.
+    fn try_from(value: &Value<'_>) -> Result<u64, ValueConversionError> {
+        match value {
+            Value::U64(n) => Ok(*n),
+            Value::I64(n) => {
+                u64::try_from(*n).map_err(|_| ValueConversionError::out_of_range("u64", value))
+            }
+            Value::F64(x) => {
+                // 2^64 is exactly representable as f64, and in range the cast
+                // only drops the fractional part
+                if *x >= 0.0 && *x < 18446744073709551616.0 && *x as u64 as f64 == *x {
+                    Ok(*x as u64)
+                } else {
+                    Err(ValueConversionError::out_of_range("u64", value))
+                }
+            }
+            _ => Err(ValueConversionError::mismatch("u64", value)),
+        }
+    }
+}
+
+impl TryFrom<&Value<'_>> for f64 {
+    type Error = ValueConversionError;
+
+    /// Converts floating-point values, and integer values that can be
+    /// represented exactly as `f64`.
+    fn try_from(value: &Value<'_>) -> Result<f64, ValueConversionError> {
+        match value {
+            Value::F64(x) => Ok(*x),
+            Value::I64(n) if (*n as f64) as i128 == i128::from(*n) => Ok(*n as f64),
+            Value::U64(n) if (*n as f64) as i128 == i128::from(*n) => Ok(*n as f64),
+            Value::I64(_) | Value::U64(_) => Err(ValueConversionError::out_of_range("f64", value)),
+            _ => Err(ValueConversionError::mismatch("f64", value)),
+        }
+    }
+}
+
+impl TryFrom<&Value<'_>> for Duration {
+    type Error = ValueConversionError;
+
+    fn try_from(value: &Value<'_>) -> Result<Duration, ValueConversionError> {
+        match value {
+            Value::Duration(d) => Ok(*d),
+            _ => Err(ValueConversionError::mismatch("duration", value)),
+        }
+    }
+}
+
+impl TryFrom<&Value<'_>> for String {
+    type Error = ValueConversionError;
+
+    fn try_from(value: &Value<'_>) -> Result<String, ValueConversionError> {
+        match value {
+            Value::Str(s) => Ok(s.clone().into_owned()),
+            _ => Err(ValueConversionError::mismatch("string", value)),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Value<'_>> for &'a str {
+    type Error = ValueConversionError;
+
+    fn try_from(value: &'a Value<'_>) -> Result<&'a str, ValueConversionError> {
+        match value {
+            Value::Str(s) => Ok(s),
+            _ => Err(ValueConversionError::mismatch("string", value)),
+        }
+    }
+}
+
 /// A trait for types that can be converted to a [`Value`].
 pub trait ToValue {
     /// Convert the type to a [`Value`].
@@ -239,3 +509,17 @@ impl ToValue for f64 {
         Value::F64(*self)
     }
 }
+
+impl ToValue for Duration {
+    fn to_value(&self) -> Value<'_> {
+        Value::Duration(*self)
+    }
+}
+
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+impl ToValue for serde_json::Value {
+    fn to_value(&self) -> Value<'_> {
+        Value::Json(Cow::Borrowed(self))
+    }
+}
@@ -0,0 +1,30 @@
+#![allow(missing_docs)]
+#![cfg(feature = "callsite-tracking")]
+
+use featureflag::{
+    Feature,
+    diagnostics::{callsites_for, enable_tracking},
+};
+
+#[test]
+fn test_callsites() {
+    enable_tracking();
+
+    for _ in 0..3 {
+        featureflag::is_enabled!("feature", false);
+    }
+    let line = line!() - 2;
+
+    Feature::new("feature", false).is_enabled();
+    Feature::new("other", false).is_enabled();
+
+    let usages = callsites_for("feature");
+    assert_eq!(usages.len(), 2);
+
+    assert_eq!(usages[0].location().file(), file!());
+    assert_eq!(usages[0].location().line(), line);
+    assert_eq!(usages[0].count(), 3);
+
+    assert_eq!(usages[1].location().line(), line + 4);
+    assert_eq!(usages[1].count(), 1);
+}
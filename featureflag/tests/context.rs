@@ -1,6 +1,16 @@
 #![allow(missing_docs)]
 
-use featureflag::{Context, context, evaluator::with_default};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use featureflag::{
+    Context, context,
+    context::{ContextId, ContextRef},
+    evaluator::{Evaluator, with_default},
+    fields::Fields,
+};
 use featureflag_test::{TestContextExt, TestEvaluator};
 
 #[test]
@@ -36,3 +46,180 @@ fn test_context() {
         });
     });
 }
+
+#[test]
+fn test_lazy_context() {
+    let calls = std::cell::Cell::new(0);
+    let expensive = || {
+        calls.set(calls.get() + 1);
+        "value"
+    };
+
+    let context = context!(lazy: foo = expensive());
+    assert!(context.test_fields().is_none());
+    assert_eq!(calls.get(), 0);
+
+    with_default(TestEvaluator::new(), || {
+        let parent = context!(lazy: foo = expensive());
+        assert_eq!(calls.get(), 1);
+        assert_eq!(
+            parent.test_fields().and_then(|f| f.get("foo")),
+            Some(&featureflag::value::Value::Str("value".into()))
+        );
+
+        let child = context!(parent: parent, lazy: bar = expensive());
+        assert_eq!(calls.get(), 2);
+        assert!(child.parent().is_some_and(|p| p.id() == parent.id()));
+    });
+}
+
+#[test]
+fn test_context_extensions() {
+    struct RequestId(u64);
+    struct Region(&'static str);
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("eu", |c: &Context| {
+        c.iter()
+            .find_map(|c| c.extensions().get::<Region>())
+            .map(|region| region.0 == "eu")
+    });
+
+    let detached = context!(extensions: [RequestId(1)]);
+    assert_eq!(
+        detached.extensions().get::<RequestId>().map(|id| id.0),
+        Some(1)
+    );
+
+    with_default(evaluator, || {
+        let parent = context!(extensions: [RequestId(2), Region("eu")], foo = 1);
+        assert_eq!(
+            parent.extensions().get::<RequestId>().map(|id| id.0),
+            Some(2)
+        );
+        assert!(parent.test_fields().is_some_and(|f| f.get("foo").is_some()));
+        assert!(featureflag::is_enabled!(context: parent, "eu", false));
+
+        let child = context!(parent: parent, extensions: [Region("us")], lazy: bar = 2);
+        assert!(child.test_fields().is_some_and(|f| f.get("bar").is_some()));
+        assert!(!featureflag::is_enabled!(context: child, "eu", true));
+    });
+}
+
+#[test]
+fn test_empty_child_context() {
+    struct Plain;
+
+    impl Evaluator for Plain {
+        fn is_enabled(&self, _feature: &str, _context: &Context) -> Option<bool> {
+            None
+        }
+    }
+
+    with_default(Plain, || {
+        let parent = context!(foo = 1);
+        assert_eq!(context!(parent: parent).id(), parent.id());
+        assert_ne!(context!(parent: parent, foo = 2).id(), parent.id());
+        assert_ne!(
+            context!(parent: parent, extensions: [Marker]).id(),
+            parent.id()
+        );
+        assert!(!context!(parent: None, foo = 1).is_root());
+
+        // empty children of the root stay associated with the evaluator
+        let child = with_default(Plain, || context!(parent: None));
+        assert!(!child.is_root());
+        assert!(child.is_detached());
+
+        let other = with_default(Plain, || context!(parent: parent));
+        assert_ne!(other.id(), parent.id());
+    });
+
+    // without an evaluator, children of detached contexts are not created
+    let detached = context!(foo = 1);
+    assert!(detached.is_detached());
+    assert_eq!(context!(parent: detached, foo = 2).id(), detached.id());
+    assert_ne!(
+        context!(parent: detached, extensions: [Marker]).id(),
+        detached.id()
+    );
+    assert_ne!(context!(parent: None).id(), ContextId::ROOT);
+
+    // the evaluator is only notified about contexts that are created
+    #[derive(Default)]
+    struct Counting {
+        new: AtomicUsize,
+        closed: AtomicUsize,
+    }
+
+    impl Evaluator for Counting {
+        fn is_enabled(&self, _feature: &str, _context: &Context) -> Option<bool> {
+            None
+        }
+
+        fn on_new_context(&self, _context: ContextRef<'_>, _fields: Fields<'_>) {
+            self.new.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_close_context(&self, _context: ContextRef<'_>) {
+            self.closed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let evaluator = Arc::new(Counting::default());
+    with_default(evaluator.clone(), || {
+        let parent = context!(foo = 1);
+        assert_eq!(context!(parent: parent).id(), parent.id());
+        assert_eq!(context!(parent: parent).id(), parent.id());
+    });
+    assert_eq!(evaluator.new.load(Ordering::Relaxed), 1);
+    assert_eq!(evaluator.closed.load(Ordering::Relaxed), 1);
+}
+
+struct Marker;
+
+#[test]
+fn test_inherited_extensions() {
+    #[derive(Debug, PartialEq)]
+    struct Tenant(String);
+
+    struct TenantEvaluator;
+
+    impl Evaluator for TenantEvaluator {
+        fn is_enabled(&self, _feature: &str, context: &Context) -> Option<bool> {
+            context
+                .extensions()
+                .get::<Tenant>()
+                .map(|tenant| tenant.0 == "acme")
+        }
+
+        fn on_new_context(&self, mut context: ContextRef<'_>, fields: Fields<'_>) {
+            if let Some(tenant) = fields.get("tenant").and_then(|v| v.as_str()) {
+                context
+                    .extensions_mut()
+                    .insert_inherited(Tenant(tenant.to_string()));
+            }
+        }
+    }
+
+    with_default(TenantEvaluator, || {
+        let parent = context!(extensions: [Marker], tenant = "acme");
+        let child = context!(parent: parent, user_id = 1u64);
+        let other = context!(parent: child, tenant = "other");
+
+        assert!(featureflag::is_enabled!(context: parent, "feature", false));
+        assert!(featureflag::is_enabled!(context: child, "feature", false));
+        assert!(!featureflag::is_enabled!(context: other, "feature", true));
+        assert_eq!(
+            parent.extensions().get::<Tenant>(),
+            Some(&Tenant("acme".to_string()))
+        );
+
+        // data inserted with `insert` is not inherited
+        assert!(parent.extensions().has::<Marker>());
+        assert!(!child.extensions().has::<Marker>());
+
+        // an empty child only has the inherited data, so the parent is reused
+        assert_eq!(context!(parent: child).id(), child.id());
+    });
+}
@@ -0,0 +1,43 @@
+#![allow(missing_docs)]
+
+use std::collections::HashMap;
+
+use featureflag::{
+    Feature,
+    defaults::{RegistryDefaults, clear_default_provider, default_for, set_default_provider},
+    evaluator::with_default,
+};
+use featureflag_test::TestEvaluator;
+
+const REGISTERED: Feature = featureflag::feature!("defaults.registered", true);
+
+// The default provider is global, so everything is tested in a single test.
+#[test]
+fn test_default_provider() {
+    assert_eq!(default_for("new_ui"), None);
+    assert!(!featureflag::is_enabled!("new_ui", false));
+
+    set_default_provider(HashMap::from([("new_ui".to_string(), true)]));
+    assert_eq!(default_for("new_ui"), Some(true));
+    assert!(featureflag::is_enabled!("new_ui", false));
+    assert!(Feature::new("new_ui", false).evaluate().is_enabled());
+    assert!(!featureflag::is_enabled!("other", false));
+
+    // values from the evaluator take precedence
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("new_ui", false);
+    with_default(evaluator, || {
+        assert!(!featureflag::is_enabled!("new_ui", true));
+    });
+
+    set_default_provider(|feature: &str| feature.starts_with("beta.").then_some(true));
+    assert!(featureflag::is_enabled!("beta.search", false));
+    assert!(!featureflag::is_enabled!("new_ui", false));
+
+    assert!(REGISTERED.is_enabled());
+    set_default_provider(RegistryDefaults::new());
+    assert!(Feature::new("defaults.registered", false).is_enabled());
+
+    clear_default_provider();
+    assert!(!Feature::new("defaults.registered", false).is_enabled());
+}
@@ -0,0 +1,33 @@
+#![allow(missing_docs)]
+
+use featureflag::{
+    Context, Feature, context,
+    context::DetachedPolicy,
+    evaluator::{set_default, with_default},
+};
+use featureflag_test::TestEvaluator;
+
+// The policy is global, so this is the only test in this file.
+#[test]
+fn test_detached_policy() {
+    let old = TestEvaluator::new();
+    old.set_feature("feature", true);
+    let context = with_default(old, || context!(user_id = 1u64));
+    assert!(context.is_detached());
+    assert!(!Context::root().is_detached());
+
+    let current = TestEvaluator::new();
+    current.set_feature("feature", false);
+    let _guard = set_default(current);
+    assert!(!context!(user_id = 1u64).is_detached());
+
+    let feature = Feature::new("feature", true);
+    assert_eq!(DetachedPolicy::get(), DetachedPolicy::UseDefaults);
+    assert!(feature.is_enabled_in(Some(&context)));
+
+    DetachedPolicy::set(DetachedPolicy::UseCurrent);
+    assert!(!feature.is_enabled_in(Some(&context)));
+
+    DetachedPolicy::set(DetachedPolicy::UseDefaults);
+    assert!(feature.is_enabled_in(Some(&context)));
+}
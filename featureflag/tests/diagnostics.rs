@@ -0,0 +1,207 @@
+#![allow(missing_docs)]
+
+use std::{
+    sync::{Arc, Mutex, mpsc},
+    time::Duration,
+};
+
+use featureflag::{
+    Feature,
+    diagnostics::{
+        capture_usage, clear_unknown_feature_hook, enable_tracking, feature_conflicts, flag_drift,
+        report, report_unused_features, set_unknown_feature_hook, unused_features,
+        warn_feature_conflicts,
+    },
+    evaluator::{EvaluatorExt, with_default},
+    feature::known_features,
+    registry::{dynamic_features, register_dynamic},
+};
+use featureflag_test::TestEvaluator;
+
+#[allow(dead_code)]
+fn func() {
+    featureflag::feature!("used", false);
+    featureflag::feature!("unused", false);
+    featureflag::feature!("known", false);
+    featureflag::feature!("conflict.default", false);
+    featureflag::is_enabled!("conflict.default", true);
+    featureflag::feature!("conflict.metadata", false, owner = "a");
+    featureflag::feature!("conflict.metadata", false, owner = "b");
+    featureflag::feature!("conflict.none", false, owner = "a");
+    featureflag::is_enabled!("conflict.none", false);
+}
+
+#[test]
+fn test_unused_features() {
+    enable_tracking();
+    assert!(unused_features().contains(&"used"));
+    assert!(unused_features().contains(&"unused"));
+
+    featureflag::is_enabled!("used", false);
+
+    assert!(!unused_features().contains(&"used"));
+    assert!(unused_features().contains(&"unused"));
+
+    let (tx, rx) = mpsc::channel();
+    let handle = report_unused_features(Duration::from_millis(10), move |unused| {
+        let _ = tx.send(unused.to_vec());
+    });
+
+    assert!(rx.recv().unwrap().contains(&"unused"));
+    drop(handle);
+}
+
+#[test]
+fn test_unknown_feature_hook() {
+    let unknown = Arc::new(Mutex::new(Vec::new()));
+    set_unknown_feature_hook({
+        let unknown = unknown.clone();
+        move |feature| unknown.lock().unwrap().push(feature.to_string())
+    });
+
+    Feature::new("known", false).is_enabled();
+    Feature::new("dynamic", false).is_enabled();
+
+    clear_unknown_feature_hook();
+
+    Feature::new("dynamic", false).is_enabled();
+
+    assert_eq!(*unknown.lock().unwrap(), ["dynamic"]);
+
+    // a hook can replace itself without deadlocking
+    set_unknown_feature_hook({
+        let unknown = unknown.clone();
+        move |feature| {
+            unknown.lock().unwrap().push(feature.to_string());
+            clear_unknown_feature_hook();
+        }
+    });
+    Feature::new("once", false).is_enabled();
+    Feature::new("once", false).is_enabled();
+
+    assert_eq!(*unknown.lock().unwrap(), ["dynamic", "once"]);
+}
+
+#[test]
+fn test_capture_usage() {
+    let (inner, outer) = capture_usage(|| {
+        featureflag::is_enabled!("captured.outer", true);
+        let ((), inner) = capture_usage(|| {
+            featureflag::is_enabled!("captured.inner", false);
+        });
+        inner
+    });
+
+    assert_eq!(inner.len(), 1);
+    assert_eq!(inner[0].feature(), "captured.inner");
+    assert_eq!(inner[0].enabled(), Some(false));
+    assert!(inner[0].is_default());
+
+    assert_eq!(
+        outer
+            .iter()
+            .map(|usage| usage.feature())
+            .collect::<Vec<_>>(),
+        ["captured.outer", "captured.inner"]
+    );
+}
+
+#[test]
+fn test_flag_drift() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("used", true);
+    evaluator.set_feature("extra", false);
+
+    let drift = flag_drift(&evaluator).unwrap();
+    assert!(drift.missing_in_evaluator().contains(&"unused"));
+    assert!(drift.missing_in_evaluator().contains(&"known"));
+    assert!(!drift.missing_in_evaluator().contains(&"used"));
+    assert_eq!(drift.missing_in_code(), ["extra"]);
+    assert!(!drift.is_empty());
+    assert!(drift.to_string().ends_with("; missing in code: extra"));
+
+    let drift = flag_drift(&evaluator.clone().fallback([("unused", true)])).unwrap();
+    assert!(!drift.missing_in_evaluator().contains(&"unused"));
+
+    assert!(flag_drift(&evaluator.map_names(|name: &str| Some(name.to_string()))).is_none());
+}
+
+#[test]
+fn test_feature_conflicts() {
+    let conflicts = feature_conflicts();
+    let names = conflicts
+        .iter()
+        .map(|conflict| conflict.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["conflict.default", "conflict.metadata"]);
+
+    let defaults = conflicts[0]
+        .definitions()
+        .iter()
+        .map(|info| info.default())
+        .collect::<Vec<_>>();
+    assert_eq!(defaults, [Some(false), Some(true)]);
+
+    let message = conflicts[0].to_string();
+    assert!(message.starts_with(
+        "feature \"conflict.default\" has conflicting definitions: default false at "
+    ));
+    assert!(message.contains(", default true at "));
+
+    let mut warned = Vec::new();
+    assert!(warn_feature_conflicts(
+        |conflict| warned.push(conflict.name())
+    ));
+    assert_eq!(warned, names);
+}
+
+#[test]
+fn test_register_dynamic() {
+    let name = format!("dynamic.{}", "runtime");
+    assert!(!known_features().contains(name.as_str()));
+
+    assert!(register_dynamic(&name, true));
+    assert!(!register_dynamic(&name, false));
+    assert!(!register_dynamic("used", false));
+    assert!(known_features().contains(name.as_str()));
+
+    let info = dynamic_features()
+        .into_iter()
+        .find(|info| info.name() == name)
+        .unwrap();
+    assert!(info.is_dynamic());
+    assert_eq!(info.default(), Some(true));
+    assert!(info.file().ends_with("diagnostics.rs"));
+
+    assert!(unused_features().contains(&"dynamic.runtime"));
+    enable_tracking();
+    Feature::new(&name, false).is_enabled();
+    assert!(!unused_features().contains(&"dynamic.runtime"));
+}
+
+#[test]
+fn test_report() {
+    let empty = report();
+    assert!(empty.evaluator().is_none());
+    assert!(!empty.health().is_ready());
+    assert!(empty.to_json().starts_with(concat!(
+        r#"{"evaluator":null,"#,
+        r#""health":{"status":"not_ready","reason":"no evaluator set","last_refresh":null},"#,
+        r#""known_flags":null,"features":[{"name":"captured.inner","default":false,"#,
+    )));
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("used", true);
+    with_default(evaluator.fallback([("unused", true)]), || {
+        let report = report();
+        assert_eq!(report.evaluator().unwrap().name(), "Fallback");
+        assert_eq!(report.known_flags().unwrap().len(), 2);
+        assert!(report.features().iter().any(|info| info.name() == "known"));
+        assert!(report.unused_features().contains(&"unused"));
+
+        let json = report.to_json();
+        assert!(json.starts_with(r#"{"evaluator":{"name":"Fallback","summary":"#));
+        assert!(json.contains(r#","known_flags":["u"#));
+        assert!(json.ends_with("]}"));
+    });
+}
@@ -0,0 +1,1301 @@
+#![allow(missing_docs)]
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use featureflag::{
+    Context, Evaluator, Feature, context,
+    evaluator::{
+        AuditEvaluator, AuditRecord, BootstrapEvaluator, Either, EvaluationHook, EvaluatorExt,
+        EvaluatorRef, ExposureEvaluator, ExposureEvent, Namespace, NoEvaluator, PersistentCache,
+        SwitchBy, TenantRouter, set_default, set_thread_default, set_thread_default_scoped,
+        try_set_thread_default, with_default,
+    },
+};
+use featureflag_test::TestEvaluator;
+
+#[derive(Default)]
+struct RecordingHook {
+    events: Mutex<Vec<String>>,
+}
+
+impl EvaluationHook for RecordingHook {
+    fn before_evaluation(&self, feature: &str, _context: &Context) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("before {feature}"));
+    }
+
+    fn after_evaluation(&self, feature: &str, _context: &Context, result: Option<bool>) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("after {feature} {result:?}"));
+    }
+}
+
+#[test]
+fn test_with_hook() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("enabled", true);
+
+    let hook = Arc::new(RecordingHook::default());
+
+    with_default(evaluator.with_hook(hook.clone()), || {
+        assert!(featureflag::is_enabled!("enabled", false));
+        assert!(!featureflag::is_enabled!("unknown", false));
+    });
+
+    assert_eq!(
+        *hook.events.lock().unwrap(),
+        [
+            "before enabled",
+            "after enabled Some(true)",
+            "before unknown",
+            "after unknown None",
+        ]
+    );
+}
+
+#[test]
+fn test_filter_contexts() {
+    use featureflag::{context::ContextRef, fields::Fields};
+
+    #[derive(Default)]
+    struct Counting {
+        new: Mutex<usize>,
+        closed: Mutex<usize>,
+    }
+
+    impl Evaluator for Counting {
+        fn is_enabled(&self, _feature: &str, _context: &Context) -> Option<bool> {
+            Some(true)
+        }
+
+        fn on_new_context(&self, _context: ContextRef<'_>, _fields: Fields<'_>) {
+            *self.new.lock().unwrap() += 1;
+        }
+
+        fn on_close_context(&self, _context: ContextRef<'_>) {
+            *self.closed.lock().unwrap() += 1;
+        }
+    }
+
+    let counting = Arc::new(Counting::default());
+    let evaluator = counting
+        .clone()
+        .filter_contexts(|fields| fields.get("tenant").is_some());
+
+    with_default(evaluator, || {
+        let tenant = context!(tenant = "a");
+        let request = context!(parent: tenant, request_id = 1);
+        assert!(Feature::new("a", false).is_enabled_in(Some(&request)));
+        drop((tenant, request));
+
+        drop(context!(user_id = 1));
+    });
+
+    assert_eq!(*counting.new.lock().unwrap(), 1);
+    assert_eq!(*counting.closed.lock().unwrap(), 1);
+}
+
+#[test]
+fn test_audit() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("enabled", true);
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let records = records.clone();
+        move |record: AuditRecord| records.lock().unwrap().push(record)
+    };
+
+    let evaluator = AuditEvaluator::new(evaluator, sink).with_fields(["user"]);
+
+    with_default(evaluator, || {
+        context!(user = "alice", session = 1).in_scope(|| {
+            context!().in_scope(|| {
+                assert!(featureflag::is_enabled!("enabled", false));
+            });
+        });
+        assert!(!featureflag::is_enabled!("unknown", false));
+    });
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+
+    assert_eq!(records[0].feature(), "enabled");
+    assert_eq!(records[0].result(), Some(true));
+    assert_eq!(records[0].fields().len(), 1);
+    assert_eq!(
+        records[0].field("user").and_then(|v| v.as_str()),
+        Some("alice")
+    );
+
+    assert_eq!(records[1].feature(), "unknown");
+    assert_eq!(records[1].result(), None);
+    assert!(records[1].fields().is_empty());
+}
+
+#[test]
+fn test_audit_chained() {
+    let inner_records = Arc::new(Mutex::new(Vec::new()));
+    let outer_records = Arc::new(Mutex::new(Vec::new()));
+    let sink = |records: &Arc<Mutex<Vec<AuditRecord>>>| {
+        let records = records.clone();
+        move |record: AuditRecord| records.lock().unwrap().push(record)
+    };
+
+    let evaluator =
+        AuditEvaluator::new(TestEvaluator::new(), sink(&inner_records)).with_fields(["user"]);
+    let evaluator = AuditEvaluator::new(evaluator, sink(&outer_records)).with_fields(["session"]);
+
+    with_default(evaluator, || {
+        context!(user = "alice", session = 1).in_scope(|| {
+            featureflag::is_enabled!("feature", false);
+        });
+    });
+
+    let inner_records = inner_records.lock().unwrap();
+    assert_eq!(inner_records.len(), 1);
+    assert_eq!(inner_records[0].fields().len(), 1);
+    assert!(inner_records[0].field("user").is_some());
+
+    let outer_records = outer_records.lock().unwrap();
+    assert_eq!(outer_records.len(), 1);
+    assert_eq!(outer_records[0].fields().len(), 1);
+    assert!(outer_records[0].field("session").is_some());
+}
+
+#[test]
+fn test_audit_sampling() {
+    let count = Arc::new(Mutex::new(0));
+    let sink = {
+        let count = count.clone();
+        move |_: AuditRecord| *count.lock().unwrap() += 1
+    };
+
+    let evaluator = AuditEvaluator::new(TestEvaluator::new(), sink)
+        .sample_every(2)
+        .max_per_second(3);
+
+    with_default(evaluator, || {
+        for _ in 0..10 {
+            featureflag::is_enabled!("feature", false);
+        }
+    });
+
+    assert_eq!(*count.lock().unwrap(), 3);
+}
+
+#[test]
+fn test_evaluation_trace() {
+    let a = TestEvaluator::new();
+    a.set_feature("a", true);
+    a.set_feature("b", false);
+
+    let b = TestEvaluator::new();
+    b.set_feature("b", true);
+
+    let evaluator = a.filter(|feature| feature == "a").chain(b);
+
+    with_default(evaluator, || {
+        let detail = Feature::new("b", false).evaluate();
+        assert!(detail.is_enabled());
+        assert!(!detail.is_default());
+
+        let steps = detail.trace().steps();
+        assert_eq!(steps.len(), 2);
+        assert!(steps[0].evaluator().contains("Filter"));
+        assert_eq!(steps[0].result(), None);
+        assert_eq!(steps[1].evaluator(), "featureflag_test::TestEvaluator");
+        assert_eq!(steps[1].result(), Some(true));
+        assert_eq!(
+            detail.trace().decided_by(),
+            Some("featureflag_test::TestEvaluator")
+        );
+
+        let detail = Feature::new("unknown", true).evaluate();
+        assert!(detail.is_enabled());
+        assert!(detail.is_default());
+        assert_eq!(detail.trace().decided_by(), None);
+    });
+}
+
+#[test]
+fn test_exposure() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("a", true);
+
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let batches = batches.clone();
+        move |events: Vec<ExposureEvent>| batches.lock().unwrap().push(events)
+    };
+
+    let evaluator = Arc::new(
+        ExposureEvaluator::new(evaluator, sink)
+            .batch_size(10)
+            .flush_interval(Duration::from_secs(60)),
+    );
+
+    let (first, second) = with_default(evaluator.clone(), || {
+        let first = context!();
+        first.in_scope(|| {
+            featureflag::is_enabled!("a", false);
+            featureflag::is_enabled!("a", false);
+            featureflag::is_enabled!("b", false);
+        });
+
+        let second = context!();
+        second.in_scope(|| {
+            featureflag::is_enabled!("a", false);
+        });
+
+        (first.id(), second.id())
+    });
+
+    assert!(batches.lock().unwrap().is_empty());
+    evaluator.flush();
+
+    let batches = batches.lock().unwrap();
+    assert_eq!(batches.len(), 1);
+
+    let events = batches[0]
+        .iter()
+        .map(|event| (event.feature(), event.context_id(), event.result()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            ("a", first, Some(true)),
+            ("b", first, None),
+            ("a", second, Some(true)),
+        ]
+    );
+
+    // names of registered features are shared
+    assert_eq!(
+        batches[0][0].feature().as_ptr(),
+        batches[0][2].feature().as_ptr()
+    );
+}
+
+#[test]
+fn test_exposure_chained() {
+    let inner_events = Arc::new(Mutex::new(Vec::new()));
+    let outer_events = Arc::new(Mutex::new(Vec::new()));
+    let sink = |events: &Arc<Mutex<Vec<ExposureEvent>>>| {
+        let events = events.clone();
+        move |batch: Vec<ExposureEvent>| events.lock().unwrap().extend(batch)
+    };
+
+    let inner = Arc::new(ExposureEvaluator::new(
+        TestEvaluator::new(),
+        sink(&inner_events),
+    ));
+    let outer = Arc::new(ExposureEvaluator::new(inner.clone(), sink(&outer_events)));
+
+    with_default(outer.clone(), || {
+        context!().in_scope(|| {
+            featureflag::is_enabled!("a", false);
+            featureflag::is_enabled!("a", false);
+        });
+    });
+    inner.flush();
+    outer.flush();
+
+    assert_eq!(inner_events.lock().unwrap().len(), 1);
+    assert_eq!(outer_events.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_exposure_untracked_contexts() {
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let batches = batches.clone();
+        move |events: Vec<ExposureEvent>| batches.lock().unwrap().extend(events)
+    };
+    let evaluator = Arc::new(ExposureEvaluator::new(TestEvaluator::new(), sink));
+
+    // the evaluator is not notified about contexts, so it can't store exposures in them
+    let feature = Feature::new("a", false);
+    let (first, second) = with_default(evaluator.clone().filter_contexts(|_| false), || {
+        let first = context!(user_id = 1);
+        let second = context!(user_id = 2);
+        feature.is_enabled_in(Some(&first));
+        feature.is_enabled_in(Some(&first));
+        feature.is_enabled_in(Some(&second));
+        feature.is_enabled_in(None);
+        (first, second)
+    });
+    evaluator.flush();
+
+    let context_ids = batches
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|event| event.context_id())
+        .collect::<Vec<_>>();
+    assert_eq!(context_ids, [first.id(), second.id(), Context::root().id()]);
+}
+
+#[test]
+fn test_describe() {
+    let evaluator = TestEvaluator::new()
+        .filter(|feature| feature.starts_with("a"))
+        .chain(TestEvaluator::new());
+
+    let description = evaluator.describe();
+    assert_eq!(description.name(), "Chain");
+    assert_eq!(description.children().len(), 2);
+    assert_eq!(
+        description.to_string(),
+        "Chain\n  Filter\n    featureflag_test::TestEvaluator\n  featureflag_test::TestEvaluator"
+    );
+}
+
+#[test]
+fn test_memoize() {
+    let evaluator = TestEvaluator::new();
+    let calls = Arc::new(Mutex::new(0));
+    evaluator.set_feature("feature", {
+        let calls = calls.clone();
+        move |_: &Context| {
+            *calls.lock().unwrap() += 1;
+            true
+        }
+    });
+
+    with_default(evaluator.memoize(), || {
+        let context = context!();
+        for _ in 0..3 {
+            assert!(featureflag::is_enabled!(context: context, "feature", false));
+        }
+        assert_eq!(*calls.lock().unwrap(), 1);
+
+        context!().in_scope(|| {
+            assert!(featureflag::is_enabled!("feature", false));
+        });
+        assert_eq!(*calls.lock().unwrap(), 2);
+
+        for _ in 0..2 {
+            assert!(featureflag::is_enabled!("feature", false));
+        }
+        assert_eq!(*calls.lock().unwrap(), 4);
+    });
+}
+
+#[test]
+fn test_memoize_chained() {
+    let first = TestEvaluator::new();
+    first.set_feature("a", true);
+
+    let second = TestEvaluator::new();
+    second.set_feature("b", true);
+
+    // each memoized evaluator has its own cache in the context
+    with_default(first.memoize().chain(second.memoize()), || {
+        context!().in_scope(|| {
+            for _ in 0..2 {
+                assert!(featureflag::is_enabled!("a", false));
+                assert!(featureflag::is_enabled!("b", false));
+            }
+        });
+    });
+}
+
+#[test]
+fn test_fallback() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("set", false);
+
+    let evaluator = evaluator.fallback([("set", true), ("unset", true)]);
+
+    with_default(evaluator, || {
+        assert!(!featureflag::is_enabled!("set", false));
+        assert!(featureflag::is_enabled!("unset", false));
+        assert!(!featureflag::is_enabled!("unknown", false));
+    });
+}
+
+#[test]
+fn test_map_result() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("legacy_disabled", true);
+    evaluator.set_feature("other", true);
+
+    let evaluator = evaluator.map_result(|feature, result| match feature {
+        "legacy_disabled" => result.map(|enabled| !enabled),
+        "maintenance" => Some(false),
+        _ => result,
+    });
+
+    with_default(evaluator, || {
+        assert!(!featureflag::is_enabled!("legacy_disabled", true));
+        assert!(!featureflag::is_enabled!("maintenance", true));
+        assert!(featureflag::is_enabled!("other", false));
+        assert!(featureflag::is_enabled!("unset", true));
+    });
+}
+
+#[test]
+fn test_shadow() {
+    let primary = TestEvaluator::new();
+    primary.set_feature("same", true);
+    primary.set_feature("different", true);
+
+    let candidate = TestEvaluator::new();
+    candidate.set_feature("same", true);
+    candidate.set_feature("different", false);
+    candidate.set_feature("candidate_only", true);
+
+    let mismatches = Arc::new(Mutex::new(Vec::new()));
+    let evaluator = primary.shadow(candidate, {
+        let mismatches = mismatches.clone();
+        move |feature: &str, primary, candidate| {
+            mismatches
+                .lock()
+                .unwrap()
+                .push((feature.to_string(), primary, candidate))
+        }
+    });
+
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("same", false));
+        assert!(featureflag::is_enabled!("different", false));
+        assert!(!featureflag::is_enabled!("candidate_only", false));
+    });
+
+    assert_eq!(
+        *mismatches.lock().unwrap(),
+        [
+            ("different".to_string(), Some(true), Some(false)),
+            ("candidate_only".to_string(), None, Some(true)),
+        ]
+    );
+}
+
+#[test]
+fn test_either() {
+    fn select(use_test: bool) -> Either<TestEvaluator, NoEvaluator> {
+        if use_test {
+            let evaluator = TestEvaluator::new();
+            evaluator.set_feature("feature", true);
+            Either::Left(evaluator)
+        } else {
+            Either::Right(NoEvaluator)
+        }
+    }
+
+    with_default(select(true), || {
+        assert!(featureflag::is_enabled!("feature", false));
+    });
+
+    with_default(select(false), || {
+        assert!(!featureflag::is_enabled!("feature", false));
+    });
+}
+
+#[test]
+fn test_vec_chain() {
+    let first = TestEvaluator::new();
+    first.set_feature("first", true);
+    first.set_feature("both", false);
+
+    let second = TestEvaluator::new();
+    second.set_feature("second", true);
+    second.set_feature("both", true);
+
+    let evaluator = vec![first.into_ref(), second.into_ref()];
+
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("first", false));
+        assert!(featureflag::is_enabled!("second", false));
+        assert!(!featureflag::is_enabled!("both", true));
+        assert!(!featureflag::is_enabled!("neither", false));
+    });
+
+    with_default(Vec::<EvaluatorRef>::new(), || {
+        assert!(featureflag::is_enabled!("first", true));
+    });
+}
+
+#[test]
+fn test_indexed_chain() {
+    use featureflag::evaluator::IndexedChain;
+
+    struct Unknown;
+
+    impl Evaluator for Unknown {
+        fn is_enabled(&self, feature: &str, _context: &Context) -> Option<bool> {
+            (feature != "d").then_some(false)
+        }
+    }
+
+    let a = TestEvaluator::with_features([("a", true)]);
+    let b = TestEvaluator::with_features([("a", false), ("b", true), ("c", true)]);
+    let chain = IndexedChain::new([
+        a.clone().into_ref(),
+        Unknown.into_ref(),
+        b.clone().into_ref(),
+    ]);
+
+    with_default(chain, || {
+        // known flags are routed to their evaluators in chain order
+        assert!(featureflag::is_enabled!("a", false));
+        assert!(!featureflag::is_enabled!("b", true));
+
+        // other flags only go to evaluators without known flags
+        assert!(!featureflag::is_enabled!("c", true));
+        assert!(featureflag::is_enabled!("d", true));
+    });
+
+    let chain = IndexedChain::new([a.clone().into_ref(), b.clone().into_ref()]);
+    a.set_feature("b", false);
+    assert_eq!(chain.is_enabled("b", &Context::root()), Some(true));
+    chain.reindex();
+    assert_eq!(chain.is_enabled("b", &Context::root()), Some(false));
+}
+
+#[test]
+fn test_namespace() {
+    let experiments = TestEvaluator::new();
+    experiments.set_feature("new_ui", true);
+    experiments.set_feature("exp.new_ui", false);
+
+    let ops = TestEvaluator::new();
+    ops.set_feature("ops.retry", true);
+    ops.set_feature("ops.retry.fast", false);
+
+    let fast = TestEvaluator::new();
+    fast.set_feature("ops.retry.fast", true);
+
+    let other = TestEvaluator::new();
+    other.set_feature("other", true);
+
+    let evaluator = Namespace::new()
+        .route_stripped("exp.", experiments)
+        .route("ops.", ops)
+        .route("ops.retry.", fast)
+        .otherwise(other);
+
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("exp.new_ui", false));
+        assert!(featureflag::is_enabled!("ops.retry", false));
+        assert!(featureflag::is_enabled!("ops.retry.fast", false));
+        assert!(featureflag::is_enabled!("other", false));
+        assert!(!featureflag::is_enabled!("new_ui", false));
+    });
+}
+
+#[test]
+fn test_map_names() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("new_name", true);
+
+    let aliases = Arc::new(Mutex::new(Vec::new()));
+    let renames = HashMap::from([("old_name".to_string(), "new_name".to_string())]);
+    let evaluator = evaluator.map_names(renames).on_alias({
+        let aliases = aliases.clone();
+        move |old: &str, new: &str| aliases.lock().unwrap().push(format!("{old} -> {new}"))
+    });
+
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("old_name", false));
+        assert!(featureflag::is_enabled!("new_name", false));
+    });
+
+    assert_eq!(*aliases.lock().unwrap(), ["old_name -> new_name"]);
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("v2.feature", true);
+    let evaluator = evaluator.map_names(|name: &str| Some(format!("v2.{name}")));
+
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("feature", false));
+    });
+}
+
+#[test]
+fn test_switch_by() {
+    let acme = TestEvaluator::new();
+    acme.set_feature("feature", true);
+
+    let globex = TestEvaluator::new();
+    globex.set_feature("feature", false);
+    globex.set_feature("globex_only", true);
+
+    let default = TestEvaluator::new();
+    default.set_feature("default_only", true);
+
+    let evaluator = SwitchBy::new("tenant")
+        .case("acme", acme)
+        .case("globex", globex)
+        .otherwise(default);
+
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("default_only", false));
+
+        let acme = context!(tenant = "acme");
+        assert!(featureflag::is_enabled!(context: acme, "feature", false));
+        assert!(!featureflag::is_enabled!(context: acme, "default_only", false));
+
+        let child = context!(parent: acme, user = "bob");
+        assert!(featureflag::is_enabled!(context: child, "feature", false));
+
+        let globex = context!(tenant = "globex");
+        assert!(!featureflag::is_enabled!(context: globex, "feature", true));
+        assert!(featureflag::is_enabled!(context: globex, "globex_only", false));
+
+        let other = context!(tenant = "initech");
+        assert!(featureflag::is_enabled!(context: other, "default_only", false));
+    });
+}
+
+#[test]
+fn test_tenant_router() {
+    let created = Arc::new(Mutex::new(Vec::new()));
+    let fallback = TestEvaluator::new();
+    fallback.set_feature("feature", false);
+
+    let evaluator = Arc::new(
+        TenantRouter::new("tenant", {
+            let created = created.clone();
+            move |tenant: &str| {
+                created.lock().unwrap().push(tenant.to_string());
+                let evaluator = TestEvaluator::new();
+                evaluator.set_feature("feature", tenant == "acme");
+                evaluator
+            }
+        })
+        .otherwise(fallback)
+        .idle_timeout(Duration::ZERO),
+    );
+
+    with_default(evaluator.clone(), || {
+        assert!(!featureflag::is_enabled!("feature", true));
+
+        let acme = context!(tenant = "acme");
+        let acme_user = context!(parent: acme, user = "bob");
+        let globex = context!(tenant = "globex");
+        assert!(featureflag::is_enabled!(context: acme, "feature", false));
+        assert!(featureflag::is_enabled!(context: acme_user, "feature", false));
+        assert!(!featureflag::is_enabled!(context: globex, "feature", true));
+
+        // Contexts created before eviction keep their evaluator.
+        evaluator.evict_idle();
+        assert_eq!(evaluator.tenant_count(), 0);
+        assert!(featureflag::is_enabled!(context: acme, "feature", false));
+
+        let acme = context!(tenant = "acme");
+        assert!(featureflag::is_enabled!(context: acme, "feature", false));
+    });
+
+    assert_eq!(*created.lock().unwrap(), ["acme", "globex", "acme"]);
+}
+
+#[test]
+fn test_tenant_router_reentrant_factory() {
+    // the factory can create contexts for other tenants of the same router
+    let evaluator = Arc::new(TenantRouter::new("tenant", |tenant: &str| {
+        if tenant == "reseller" {
+            let _ = context!(tenant = "acme");
+        }
+        TestEvaluator::new()
+    }));
+
+    with_default(evaluator.clone(), || {
+        let _ = context!(tenant = "reseller");
+    });
+    assert_eq!(evaluator.tenant_count(), 2);
+}
+
+#[test]
+fn test_persistent_cache() {
+    let path = std::env::temp_dir().join(format!(
+        "featureflag-test-persistent-cache-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let backend = TestEvaluator::new();
+    backend.set_feature("enabled", true);
+    backend.set_feature("disabled", false);
+
+    let evaluator = PersistentCache::new(backend, &path).unwrap();
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("enabled", false));
+        assert!(!featureflag::is_enabled!("disabled", true));
+        assert!(featureflag::is_enabled!("unset", true));
+    });
+
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "disabled=false\nenabled=true\n"
+    );
+
+    // A backend that hasn't loaded anything yet is served from the cache.
+    let evaluator = PersistentCache::new(TestEvaluator::new(), &path).unwrap();
+    with_default(evaluator, || {
+        assert!(featureflag::is_enabled!("enabled", false));
+        assert!(!featureflag::is_enabled!("disabled", true));
+    });
+
+    // Names are escaped, and values are written when flushed.
+    let backend = TestEvaluator::new();
+    backend.set_feature("a=b\nc%", true);
+    let evaluator = PersistentCache::new(backend, &path).unwrap();
+    assert_eq!(
+        evaluator.is_enabled("a=b\nc%", &Context::root()),
+        Some(true)
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "disabled=false\nenabled=true\n"
+    );
+    evaluator.flush().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "a%3Db%0Ac%25=true\ndisabled=false\nenabled=true\n"
+    );
+    let evaluator = PersistentCache::new(TestEvaluator::new(), &path).unwrap();
+    assert_eq!(
+        evaluator.is_enabled("a=b\nc%", &Context::root()),
+        Some(true)
+    );
+
+    // A corrupt file is ignored, and reported until it is replaced.
+    std::fs::write(&path, "enabled=tr").unwrap();
+    let backend = TestEvaluator::new();
+    let evaluator = PersistentCache::new(backend.clone(), &path).unwrap();
+    assert_eq!(evaluator.is_enabled("enabled", &Context::root()), None);
+    let health = evaluator.health();
+    assert!(health.is_ready());
+    assert!(
+        health
+            .reason()
+            .is_some_and(|reason| reason.starts_with("ignored corrupt feature cache"))
+    );
+
+    backend.set_feature("enabled", true);
+    assert_eq!(
+        evaluator.is_enabled("enabled", &Context::root()),
+        Some(true)
+    );
+    evaluator.flush().unwrap();
+    assert_eq!(evaluator.health(), featureflag::evaluator::Health::ready());
+    drop(evaluator);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_bootstrap() {
+    let bootstrap = featureflag::bootstrap!("data/bootstrap.json");
+    assert_eq!(bootstrap.get("enabled"), Some(true));
+    assert_eq!(bootstrap.get("disabled"), Some(false));
+    assert_eq!(bootstrap.get("default"), None);
+    assert_eq!(bootstrap.get("escaped\"name"), Some(true));
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("disabled", true);
+
+    with_default(evaluator.chain(bootstrap), || {
+        assert!(featureflag::is_enabled!("enabled", false));
+        assert!(featureflag::is_enabled!("disabled", false));
+        assert!(featureflag::is_enabled!("default", true));
+    });
+
+    assert!(BootstrapEvaluator::from_json("{}").is_ok());
+    assert_eq!(
+        BootstrapEvaluator::from_json(r#"{ "a": 1 }"#)
+            .unwrap_err()
+            .to_string(),
+        r#"expected `true`, `false`, `null` or an object for feature "a""#
+    );
+    assert!(BootstrapEvaluator::from_json(r#"{ "a": 01 }"#).is_err());
+    assert!(BootstrapEvaluator::from_json("[]").is_err());
+    assert!(BootstrapEvaluator::from_json(r#"{ "a": true, }"#).is_err());
+    assert!(BootstrapEvaluator::from_json(r#"{ "a": true } x"#).is_err());
+}
+
+#[test]
+fn test_bootstrap_escapes() {
+    let bootstrap =
+        BootstrapEvaluator::from_json(r#"{ "\b\f\n\r\t\/\\\"\u00e9\ud83d\ude00": true }"#).unwrap();
+    assert_eq!(bootstrap.get("\u{8}\u{c}\n\r\t/\\\"é😀"), Some(true));
+
+    for json in [
+        r#"{ "\ud83d": true }"#,
+        r#"{ "\ud83d\u0041": true }"#,
+        r#"{ "\ude00": true }"#,
+        r#"{ "\u+0ab": true }"#,
+        r#"{ "\x": true }"#,
+        "{ \"\n\": true }",
+    ] {
+        assert!(BootstrapEvaluator::from_json(json).is_err(), "{json}");
+    }
+}
+
+#[test]
+fn test_bootstrap_env() {
+    let base = featureflag::bootstrap!("data/bootstrap_env.json");
+    assert_eq!(base.get("base"), Some(true));
+    assert_eq!(base.get("new_ui"), Some(false));
+    assert_eq!(base.get("beta"), Some(true));
+    assert_eq!(base.get("staging_only"), None);
+
+    let staging = featureflag::bootstrap!("data/bootstrap_env.json", env = "staging");
+    assert_eq!(staging.get("base"), Some(true));
+    assert_eq!(staging.get("new_ui"), Some(true));
+    assert_eq!(staging.get("beta"), Some(true));
+    assert_eq!(staging.get("staging_only"), Some(false));
+
+    let env = String::from("production");
+    let production = featureflag::bootstrap!("data/bootstrap_env.json", env = &env);
+    assert_eq!(production.get("new_ui"), Some(false));
+    assert_eq!(production.get("beta"), None);
+    assert_eq!(production.get("staging_only"), None);
+
+    assert!(BootstrapEvaluator::from_json_for_env(r#"{ "a": {} }"#, "staging").is_ok());
+    assert_eq!(
+        BootstrapEvaluator::from_json_for_env(r#"{ "a": { "b": {} } }"#, "b")
+            .unwrap_err()
+            .to_string(),
+        r#"expected `true`, `false` or `null` for feature "a" in environment "b""#
+    );
+    assert!(BootstrapEvaluator::from_json_for_env(r#"{ "a": { "b": true "#, "b").is_err());
+}
+
+#[test]
+fn test_set_default() {
+    let first = TestEvaluator::new();
+    first.set_feature("feature", true);
+
+    let second = TestEvaluator::new();
+    second.set_feature("feature", false);
+
+    assert!(!featureflag::is_enabled!("feature", false));
+
+    let guard = set_default(first);
+    assert!(featureflag::is_enabled!("feature", false));
+
+    {
+        let _guard = set_default(second);
+        assert!(!featureflag::is_enabled!("feature", true));
+    }
+    assert!(featureflag::is_enabled!("feature", false));
+
+    drop(guard);
+    assert!(!featureflag::is_enabled!("feature", false));
+}
+
+#[test]
+fn test_current() {
+    assert!(featureflag::evaluator::current().is_none());
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("feature", true);
+
+    let current = with_default(evaluator, featureflag::evaluator::current).unwrap();
+    assert!(featureflag::evaluator::current().is_none());
+
+    with_default(current, || {
+        assert!(featureflag::is_enabled!("feature", false));
+    });
+}
+
+#[test]
+fn test_set_thread_default_scoped() {
+    std::thread::spawn(|| {
+        let first = TestEvaluator::new();
+        first.set_feature("feature", true);
+
+        let second = TestEvaluator::new();
+        second.set_feature("feature", false);
+
+        {
+            let _guard = set_thread_default_scoped(first.clone());
+            assert!(featureflag::is_enabled!("feature", false));
+        }
+        assert!(!featureflag::is_enabled!("feature", false));
+
+        set_thread_default(first);
+        assert!(featureflag::is_enabled!("feature", false));
+
+        let guard = set_thread_default_scoped(second);
+        assert!(!featureflag::is_enabled!("feature", true));
+        assert!(try_set_thread_default(NoEvaluator).is_err());
+
+        drop(guard);
+        assert!(featureflag::is_enabled!("feature", false));
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn test_combinator_conformance() {
+    fn base() -> TestEvaluator {
+        TestEvaluator::with_features([("a", true), ("b", false), ("exp.a", false)])
+    }
+    let features = &["a", "b", "c", "exp.a"];
+
+    featureflag_test::conformance::run(|| base().memoize(), features);
+    featureflag_test::conformance::run(|| base().fallback([("c", true)]), features);
+    featureflag_test::conformance::run(|| base().filter(|f| f != "b"), features);
+    featureflag_test::conformance::run(
+        || base().filter_contexts(|fields| fields.get("user").is_some()),
+        features,
+    );
+    featureflag_test::conformance::run(|| base().map_result(|_, r| r.map(|e| !e)), features);
+    featureflag_test::conformance::run(
+        || {
+            Namespace::new()
+                .route_stripped("exp.", base())
+                .otherwise(base())
+        },
+        features,
+    );
+    featureflag_test::conformance::run(
+        || SwitchBy::new("user").case("conformance", base()),
+        features,
+    );
+}
+
+#[test]
+fn test_known_flags() {
+    let bootstrap =
+        |names: &[&str]| BootstrapEvaluator::new(names.iter().map(|name| (*name, true)));
+
+    let namespace = Namespace::new()
+        .route_stripped("exp.", bootstrap(&["a"]))
+        .route("ops.", bootstrap(&["ops.b", "c"]))
+        .otherwise(bootstrap(&["exp.d", "e"]));
+    assert_eq!(namespace.known_flags().unwrap(), ["e", "exp.a", "ops.b"]);
+
+    let chain = bootstrap(&["b", "a"]).chain(bootstrap(&["a", "c"]));
+    assert_eq!(chain.known_flags().unwrap(), ["a", "b", "c"]);
+
+    let filtered = chain.filter(|name| name != "b");
+    assert_eq!(filtered.known_flags().unwrap(), ["a", "c"]);
+
+    assert_eq!(NoEvaluator.known_flags().unwrap(), Vec::<String>::new());
+    assert!(
+        NoEvaluator
+            .chain(TenantRouter::new("tenant", |_: &str| NoEvaluator))
+            .known_flags()
+            .is_none()
+    );
+}
+
+#[test]
+fn test_shutdown() {
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let batches = batches.clone();
+        move |events: Vec<ExposureEvent>| batches.lock().unwrap().push(events)
+    };
+
+    let exposure = ExposureEvaluator::new(TestEvaluator::new(), sink)
+        .batch_size(10)
+        .flush_interval(Duration::from_secs(60));
+    let evaluator = TestEvaluator::new().chain(exposure);
+
+    with_default(evaluator, || {
+        featureflag::is_enabled!("a", false);
+        assert!(batches.lock().unwrap().is_empty());
+
+        featureflag::shutdown();
+        assert_eq!(batches.lock().unwrap().len(), 1);
+        assert_eq!(batches.lock().unwrap()[0][0].feature(), "a");
+    });
+
+    // shutting down without an evaluator does nothing
+    featureflag::shutdown();
+}
+
+#[test]
+fn test_registration_info() {
+    use featureflag::evaluator::{RegistrationInfo, RegistrationKind};
+
+    #[derive(Default)]
+    struct Recording(Mutex<Vec<RegistrationInfo>>);
+
+    impl Evaluator for Recording {
+        fn is_enabled(&self, _feature: &str, _context: &Context) -> Option<bool> {
+            None
+        }
+
+        fn on_registration(&self, info: &RegistrationInfo) {
+            self.0.lock().unwrap().push(*info);
+        }
+    }
+
+    let recording = Arc::new(Recording::default());
+    with_default(recording.clone(), || {});
+    drop(set_default(recording.clone().into_ref()));
+    let evaluator = recording.clone();
+    std::thread::spawn(move || set_thread_default(evaluator))
+        .join()
+        .unwrap();
+
+    let infos = recording.0.lock().unwrap().clone();
+    assert_eq!(
+        infos,
+        [
+            RegistrationInfo::new(RegistrationKind::Scoped, 1),
+            RegistrationInfo::new(RegistrationKind::Scoped, 2),
+            RegistrationInfo::new(RegistrationKind::Thread, 3),
+        ]
+    );
+    assert!(infos[0].is_first());
+
+    // registering another evaluator starts counting again
+    let other = Arc::new(Recording::default());
+    with_default(other.clone(), || {});
+    with_default(other.clone().into_ref(), || {});
+    assert_eq!(
+        *other.0.lock().unwrap(),
+        [
+            RegistrationInfo::new(RegistrationKind::Scoped, 1),
+            RegistrationInfo::new(RegistrationKind::Scoped, 2),
+        ]
+    );
+}
+
+#[test]
+fn test_health() {
+    use featureflag::evaluator::{Health, HealthStatus, health, readiness};
+
+    struct Cold;
+
+    impl Evaluator for Cold {
+        fn is_enabled(&self, _feature: &str, _context: &Context) -> Option<bool> {
+            None
+        }
+
+        fn health(&self) -> Health {
+            Health::not_ready("flags not loaded")
+        }
+    }
+
+    assert_eq!(health(), Health::not_ready("no evaluator set"));
+
+    let refreshed = std::time::UNIX_EPOCH + Duration::from_secs(60);
+    let stale = Health::degraded("stale").with_last_refresh(refreshed);
+    let combined = Health::combine([
+        Health::ready().with_last_refresh(refreshed + Duration::from_secs(60)),
+        stale.clone(),
+        Health::degraded("slow"),
+    ]);
+    assert_eq!(
+        combined.status(),
+        &HealthStatus::Degraded("stale; slow".into())
+    );
+    assert_eq!(combined.last_refresh(), Some(refreshed));
+    assert!(combined.is_ready());
+    assert_eq!(Health::combine([]), Health::ready());
+
+    with_default(TestEvaluator::new().chain(NoEvaluator), || {
+        assert_eq!(readiness(), Ok(Health::ready()));
+    });
+
+    with_default(TestEvaluator::new().chain(Cold), || {
+        let health = readiness().unwrap_err();
+        assert_eq!(health.reason(), Some("flags not loaded"));
+        assert_eq!(health.to_string(), "not ready: flags not loaded");
+    });
+}
+
+#[test]
+fn test_lazy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use featureflag::evaluator::Lazy;
+
+    let built = Arc::new(AtomicUsize::new(0));
+    let evaluator = Lazy::new({
+        let built = built.clone();
+        move || {
+            built.fetch_add(1, Ordering::Relaxed);
+            let evaluator = TestEvaluator::new();
+            evaluator.set_feature("feature", true);
+            evaluator
+        }
+    });
+
+    assert!(evaluator.get().is_none());
+    assert_eq!(evaluator.describe().summary(), Some("not built"));
+    assert_eq!(built.load(Ordering::Relaxed), 0);
+
+    let evaluator = Arc::new(evaluator);
+    with_default(evaluator.clone(), || {
+        assert_eq!(built.load(Ordering::Relaxed), 0);
+        assert!(featureflag::is_enabled!("feature", false));
+        assert_eq!(built.load(Ordering::Relaxed), 1);
+    });
+
+    assert!(evaluator.get().is_some());
+    assert_eq!(built.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_required_fields() {
+    use featureflag::evaluator::{Health, MissingFields, RequiredFields};
+
+    const ROLLOUT: Feature =
+        featureflag::feature!("required.rollout", false, requires = "user_id, org_id");
+
+    let missing = Arc::new(Mutex::new(Vec::<MissingFields>::new()));
+    let evaluator = Arc::new(
+        RequiredFields::from_registry(TestEvaluator::new())
+            .require("required.other", ["user_id"])
+            .on_missing({
+                let missing = missing.clone();
+                move |fields| missing.lock().unwrap().push(fields.clone())
+            }),
+    );
+    assert_eq!(evaluator.health(), Health::ready());
+
+    with_default(evaluator.clone(), || {
+        assert!(!ROLLOUT.is_enabled());
+
+        let user = context!(user_id = 1);
+        let org = context!(parent: user, org_id = 2);
+        assert!(!ROLLOUT.is_enabled_in(Some(&user)));
+        assert!(!ROLLOUT.is_enabled_in(Some(&org)));
+        assert!(!featureflag::is_enabled!(context: org, "required.other", false));
+        assert!(!featureflag::is_enabled!("unrequired", false));
+    });
+
+    let missing = missing.lock().unwrap();
+    assert_eq!(missing.len(), 2);
+    assert_eq!(missing[0].feature(), "required.rollout");
+    assert_eq!(missing[0].missing(), ["user_id", "org_id"]);
+    assert_eq!(missing[1].missing(), ["org_id"]);
+    assert_eq!(
+        missing[1].to_string(),
+        "feature \"required.rollout\" evaluated without required fields: org_id"
+    );
+
+    // only the first evaluation of each feature is reported by the health
+    assert_eq!(
+        evaluator.health(),
+        Health::degraded(
+            "feature \"required.rollout\" evaluated without required fields: user_id, org_id"
+        )
+    );
+}
+
+#[test]
+fn test_context_cache() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use featureflag::{context::ContextRef, evaluator::ContextCache};
+
+    struct Remote {
+        cache: ContextCache<String, Option<bool>>,
+        lookups: AtomicUsize,
+    }
+
+    impl Evaluator for Remote {
+        fn is_enabled(&self, feature: &str, context: &Context) -> Option<bool> {
+            self.cache
+                .get_or_insert_with(context, feature.to_string(), || {
+                    self.lookups.fetch_add(1, Ordering::Relaxed);
+                    Some(true)
+                })
+        }
+
+        fn on_close_context(&self, context: ContextRef<'_>) {
+            self.cache.remove_context(context.id());
+        }
+    }
+
+    let remote = Arc::new(Remote {
+        cache: ContextCache::new(3),
+        lookups: AtomicUsize::new(0),
+    });
+
+    with_default(remote.clone(), || {
+        // nothing is cached in the root context
+        assert!(featureflag::is_enabled!("a", false));
+        assert!(featureflag::is_enabled!("a", false));
+        assert_eq!(remote.lookups.load(Ordering::Relaxed), 2);
+        assert!(remote.cache.is_empty());
+
+        let first = context!(user_id = 1u64);
+        assert!(featureflag::is_enabled!(context: first, "a", false));
+        assert!(featureflag::is_enabled!(context: first, "a", false));
+        assert!(featureflag::is_enabled!(context: first, "b", false));
+        assert_eq!(remote.lookups.load(Ordering::Relaxed), 4);
+        assert_eq!(remote.cache.get(&first, "a"), Some(Some(true)));
+        assert_eq!(remote.cache.len(), 2);
+
+        // entries of closed contexts are removed
+        let closed = context!(user_id = 2u64);
+        assert!(featureflag::is_enabled!(context: closed, "a", false));
+        assert_eq!(remote.cache.len(), 3);
+        drop(closed);
+        assert_eq!(remote.cache.len(), 2);
+
+        // entries of the oldest contexts are evicted when the cache is full
+        let second = context!(user_id = 3u64);
+        for feature in ["a", "b"] {
+            assert!(featureflag::is_enabled!(context: second, feature, false));
+        }
+        assert_eq!(remote.cache.len(), 2);
+        assert_eq!(remote.cache.get(&first, "a"), None);
+        assert_eq!(remote.cache.get(&second, "b"), Some(Some(true)));
+
+        // a single context is bounded by the capacity as well
+        for feature in ["c", "d", "e"] {
+            remote.cache.insert(&second, feature.to_string(), None);
+        }
+        assert_eq!(remote.cache.len(), 3);
+    });
+}
+
+#[test]
+fn test_sticky() {
+    use featureflag::evaluator::{MemoryStickyStore, Sticky};
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("rollout", true);
+    let store = Arc::new(MemoryStickyStore::new());
+
+    with_default(
+        Sticky::new(evaluator.clone(), "user_id", store.clone()),
+        || {
+            let user = context!(user_id = 1u64);
+            let session = context!(parent: user, session_id = "abc");
+            assert!(featureflag::is_enabled!(context: session, "rollout", false));
+            assert_eq!(store.len(), 1);
+
+            // stored assignments take precedence over the evaluator
+            evaluator.set_feature("rollout", false);
+            assert!(featureflag::is_enabled!(context: user, "rollout", false));
+            assert!(
+                Feature::new("rollout", false)
+                    .evaluate_in(Some(&session))
+                    .is_enabled()
+            );
+
+            // new keys get the current assignment
+            let other = context!(user_id = "2");
+            assert!(!featureflag::is_enabled!(context: other, "rollout", true));
+            assert_eq!(store.len(), 2);
+
+            // contexts without a key and unset features are not stored
+            assert!(!featureflag::is_enabled!("rollout", true));
+            assert!(!featureflag::is_enabled!(context: user, "unset", false));
+            assert_eq!(store.len(), 2);
+        },
+    );
+}
@@ -0,0 +1,113 @@
+#![allow(missing_docs)]
+
+use std::collections::HashMap;
+
+use featureflag::{
+    Feature,
+    evaluator::with_default,
+    events::{AtomicFlag, FlagChanged, FlagEvents},
+};
+use featureflag_test::TestEvaluator;
+
+fn changed(name: &str, old: Option<bool>, new: Option<bool>) -> FlagChanged {
+    FlagChanged {
+        name: name.into(),
+        old,
+        new,
+    }
+}
+
+#[test]
+fn test_subscribe() {
+    let events = FlagEvents::new();
+    let all = events.subscribe();
+    let checkout = events.clone().subscribe_to(["checkout"]);
+
+    events.publish(changed("checkout", None, Some(true)));
+    events.publish(changed("search", Some(true), Some(false)));
+
+    assert_eq!(
+        all.try_iter().collect::<Vec<_>>(),
+        [
+            changed("checkout", None, Some(true)),
+            changed("search", Some(true), Some(false)),
+        ]
+    );
+    assert_eq!(
+        checkout.try_iter().collect::<Vec<_>>(),
+        [changed("checkout", None, Some(true))]
+    );
+
+    drop(all);
+    events.publish(changed("checkout", Some(true), Some(false)));
+    assert_eq!(
+        checkout.recv(),
+        Some(changed("checkout", Some(true), Some(false)))
+    );
+
+    drop(events);
+    assert_eq!(checkout.recv(), None);
+}
+
+#[test]
+fn test_prune_subscribers() {
+    let events = FlagEvents::new();
+    let checkout = events.subscribe_to(["checkout"]);
+    let flag = AtomicFlag::watch(&Feature::new("checkout", false), &events);
+    assert_eq!(events.subscriber_count(), 2);
+
+    // dropped subscribers are removed even if no events are delivered to them
+    drop((checkout, flag));
+    assert_eq!(events.subscriber_count(), 0);
+    events.publish(changed("search", None, Some(true)));
+    let _search = events.subscribe_to(["search"]);
+    assert_eq!(events.subscriber_count(), 1);
+}
+
+#[test]
+fn test_publish_changes() {
+    let events = FlagEvents::new();
+    let subscription = events.subscribe();
+
+    let old = HashMap::from([("a".to_string(), true), ("b".to_string(), true)]);
+    let new = HashMap::from([("b".to_string(), true), ("c".to_string(), false)]);
+    events.publish_changes(&old, &new);
+
+    assert_eq!(
+        subscription.try_iter().collect::<Vec<_>>(),
+        [
+            changed("a", Some(true), None),
+            changed("c", None, Some(false))
+        ]
+    );
+}
+
+#[test]
+fn test_atomic_flag() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("checkout", true);
+
+    let events = FlagEvents::new();
+    let flag = with_default(evaluator.clone(), || {
+        AtomicFlag::watch(&Feature::new("checkout", false), &events)
+    });
+    assert_eq!(flag.name(), "checkout");
+    assert!(flag.is_enabled());
+
+    events.publish(changed("search", None, Some(false)));
+    assert!(flag.is_enabled());
+    events.publish(changed("checkout", Some(true), Some(false)));
+    assert!(!flag.is_enabled());
+    events.publish(changed("checkout", Some(false), None));
+    assert!(!flag.is_enabled());
+
+    let clone = flag.clone();
+    with_default(evaluator, || clone.refresh());
+    assert!(flag.is_enabled());
+
+    let unset = AtomicFlag::new(&Feature::new("checkout", true));
+    assert!(unset.is_enabled());
+
+    drop((flag, clone));
+    events.publish(changed("checkout", None, Some(false)));
+}
@@ -1,6 +1,8 @@
 #![allow(missing_docs)]
 
-use featureflag::{Feature, evaluator::with_default};
+use std::sync::Arc;
+
+use featureflag::{Feature, evaluator::with_default, feature::intern};
 use featureflag_test::TestEvaluator;
 
 #[test]
@@ -50,3 +52,180 @@ fn test_feature_macro() {
         assert!(!UNKNOWN_FALSE.is_enabled());
     });
 }
+
+#[test]
+fn test_expression_names() {
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("checkout.enabled", true);
+    evaluator.set_feature("checkout.disabled", false);
+
+    with_default(evaluator, || {
+        const ENABLED: Feature = featureflag::feature!(concat!("checkout", ".enabled"), false);
+        assert_eq!(ENABLED.name(), "checkout.enabled");
+        assert!(ENABLED.is_enabled());
+
+        let name = String::from("checkout.disabled");
+        assert!(!featureflag::feature!(name.as_str(), true).is_enabled());
+        assert!(!featureflag::is_enabled!(&name, true));
+
+        let context = featureflag::context!();
+        assert!(
+            featureflag::is_enabled!(context: context, &*format!("checkout.{}", "enabled"), false)
+        );
+        assert!(
+            featureflag::is_enabled!(context: context, &*format!("checkout.{}", "unknown"), true)
+        );
+    });
+}
+
+#[test]
+fn test_feature_gated() {
+    struct Algo(u64);
+
+    impl Algo {
+        fn old_algo(&self, n: u64) -> u64 {
+            self.0 + n
+        }
+
+        #[featureflag::feature_gated("new_algo", fallback = Self::old_algo)]
+        fn new_algo(&self, n: u64) -> u64 {
+            self.0 * n
+        }
+    }
+
+    #[featureflag::feature_gated("new_default", default = true)]
+    fn new_default() -> Option<&'static str> {
+        Some("new")
+    }
+
+    let algo = Algo(3);
+    let evaluator = TestEvaluator::new();
+
+    with_default(evaluator.clone(), || {
+        assert_eq!(algo.new_algo(4), 7);
+        assert_eq!(new_default(), Some("new"));
+
+        evaluator.set_feature("new_algo", true);
+        evaluator.set_feature("new_default", false);
+
+        assert_eq!(algo.new_algo(4), 12);
+        assert_eq!(new_default(), None);
+    });
+}
+
+mod flags {
+    featureflag::flags_from_file!("tests/data/flags.toml");
+}
+
+#[test]
+fn test_flags_from_file() {
+    assert_eq!(flags::FAST_PATH.name(), "fast_path");
+    assert_eq!(flags::CHECKOUT_NEW_FLOW.name(), "checkout.new_flow");
+    assert_eq!(
+        flags::ALL.iter().map(Feature::name).collect::<Vec<_>>(),
+        ["fast_path", "checkout.new_flow"]
+    );
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("fast_path", true);
+
+    with_default(evaluator, || {
+        assert!(flags::FAST_PATH.is_enabled());
+        assert!(flags::CHECKOUT_NEW_FLOW.is_enabled());
+    });
+}
+
+#[derive(featureflag::FeatureFlags)]
+enum Flags {
+    NewUi,
+    #[featureflag(name = "fast-path", default = true)]
+    FastPath,
+}
+
+#[test]
+fn test_derive_feature_flags() {
+    assert_eq!(Flags::NewUi.name(), "new_ui");
+    assert_eq!(Flags::FastPath.feature().name(), "fast-path");
+    assert_eq!(
+        Flags::iter().map(Flags::name).collect::<Vec<_>>(),
+        ["new_ui", "fast-path"]
+    );
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("new_ui", true);
+
+    with_default(evaluator, || {
+        assert!(Flags::NewUi.is_enabled());
+        assert!(Flags::FastPath.is_enabled());
+        assert!(Flags::NewUi.is_enabled_in(Some(&featureflag::context!())));
+    });
+}
+
+#[test]
+fn test_name_style() {
+    use featureflag::feature::NameStyle;
+
+    assert!(NameStyle::KebabCase.matches("new-ui"));
+    assert!(NameStyle::KebabCase.matches("checkout.new-flow-2"));
+    assert!(!NameStyle::KebabCase.matches("new_ui"));
+    assert!(!NameStyle::KebabCase.matches("New-UI"));
+    assert!(!NameStyle::KebabCase.matches("new--ui"));
+    assert!(!NameStyle::KebabCase.matches("new-ui-"));
+    assert!(!NameStyle::KebabCase.matches("checkout..flow"));
+    assert!(!NameStyle::KebabCase.matches("2fa"));
+    assert!(!NameStyle::KebabCase.matches(""));
+
+    assert!(NameStyle::SnakeCase.matches("new_ui"));
+    assert!(NameStyle::SnakeCase.matches("checkout.new_flow"));
+    assert!(!NameStyle::SnakeCase.matches("new-ui"));
+    assert!(!NameStyle::SnakeCase.matches("_new_ui"));
+
+    assert!(NameStyle::SnakeCase.matches_namespace("my_crate::ui"));
+    assert!(NameStyle::SnakeCase.matches_namespace("checkout"));
+    assert!(!NameStyle::SnakeCase.matches_namespace("my_crate::"));
+    assert!(!NameStyle::SnakeCase.matches_namespace("my_crate:ui"));
+    assert!(!NameStyle::SnakeCase.matches_namespace("my_crate::Ui"));
+    assert!(!NameStyle::KebabCase.matches_namespace("my_crate::ui"));
+    assert!(NameStyle::KebabCase.matches_namespace("my-crate::ui"));
+}
+
+#[test]
+fn test_intern() {
+    let name = intern("interned");
+    assert_eq!(&*name, "interned");
+    assert!(Arc::ptr_eq(&name, &intern(&format!("inter{}", "ned"))));
+    assert!(!Arc::ptr_eq(&name, &intern("other")));
+}
+
+#[test]
+fn test_feature_map() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use featureflag::{context, feature::TypedFeature};
+
+    static PARSED: AtomicUsize = AtomicUsize::new(0);
+    static LIMIT: TypedFeature<u32> =
+        TypedFeature::new(featureflag::feature!("higher_limit", false), |enabled| {
+            PARSED.fetch_add(1, Ordering::Relaxed);
+            if enabled { 1000 } else { 100 }
+        });
+
+    let feature = Feature::new("higher_limit", false);
+    assert_eq!(feature.map(|enabled| enabled.then_some("on")), None);
+    assert_eq!(*LIMIT.get(), 100);
+
+    let evaluator = TestEvaluator::new();
+    evaluator.set_feature("higher_limit", true);
+    with_default(evaluator, || {
+        assert_eq!(feature.map(|enabled| enabled.then_some("on")), Some("on"));
+        assert_eq!(*LIMIT.get(), 1000);
+        assert_eq!(*LIMIT.get_in(Some(&context!(user_id = 1u64))), 1000);
+
+        let typed = feature.typed(|enabled| enabled.to_string());
+        assert_eq!(typed.get(), "true");
+        assert_eq!(typed.feature().name(), "higher_limit");
+    });
+
+    assert_eq!(*LIMIT.get(), 100);
+    assert_eq!(PARSED.load(Ordering::Relaxed), 2);
+}
@@ -0,0 +1,81 @@
+#![allow(missing_docs)]
+
+use featureflag::{
+    fields,
+    fields::{Fields, FieldsBuf},
+    value::ToValue,
+};
+
+#[test]
+fn test_get_path() {
+    check_get_path(fields!(
+        "user.id" = 1,
+        "user" = "alice",
+        "session.id" = "abc"
+    ));
+}
+
+fn check_get_path(fields: Fields<'_>) {
+    assert_eq!(fields.get_path("user.id").and_then(|v| v.as_i64()), Some(1));
+    assert_eq!(
+        fields.get_path("user").and_then(|v| v.as_str()),
+        Some("alice")
+    );
+    assert_eq!(
+        fields.get_path("session.id").and_then(|v| v.as_str()),
+        Some("abc")
+    );
+
+    assert!(fields.get_path("user.name").is_none());
+    assert!(fields.get_path("session").is_none());
+    assert!(fields.get_path("").is_none());
+}
+
+#[test]
+fn test_fields_buf() {
+    let mut buf = check_fields_buf(fields!(user = "alice", id = 1));
+    buf.insert("id", 2.to_value());
+    buf.insert("admin", true.to_value());
+    buf.insert("owned".to_string(), 3.to_value());
+
+    assert_eq!(buf.len(), 4);
+    assert_eq!(buf.get("owned").and_then(|v| v.as_i64()), Some(3));
+    assert_eq!(buf.get("id").and_then(|v| v.as_i64()), Some(2));
+    assert_eq!(buf.get("admin").and_then(|v| v.as_bool()), Some(true));
+    assert!(FieldsBuf::new().is_empty());
+}
+
+fn check_fields_buf(fields: Fields<'_>) -> FieldsBuf {
+    let buf = FieldsBuf::from(fields);
+    assert_eq!(buf.len(), 2);
+    assert_eq!(buf.get("user").and_then(|v| v.as_str()), Some("alice"));
+    assert_eq!(buf.get_path("id").and_then(|v| v.as_i64()), Some(1));
+    assert_eq!(
+        buf.pairs().map(|(k, _)| k).collect::<Vec<_>>(),
+        ["user", "id"]
+    );
+    buf
+}
+
+#[test]
+fn test_fields_buf_spill() {
+    let mut buf = FieldsBuf::from(fields!(a = 1, b = 2, c = 3, d = 4));
+    assert_eq!(buf.len(), 4);
+
+    buf.insert("e", 5.to_value());
+    buf.insert("a", 0.to_value());
+    let copy = buf.clone();
+    buf.insert("f", 6.to_value());
+
+    assert_eq!(
+        buf.pairs()
+            .map(|(k, v)| (k, v.as_i64().unwrap()))
+            .collect::<Vec<_>>(),
+        [("a", 0), ("b", 2), ("c", 3), ("d", 4), ("e", 5), ("f", 6)]
+    );
+    assert_eq!(copy.len(), 5);
+    assert_eq!(
+        format!("{copy:?}"),
+        r#"{"a": 0, "b": 2, "c": 3, "d": 4, "e": 5}"#
+    );
+}
@@ -0,0 +1,53 @@
+#![allow(missing_docs)]
+
+use std::{sync::mpsc, thread};
+
+use featureflag::{
+    Context, Feature, context,
+    evaluator::{set_default, set_global_default, set_thread_default, with_default},
+};
+use featureflag_test::TestEvaluator;
+
+#[test]
+fn test_global_default() {
+    let global = TestEvaluator::new();
+    global.set_feature("feature", true);
+    set_global_default(global);
+
+    let local = TestEvaluator::new();
+    local.set_feature("feature", false);
+
+    let feature = Feature::new("feature", false);
+    assert!(feature.is_enabled_in(None));
+    assert!(feature.is_enabled_in(Some(&Context::root())));
+
+    // local evaluators on other threads don't affect this thread
+    let (set_tx, set_rx) = mpsc::channel();
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    let other = thread::spawn({
+        let local = local.clone();
+        move || {
+            set_thread_default(local);
+            assert!(!Feature::new("feature", true).is_enabled_in(None));
+            set_tx.send(()).unwrap();
+            done_rx.recv().unwrap();
+        }
+    });
+    set_rx.recv().unwrap();
+    assert!(feature.is_enabled_in(None));
+
+    with_default(local.clone(), || {
+        assert!(!feature.is_enabled_in(None));
+        assert!(!feature.is_enabled_in(Some(&context!(user_id = 1u64))));
+    });
+    assert!(feature.is_enabled_in(None));
+
+    done_tx.send(()).unwrap();
+    other.join().unwrap();
+
+    let guard = set_default(local);
+    assert!(!feature.is_enabled_in(None));
+    drop(guard);
+    assert!(feature.is_enabled_in(None));
+    assert!(feature.evaluate_in(None).is_enabled());
+}
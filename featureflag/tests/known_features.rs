@@ -2,7 +2,11 @@
 
 use std::collections::HashSet;
 
-use featureflag::{Feature, feature::known_features};
+use featureflag::{
+    Feature,
+    feature::known_features,
+    registry::{self, registered_features},
+};
 
 #[allow(dead_code)]
 fn func() {
@@ -10,6 +14,9 @@ fn func() {
     featureflag::feature!("b", true);
     featureflag::is_enabled!("c", false);
     featureflag::is_enabled!("d", true);
+    featureflag::feature!(ns: module_path!(), "e", false);
+    featureflag::is_enabled!(ns: "ns", "f", false);
+    featureflag::feature!("g", !cfg!(test), owner = "checkout", ticket = "FF-1");
 
     Feature::new("dynamic1", false).is_enabled();
 }
@@ -20,10 +27,91 @@ fn test_known_features() {
 
     // these are all of the features that are used in the same program
     let expected = [
-        "a", "b", "c", "d", /* not expected: "dynamic1", "dynamic2" */
+        "a",
+        "b",
+        "c",
+        "d",
+        "known_features::e",
+        "ns::f",
+        "g",
+        // not expected: "dynamic1", "dynamic2"
     ]
     .into_iter()
     .collect::<HashSet<_>>();
 
-    assert_eq!(known_features(), &expected);
+    assert_eq!(known_features(), expected);
+}
+
+#[test]
+fn test_registered_features() {
+    let features = registered_features();
+    assert_eq!(features.len(), 7);
+
+    let names = features.iter().map(|f| f.name()).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["a", "b", "c", "d", "g", "known_features::e", "ns::f"]
+    );
+
+    let b = &features[1];
+    assert_eq!(b.default(), Some(true));
+    assert_eq!(b.module_path(), "known_features");
+    assert!(b.file().ends_with("known_features.rs"));
+    assert_eq!(b.line(), line_of("feature!(\"b\""));
+    assert!(b.metadata().is_empty());
+
+    let g = &features[4];
+    assert_eq!(g.default(), None);
+    assert_eq!(g.default_expr(), "!cfg!(test)");
+    assert_eq!(g.get("owner"), Some("checkout"));
+    assert_eq!(g.metadata(), [("owner", "checkout"), ("ticket", "FF-1")]);
+}
+
+#[test]
+fn test_export_registry() {
+    let json = registry::export_json();
+    assert!(json.starts_with("[\n  {\n    \"name\": \"a\",\n    \"default\": false,\n"));
+    assert!(json.contains(&format!(
+        concat!(
+            "    \"name\": \"g\",\n",
+            "    \"default\": null,\n",
+            "    \"default_expr\": \"!cfg!(test)\",\n",
+            "    \"module_path\": \"known_features\",\n",
+            "    \"file\": \"{}\",\n",
+            "    \"line\": {},\n",
+            "    \"metadata\": {{\n",
+            "      \"owner\": \"checkout\",\n",
+            "      \"ticket\": \"FF-1\"\n",
+            "    }}\n",
+        ),
+        file!(),
+        line_of("feature!(\"g\""),
+    )));
+    assert!(json.ends_with("  }\n]\n"));
+
+    let yaml = registry::export_yaml();
+    assert!(yaml.starts_with("- name: \"a\"\n  default: false\n"));
+    assert!(yaml.contains(&format!(
+        concat!(
+            "- name: \"g\"\n",
+            "  default: null\n",
+            "  default_expr: \"!cfg!(test)\"\n",
+            "  module_path: \"known_features\"\n",
+            "  file: \"{}\"\n",
+            "  line: {}\n",
+            "  metadata:\n",
+            "    \"owner\": \"checkout\"\n",
+            "    \"ticket\": \"FF-1\"\n",
+        ),
+        file!(),
+        line_of("feature!(\"g\""),
+    )));
+}
+
+fn line_of(pattern: &str) -> u32 {
+    let line = include_str!("known_features.rs")
+        .lines()
+        .position(|line| line.contains(pattern))
+        .unwrap();
+    line as u32 + 1
 }
@@ -0,0 +1,47 @@
+#![allow(missing_docs)]
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use featureflag::{
+    Context, Evaluator, Feature,
+    evaluator::{Lazy, RegistrationInfo, RegistrationKind, set_global_default},
+};
+
+struct Recording(Arc<Mutex<Vec<RegistrationInfo>>>);
+
+impl Evaluator for Recording {
+    fn is_enabled(&self, _feature: &str, _context: &Context) -> Option<bool> {
+        Some(true)
+    }
+
+    fn on_registration(&self, info: &RegistrationInfo) {
+        self.0.lock().unwrap().push(*info);
+    }
+}
+
+#[test]
+fn test_lazy_global_default() {
+    let built = Arc::new(AtomicUsize::new(0));
+    let registrations = Arc::new(Mutex::new(Vec::new()));
+
+    set_global_default(Lazy::new({
+        let built = built.clone();
+        let registrations = registrations.clone();
+        move || {
+            built.fetch_add(1, Ordering::Relaxed);
+            Recording(registrations)
+        }
+    }));
+    assert_eq!(built.load(Ordering::Relaxed), 0);
+
+    // the registration is passed on when the evaluator is built
+    assert!(Feature::new("feature", false).is_enabled_in(None));
+    assert_eq!(built.load(Ordering::Relaxed), 1);
+    assert_eq!(
+        *registrations.lock().unwrap(),
+        [RegistrationInfo::new(RegistrationKind::Global, 1)]
+    );
+}
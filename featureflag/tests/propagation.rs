@@ -0,0 +1,127 @@
+#![allow(missing_docs)]
+
+use std::{collections::HashMap, time::Duration};
+
+use featureflag::{
+    context,
+    evaluator::{NoEvaluator, with_default},
+    propagation::{Propagate, captured_fields, context_from_headers, extract, inject, snapshot},
+    value::Value,
+};
+
+#[test]
+fn test_inject_extract() {
+    with_default(Propagate::new(NoEvaluator), || {
+        let parent = context!(user_id = 42u64, name = "alice", admin = false);
+        let child = context!(
+            parent: parent,
+            name = "bob",
+            score = -1.5,
+            timeout = Duration::from_millis(1500),
+            raw = b"\x00\xff".as_slice(),
+        );
+
+        let mut headers = HashMap::new();
+        inject(&child, &mut headers);
+        assert_eq!(headers.get("featureflag-user_id").unwrap(), "u:42");
+        assert_eq!(headers.get("featureflag-name").unwrap(), "s:bob");
+        assert_eq!(headers.get("featureflag-raw").unwrap(), "x:00ff");
+
+        headers.insert("unrelated".to_string(), "s:ignored".to_string());
+        headers.insert("featureflag-invalid".to_string(), "q:1".to_string());
+
+        let fields = extract(&headers);
+        assert_eq!(fields.len(), 6);
+        assert_eq!(fields.get("user_id"), Some(&Value::U64(42)));
+        assert_eq!(fields.get("name"), Some(&Value::Str("bob".into())));
+        assert_eq!(fields.get("admin"), Some(&Value::Bool(false)));
+        assert_eq!(fields.get("score"), Some(&Value::F64(-1.5)));
+        assert_eq!(
+            fields.get("timeout"),
+            Some(&Value::Duration(Duration::from_millis(1500)))
+        );
+        assert_eq!(fields.get("raw"), Some(&Value::Bytes(vec![0, 255].into())));
+
+        let restored = context_from_headers(None, &headers);
+        assert_eq!(
+            captured_fields(&restored).get("name"),
+            Some(&Value::Str("bob".into()))
+        );
+    });
+}
+
+#[test]
+fn test_encode_strings() {
+    with_default(Propagate::new(NoEvaluator), || {
+        let value = "a\r\nb, c=d; é 😀 %20";
+        let mut headers = HashMap::new();
+        inject(&context!(name = value), &mut headers);
+
+        let header = headers.get("featureflag-name").unwrap();
+        assert_eq!(
+            header,
+            "s:a%0D%0Ab%2C%20c%3Dd%3B%20%C3%A9%20%F0%9F%98%80%20%2520"
+        );
+        assert_eq!(
+            extract(&headers).get("name"),
+            Some(&Value::Str(value.into()))
+        );
+
+        // invalid escapes and UTF-8 are ignored
+        for invalid in ["s:%", "s:%2", "s:%zz", "s:%+1", "s:%FF"] {
+            headers.insert("featureflag-name".to_string(), invalid.to_string());
+            assert_eq!(extract(&headers).get("name"), None, "{invalid}");
+        }
+    });
+}
+
+#[test]
+fn test_with_fields() {
+    with_default(Propagate::new(NoEvaluator).with_fields(["user_id"]), || {
+        let mut headers = Vec::new();
+        inject(&context!(user_id = 1, session = "abc"), &mut headers);
+        assert_eq!(
+            headers,
+            [("featureflag-user_id".to_string(), "i:1".to_string())]
+        );
+    });
+}
+
+#[test]
+fn test_snapshot() {
+    with_default(Propagate::new(NoEvaluator), || {
+        let parent = context!(user_id = 42u64, name = "alice");
+        let child = context!(parent: parent, name = "bob");
+
+        let snapshot = snapshot(&child);
+        drop((parent, child.clone()));
+        assert_eq!(snapshot.id(), child.id());
+        assert_eq!(snapshot.fields().len(), 2);
+        assert_eq!(snapshot.get("user_id"), Some(&Value::U64(42)));
+        assert_eq!(snapshot.get("name"), Some(&Value::Str("bob".into())));
+
+        let snapshot = std::thread::spawn(move || snapshot).join().unwrap();
+        let restored = snapshot.to_context(None);
+        assert_ne!(restored.id(), snapshot.id());
+        assert_eq!(
+            captured_fields(&restored).get("name"),
+            Some(&Value::Str("bob".into()))
+        );
+    });
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_encode_json() {
+    with_default(Propagate::new(NoEvaluator), || {
+        let value = serde_json::json!({"tier": "pro", "seats": 5});
+        let mut headers = HashMap::new();
+        inject(&context!(plan = value.clone()), &mut headers);
+
+        assert!(headers.get("featureflag-plan").unwrap().starts_with("j:"));
+        assert_eq!(
+            extract(&headers).get("plan").and_then(|v| v.as_json()),
+            Some(&value)
+        );
+    });
+}
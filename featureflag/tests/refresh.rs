@@ -0,0 +1,64 @@
+#![allow(missing_docs)]
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+use featureflag::refresh::BackgroundRefresh;
+
+#[test]
+fn test_polling() {
+    let (tx, rx) = mpsc::channel();
+    let handle = BackgroundRefresh::new(Duration::from_secs(60))
+        .thread_name("refresh")
+        .spawn(move || {
+            tx.send(std::thread::current().name().map(String::from))
+                .unwrap();
+        });
+
+    let timeout = Duration::from_secs(10);
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap().as_deref(),
+        Some("refresh")
+    );
+
+    handle.refresh_now();
+    assert!(rx.recv_timeout(timeout).is_ok());
+
+    assert!(!handle.is_finished());
+    handle.shutdown().unwrap();
+    assert!(rx.recv().is_err());
+}
+
+#[test]
+fn test_streaming() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let handle = BackgroundRefresh::new(Duration::from_secs(60)).spawn_with({
+        let runs = runs.clone();
+        move |token| {
+            while !token.wait(Duration::from_millis(1)) {
+                runs.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    });
+
+    while runs.load(Ordering::Relaxed) < 3 {
+        std::thread::yield_now();
+    }
+    drop(handle);
+
+    let runs_after_drop = runs.load(Ordering::Relaxed);
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(runs.load(Ordering::Relaxed), runs_after_drop);
+}
+
+#[test]
+fn test_panic() {
+    let handle = BackgroundRefresh::new(Duration::from_secs(60)).spawn_with(|_| panic!("boom"));
+    assert!(handle.shutdown().is_err());
+}
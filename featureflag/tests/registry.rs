@@ -0,0 +1,45 @@
+#![allow(missing_docs)]
+
+use featureflag::registry::{FlagGroup, flag_groups};
+
+#[allow(dead_code)]
+fn func() {
+    featureflag::feature!("read_new_db", false, group = "orders_db", group_step = "2");
+    featureflag::feature!("write_new_db", false, group = "orders_db", group_step = "1");
+    featureflag::feature!("write_new_db", false, group = "orders_db", group_step = "1");
+    featureflag::feature!("drop_old_db", false, group = "orders_db");
+    featureflag::feature!("new_checkout", false, group = "checkout");
+    featureflag::feature!("ungrouped", false);
+}
+
+#[test]
+fn test_flag_groups() {
+    let groups = flag_groups();
+    assert_eq!(
+        groups,
+        [
+            FlagGroup::new("checkout", ["new_checkout"]),
+            FlagGroup::new("orders_db", ["write_new_db", "read_new_db", "drop_old_db"]),
+        ]
+    );
+
+    let orders = &groups[1];
+    assert_eq!(orders.name(), "orders_db");
+    assert_eq!(
+        orders.disable_order().collect::<Vec<_>>(),
+        ["drop_old_db", "read_new_db", "write_new_db"]
+    );
+}
+
+#[test]
+fn test_flag_group_consistency() {
+    let group = FlagGroup::new("migration", ["a", "b", "c", "a"]);
+    assert_eq!(group.enable_order().collect::<Vec<_>>(), ["a", "b", "c"]);
+
+    assert!(group.is_consistent(|_| false));
+    assert!(group.is_consistent(|feature| feature == "a"));
+    assert!(group.is_consistent(|feature| feature != "c"));
+    assert!(group.is_consistent(|_| true));
+    assert!(!group.is_consistent(|feature| feature == "b"));
+    assert!(!group.is_consistent(|feature| feature != "b"));
+}
@@ -0,0 +1,7 @@
+#![allow(missing_docs)]
+
+#[test]
+fn test_ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
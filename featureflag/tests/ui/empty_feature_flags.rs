@@ -0,0 +1,4 @@
+#[derive(featureflag::FeatureFlags)]
+enum Flags {}
+
+fn main() {}
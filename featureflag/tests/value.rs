@@ -0,0 +1,87 @@
+#![allow(missing_docs)]
+
+use std::time::Duration;
+
+use featureflag::value::{ToValue, Value};
+
+#[test]
+fn test_try_from_same_type() {
+    assert_eq!(bool::try_from(&true.to_value()), Ok(true));
+    assert_eq!(i64::try_from(&(-5i32).to_value()), Ok(-5));
+    assert_eq!(u64::try_from(&5u8.to_value()), Ok(5));
+    assert_eq!(f64::try_from(&1.5f64.to_value()), Ok(1.5));
+    assert_eq!(String::try_from(&"foo".to_value()), Ok("foo".to_string()));
+    assert_eq!(<&str>::try_from(&"foo".to_value()), Ok("foo"));
+}
+
+#[test]
+fn test_try_from_numeric() {
+    assert_eq!(i64::try_from(&Value::U64(5)), Ok(5));
+    assert_eq!(u64::try_from(&Value::I64(5)), Ok(5));
+    assert_eq!(i64::try_from(&Value::F64(-3.0)), Ok(-3));
+    assert_eq!(f64::try_from(&Value::I64(1 << 53)), Ok(9007199254740992.0));
+
+    let err = i64::try_from(&Value::U64(u64::MAX)).unwrap_err();
+    assert!(err.is_out_of_range());
+    assert_eq!(err.to_string(), "u64 value out of range for i64");
+
+    assert!(
+        u64::try_from(&Value::I64(-1))
+            .unwrap_err()
+            .is_out_of_range()
+    );
+    assert!(
+        i64::try_from(&Value::F64(0.5))
+            .unwrap_err()
+            .is_out_of_range()
+    );
+    assert!(i64::try_from(&Value::F64(9223372036854775808.0)).is_err());
+    assert!(f64::try_from(&Value::I64(i64::MAX)).is_err());
+    assert!(f64::try_from(&Value::U64((1 << 53) + 1)).is_err());
+}
+
+#[test]
+fn test_try_from_mismatch() {
+    let err = bool::try_from(&Value::Null).unwrap_err();
+    assert!(!err.is_out_of_range());
+    assert_eq!(err.expected(), "bool");
+    assert_eq!(err.found(), "null");
+    assert_eq!(err.to_string(), "expected bool, found null");
+
+    assert!(String::try_from(&Value::I64(1)).is_err());
+    assert!(i64::try_from(&"1".to_value()).is_err());
+}
+
+#[test]
+fn test_duration() {
+    let duration = Duration::from_millis(100);
+    let value = duration.to_value();
+
+    assert_eq!(value.as_duration(), Some(Duration::from_millis(100)));
+    assert_eq!(Duration::try_from(&value), Ok(Duration::from_millis(100)));
+    assert_eq!(
+        Duration::try_from(&Value::U64(100))
+            .unwrap_err()
+            .to_string(),
+        "expected duration, found u64"
+    );
+    assert_eq!(format!("{value:?}"), "100ms");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json() {
+    let json = serde_json::json!({"plan": {"tier": "pro", "seats": [3, 5]}});
+    let value = json.to_value();
+
+    assert!(value.as_json().is_some());
+    assert_eq!(value.json_path(""), value.as_json());
+    assert_eq!(
+        value.json_path("plan.tier"),
+        Some(&serde_json::json!("pro"))
+    );
+    assert_eq!(value.json_path("plan.seats.1"), Some(&serde_json::json!(5)));
+    assert_eq!(value.json_path("plan.seats.2"), None);
+    assert_eq!(value.json_path("plan.missing"), None);
+    assert_eq!(Value::Bool(true).json_path(""), None);
+}